@@ -0,0 +1,867 @@
+/// Module for the long-lived archive database shared across runs
+use std::path::PathBuf;
+
+use reqwest::Url;
+use sqlite::{Connection, State};
+
+use crate::clean_content;
+use crate::common::{FeedStatus, NewsItem, DEFAULT_ARCHIVE_FILE, DEFAULT_CONFIG_FOLDER_NAME};
+use crate::health::FeedRun;
+use crate::snapshot::{compress_snapshot, decompress_snapshot, snapshot_hash};
+use crate::storage::{migrate, Migration, UpsertOutcome};
+
+/// Query parameters that only track the origin of a visit and are removed
+/// from the links
+const TRACKING_PARAMETERS: [&str; 7] = ["fbclid", "gclid", "mc_cid", "mc_eid", "ref", "rss", "cmpid"];
+
+/// Prefix of the query parameters of the UTM family, all of them are removed
+const TRACKING_PREFIX: &str = "utm_";
+
+/// Name of the archive schema in the schema_version table
+pub const ARCHIVE_SCHEMA: &str = "archive";
+
+/// Migrations of the archive tables
+pub const ARCHIVE_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the archive_item, run and run_item tables",
+        sql: "CREATE TABLE IF NOT EXISTS archive_item (
+                link            TEXT PRIMARY KEY,
+                first_seen      TEXT NOT NULL,
+                last_seen       TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS run (
+                id              INTEGER PRIMARY KEY,
+                command         TEXT NOT NULL,
+                started_at      TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS run_item (
+                run_id          INTEGER NOT NULL REFERENCES run(id),
+                link            TEXT NOT NULL,
+                relevance       INTEGER,
+                seen_at         TEXT NOT NULL,
+                PRIMARY KEY (run_id, link)
+            )",
+    },
+    Migration {
+        version: 2,
+        description: "Add the starred and rating columns to archive_item",
+        sql: "ALTER TABLE archive_item ADD COLUMN starred INTEGER NOT NULL DEFAULT 0;
+            ALTER TABLE archive_item ADD COLUMN rating INTEGER",
+    },
+    Migration {
+        version: 3,
+        description: "Create the snapshot and snapshot_item tables",
+        sql: "CREATE TABLE IF NOT EXISTS snapshot (
+                hash            TEXT PRIMARY KEY,
+                compressed      BLOB NOT NULL,
+                size            INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS snapshot_item (
+                link            TEXT NOT NULL,
+                hash            TEXT NOT NULL REFERENCES snapshot(hash),
+                fetched_at      TEXT NOT NULL,
+                PRIMARY KEY (link, hash)
+            )",
+    },
+    Migration {
+        version: 4,
+        description: "Create the feed_status table",
+        sql: "CREATE TABLE IF NOT EXISTS feed_status (
+                run_id          INTEGER NOT NULL REFERENCES run(id),
+                url             TEXT NOT NULL,
+                channel         TEXT,
+                items           INTEGER NOT NULL,
+                error           TEXT,
+                http_status     INTEGER,
+                latency_ms      INTEGER,
+                bytes           INTEGER NOT NULL,
+                dropped_items   INTEGER NOT NULL,
+                content_items   INTEGER NOT NULL,
+                extracted_items INTEGER NOT NULL,
+                PRIMARY KEY (run_id, url)
+            )",
+    },
+];
+
+/// Struct that represents the archive database
+pub struct Archive {
+    connection: Connection,
+    snapshots: bool,
+}
+
+/// Struct that summarizes the items recorded in a run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveSummary {
+    pub new_items: usize,
+    pub seen_items: usize,
+    pub updated_items: usize,
+}
+
+/// Struct that represents the archive history of a link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ArchiveHistory {
+    pub first_seen: String,
+    pub last_seen: String,
+    pub runs: Vec<i64>,
+    pub relevance: Vec<(i64, u64)>,
+}
+
+/// Struct that represents the retention policy of the archive. Items are
+/// aged by the last time they were seen in a run, starred and rated items are
+/// never pruned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Days after which the clean content is dropped keeping the metadata
+    pub content_days: Option<u32>,
+    /// Days after which the items are deleted
+    pub delete_days: Option<u32>,
+}
+
+/// Struct that summarizes the effect of pruning the archive
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneSummary {
+    pub contents_dropped: usize,
+    pub items_deleted: usize,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+/// Struct that summarizes the reextraction of the stored snapshots
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReextractSummary {
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+/// Condition on archive_item rows that are protected from pruning
+const UNPROTECTED: &str = "starred = 0 AND rating IS NULL";
+
+/// Function that returns the default path of the archive database,
+/// ~/.hemeroteca/archive.db
+pub fn default_archive_path() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_default()
+        .join(DEFAULT_CONFIG_FOLDER_NAME)
+        .join(DEFAULT_ARCHIVE_FILE)
+}
+
+/// Function that returns the canonical form of a link: lowercase host, no
+/// fragment, no tracking parameters and no trailing slash
+///
+/// Example:
+/// ```
+/// use hemeroteca::archive::canonical_link;
+///
+/// let link = canonical_link("https://WWW.Acme.es/section/item.html?utm_source=rss&id=3#comments");
+/// assert_eq!(link, "https://www.acme.es/section/item.html?id=3");
+/// let link = canonical_link("https://www.acme.es/section/item/?ref=home");
+/// assert_eq!(link, "https://www.acme.es/section/item");
+/// ```
+pub fn canonical_link(link: &str) -> String {
+    match Url::parse(link.trim()) {
+        Ok(mut url) => {
+            url.set_fragment(None);
+            let parameters = url
+                .query_pairs()
+                .filter(|(key, _)| !key.starts_with(TRACKING_PREFIX) && !TRACKING_PARAMETERS.contains(&key.as_ref()))
+                .map(|(key, value)| (key.into_owned(), value.into_owned()))
+                .collect::<Vec<(String, String)>>();
+            if parameters.is_empty() {
+                url.set_query(None);
+            } else {
+                url.query_pairs_mut().clear().extend_pairs(parameters);
+            }
+            let mut link = url.to_string();
+            if url.query().is_none() && link.ends_with('/') && url.path() != "/" {
+                link.pop();
+            }
+            link
+        }
+        Err(_) => link.trim().to_string(),
+    }
+}
+
+impl Archive {
+    /// Function that opens (or creates) an archive database
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::archive::Archive;
+    ///
+    /// let archive = Archive::open(":memory:");
+    /// assert!(archive.is_ok());
+    /// ```
+    pub fn open(path: &str) -> sqlite::Result<Archive> {
+        let connection = sqlite::open(path)?;
+        Archive::create_tables(&connection)?;
+        Ok(Archive {
+            connection,
+            snapshots: false,
+        })
+    }
+
+    /// Function that opens the archive database at the default path creating
+    /// the folder if needed
+    pub fn open_default() -> Result<Archive, Box<dyn std::error::Error>> {
+        let path = default_archive_path();
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder)?;
+        }
+        Ok(Archive::open(path.to_str().ok_or("Invalid archive path")?)?)
+    }
+
+    /// Function that enables storing the raw HTML of the recorded NewsItems
+    /// as compressed snapshots
+    pub fn with_snapshots(mut self, snapshots: bool) -> Archive {
+        self.snapshots = snapshots;
+        self
+    }
+
+    /// Function that returns the connection to the archive database
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// Function that creates the tables of the archive or migrates them to
+    /// the latest schema
+    fn create_tables(connection: &Connection) -> sqlite::Result<()> {
+        NewsItem::create_table(connection)?;
+        migrate(connection, ARCHIVE_SCHEMA, ARCHIVE_MIGRATIONS)?;
+        Ok(())
+    }
+
+    /// Function that records the start of a run and returns its id
+    pub fn start_run(&self, command: &str) -> sqlite::Result<i64> {
+        let mut statement = self
+            .connection
+            .prepare("INSERT INTO run (command, started_at) VALUES (:command, :started_at) RETURNING id")?;
+        statement.bind(&[(":command", command), (":started_at", chrono::Utc::now().to_rfc3339().as_str())][..])?;
+        statement.next()?;
+        statement.read::<i64, _>(0)
+    }
+
+    /// Function that upserts the NewsItems seen in a run keyed by their
+    /// canonical link, updating the first/last seen timestamps and the
+    /// relevance of the items in the run
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::archive::Archive;
+    ///
+    /// let archive = Archive::open(":memory:").unwrap();
+    /// let news_item = NewsItem {
+    ///     link: "https://www.acme.es/item.html?utm_source=rss".to_string(),
+    ///     relevance: Some(10),
+    ///     ..Default::default()
+    /// };
+    /// let run_id = archive.start_run("dossier").unwrap();
+    /// let summary = archive.record_news_items(run_id, &[news_item.clone()]).unwrap();
+    /// assert_eq!(summary.new_items, 1);
+    /// let run_id = archive.start_run("dossier").unwrap();
+    /// let summary = archive.record_news_items(run_id, &[news_item]).unwrap();
+    /// assert_eq!(summary.seen_items, 1);
+    /// ```
+    pub fn record_news_items(&self, run_id: i64, news_items: &[NewsItem]) -> sqlite::Result<ArchiveSummary> {
+        self.connection.execute("BEGIN")?;
+        match self.record_news_items_in_transaction(run_id, news_items) {
+            Ok(summary) => {
+                self.connection.execute("COMMIT")?;
+                Ok(summary)
+            }
+            Err(err) => {
+                self.connection.execute("ROLLBACK")?;
+                Err(err)
+            }
+        }
+    }
+
+    fn record_news_items_in_transaction(&self, run_id: i64, news_items: &[NewsItem]) -> sqlite::Result<ArchiveSummary> {
+        let mut summary = ArchiveSummary::default();
+        let now = chrono::Utc::now().to_rfc3339();
+
+        for news_item in news_items {
+            let news_item = NewsItem {
+                link: canonical_link(&news_item.link),
+                ..news_item.clone()
+            };
+
+            match news_item.upsert(&self.connection)? {
+                UpsertOutcome::Inserted => summary.new_items += 1,
+                UpsertOutcome::Updated => {
+                    summary.seen_items += 1;
+                    summary.updated_items += 1;
+                }
+                UpsertOutcome::Unchanged => summary.seen_items += 1,
+            }
+
+            let mut statement = self.connection.prepare(
+                "INSERT INTO archive_item (link, first_seen, last_seen) VALUES (:link, :now, :now)
+                 ON CONFLICT(link) DO UPDATE SET last_seen = excluded.last_seen",
+            )?;
+            statement.bind(&[(":link", news_item.link.as_str()), (":now", now.as_str())][..])?;
+            statement.next()?;
+
+            let mut statement = self.connection.prepare(
+                "INSERT INTO run_item (run_id, link, relevance, seen_at) VALUES (:run_id, :link, :relevance, :now)
+                 ON CONFLICT(run_id, link) DO UPDATE SET relevance = excluded.relevance, seen_at = excluded.seen_at",
+            )?;
+            statement.bind((":run_id", run_id))?;
+            statement.bind((":link", news_item.link.as_str()))?;
+            statement.bind((":relevance", news_item.relevance.map(|r| r as i64)))?;
+            statement.bind((":now", now.as_str()))?;
+            statement.next()?;
+
+            if let (true, Some(html)) = (self.snapshots, &news_item.raw_html) {
+                self.store_snapshot(&news_item.link, html, &now)?;
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Function that records the status of the feeds fetched in a run
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::archive::Archive;
+    ///
+    /// let archive = Archive::open(":memory:").unwrap();
+    /// let status = FeedStatus {
+    ///     url: "https://www.acme.es/rss".to_string(),
+    ///     http_status: Some(404),
+    ///     error: Some("HTTP status 404 Not Found".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// let run_id = archive.start_run("dossier").unwrap();
+    /// archive.record_feed_statuses(run_id, &[status.clone()]).unwrap();
+    /// let history = archive.feed_history(10).unwrap();
+    /// assert_eq!(history[0].run_id, run_id);
+    /// assert_eq!(history[0].status, status);
+    /// ```
+    pub fn record_feed_statuses(&self, run_id: i64, feeds: &[FeedStatus]) -> sqlite::Result<()> {
+        self.connection.execute("BEGIN")?;
+        let result = feeds.iter().try_for_each(|feed| {
+            let mut statement = self.connection.prepare(
+                "INSERT OR REPLACE INTO feed_status (run_id, url, channel, items, error, http_status, latency_ms, bytes, dropped_items, content_items, extracted_items)
+                 VALUES (:run_id, :url, :channel, :items, :error, :http_status, :latency_ms, :bytes, :dropped_items, :content_items, :extracted_items)",
+            )?;
+            statement.bind((":run_id", run_id))?;
+            statement.bind((":url", feed.url.as_str()))?;
+            statement.bind((":channel", feed.channel.as_deref()))?;
+            statement.bind((":items", feed.items as i64))?;
+            statement.bind((":error", feed.error.as_deref()))?;
+            statement.bind((":http_status", feed.http_status.map(|status| status as i64)))?;
+            statement.bind((":latency_ms", feed.latency_ms.map(|latency| latency as i64)))?;
+            statement.bind((":bytes", feed.bytes as i64))?;
+            statement.bind((":dropped_items", feed.dropped_items as i64))?;
+            statement.bind((":content_items", feed.content_items as i64))?;
+            statement.bind((":extracted_items", feed.extracted_items as i64))?;
+            statement.next()?;
+            Ok(())
+        });
+        match result {
+            Ok(()) => self.connection.execute("COMMIT"),
+            Err(err) => {
+                self.connection.execute("ROLLBACK")?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Function that returns the status of the feeds in the latest runs that
+    /// fetched feeds, ordered by run
+    pub fn feed_history(&self, runs: usize) -> sqlite::Result<Vec<FeedRun>> {
+        let mut statement = self.connection.prepare(
+            "SELECT run.id, run.started_at, url, channel, items, error, http_status, latency_ms, bytes, dropped_items, content_items, extracted_items
+             FROM feed_status JOIN run ON run.id = feed_status.run_id
+             WHERE run_id IN (SELECT DISTINCT run_id FROM feed_status ORDER BY run_id DESC LIMIT :runs)
+             ORDER BY run_id, url",
+        )?;
+        statement.bind((":runs", runs as i64))?;
+        let mut history = Vec::new();
+        while let State::Row = statement.next()? {
+            history.push(FeedRun {
+                run_id: statement.read::<i64, _>(0)?,
+                started_at: statement.read::<String, _>(1)?,
+                status: FeedStatus {
+                    url: statement.read::<String, _>(2)?,
+                    channel: statement.read::<Option<String>, _>(3)?,
+                    items: statement.read::<i64, _>(4)? as usize,
+                    error: statement.read::<Option<String>, _>(5)?,
+                    http_status: statement.read::<Option<i64>, _>(6)?.map(|status| status as u16),
+                    latency_ms: statement.read::<Option<i64>, _>(7)?.map(|latency| latency as u64),
+                    bytes: statement.read::<i64, _>(8)? as usize,
+                    dropped_items: statement.read::<i64, _>(9)? as usize,
+                    content_items: statement.read::<i64, _>(10)? as usize,
+                    extracted_items: statement.read::<i64, _>(11)? as usize,
+                },
+            });
+        }
+        Ok(history)
+    }
+
+    /// Function that stores the raw HTML of a link once per distinct content
+    /// and returns its hash
+    fn store_snapshot(&self, link: &str, html: &str, fetched_at: &str) -> sqlite::Result<String> {
+        let hash = snapshot_hash(html);
+        let compressed = compress_snapshot(html).map_err(|err| sqlite::Error {
+            code: None,
+            message: Some(err.to_string()),
+        })?;
+
+        let mut statement = self
+            .connection
+            .prepare("INSERT OR IGNORE INTO snapshot (hash, compressed, size) VALUES (:hash, :compressed, :size)")?;
+        statement.bind((":hash", hash.as_str()))?;
+        statement.bind((":compressed", compressed.as_slice()))?;
+        statement.bind((":size", html.len() as i64))?;
+        statement.next()?;
+
+        let mut statement = self.connection.prepare(
+            "INSERT INTO snapshot_item (link, hash, fetched_at) VALUES (:link, :hash, :fetched_at)
+             ON CONFLICT(link, hash) DO UPDATE SET fetched_at = excluded.fetched_at",
+        )?;
+        statement.bind(&[(":link", link), (":hash", hash.as_str()), (":fetched_at", fetched_at)][..])?;
+        statement.next()?;
+
+        Ok(hash)
+    }
+
+    /// Function that returns the raw HTML of the latest snapshot of a link
+    pub fn latest_snapshot(&self, link: &str) -> sqlite::Result<Option<String>> {
+        let mut statement = self.connection.prepare(
+            "SELECT snapshot.compressed FROM snapshot_item JOIN snapshot ON snapshot.hash = snapshot_item.hash
+             WHERE snapshot_item.link = :link ORDER BY snapshot_item.fetched_at DESC LIMIT 1",
+        )?;
+        statement.bind((":link", canonical_link(link).as_str()))?;
+        match statement.next()? {
+            State::Row => {
+                let compressed = statement.read::<Vec<u8>, _>(0)?;
+                decompress_snapshot(&compressed).map(Some).map_err(|err| sqlite::Error {
+                    code: None,
+                    message: Some(err.to_string()),
+                })
+            }
+            State::Done => Ok(None),
+        }
+    }
+
+    /// Function that reruns the current extraction rules over the latest
+    /// snapshot of every archived link and updates the clean content of the
+    /// items, recording the changes as revisions
+    pub fn reextract(&self) -> sqlite::Result<ReextractSummary> {
+        let mut summary = ReextractSummary::default();
+
+        let mut statement = self.connection.prepare("SELECT DISTINCT link FROM snapshot_item ORDER BY link")?;
+        let mut links = Vec::new();
+        while let State::Row = statement.next()? {
+            links.push(statement.read::<String, _>(0)?);
+        }
+
+        for link in links {
+            let (Some(stored), Some(html)) = (NewsItem::query_by_link(&self.connection, &link)?, self.latest_snapshot(&link)?)
+            else {
+                continue;
+            };
+            match clean_content(&stored.channel, html) {
+                Ok(content) if !content.is_empty() => {
+                    let news_item = NewsItem {
+                        clean_content: Some(content),
+                        error: None,
+                        ..stored
+                    };
+                    match news_item.upsert(&self.connection)? {
+                        UpsertOutcome::Unchanged => summary.unchanged += 1,
+                        _ => summary.updated += 1,
+                    }
+                }
+                Ok(_) => {
+                    log::error!("Could not reextract the content of {}. ERROR: Empty content", link);
+                    summary.failed += 1;
+                }
+                Err(err) => {
+                    log::error!("Could not reextract the content of {}. ERROR: {:?}", link, err);
+                    summary.failed += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Function that stars or unstars a link in the archive and returns false
+    /// if the link is not archived
+    pub fn star(&self, link: &str, starred: bool) -> sqlite::Result<bool> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE archive_item SET starred = :starred WHERE link = :link")?;
+        statement.bind((":starred", starred as i64))?;
+        statement.bind((":link", canonical_link(link).as_str()))?;
+        statement.next()?;
+        Ok(self.connection.change_count() > 0)
+    }
+
+    /// Function that records the rating given to a link in the archive and
+    /// returns false if the link is not archived
+    pub fn rate(&self, link: &str, rating: u64) -> sqlite::Result<bool> {
+        let mut statement = self
+            .connection
+            .prepare("UPDATE archive_item SET rating = :rating WHERE link = :link")?;
+        statement.bind((":rating", rating as i64))?;
+        statement.bind((":link", canonical_link(link).as_str()))?;
+        statement.next()?;
+        Ok(self.connection.change_count() > 0)
+    }
+
+    /// Function that returns true if the link was recorded in a run
+    pub fn contains(&self, link: &str) -> sqlite::Result<bool> {
+        let mut statement = self.connection.prepare("SELECT 1 FROM archive_item WHERE link = :link")?;
+        statement.bind((":link", canonical_link(link).as_str()))?;
+        Ok(statement.next()? == State::Row)
+    }
+
+    /// Function that returns the size in bytes of the archive database
+    fn size(&self) -> sqlite::Result<u64> {
+        let mut statement = self
+            .connection
+            .prepare("SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()")?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>(0)? as u64)
+    }
+
+    /// Function that applies the retention policy to the archive: drops the
+    /// clean content of the items not seen in `content_days`, deletes the
+    /// items not seen in `delete_days` and compacts the database. A dry run
+    /// only returns what would be pruned
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::archive::{Archive, RetentionPolicy};
+    ///
+    /// let archive = Archive::open(":memory:").unwrap();
+    /// let policy = RetentionPolicy {
+    ///     content_days: Some(30),
+    ///     delete_days: Some(365),
+    /// };
+    /// let summary = archive.prune(&policy, true).unwrap();
+    /// assert_eq!(summary.items_deleted, 0);
+    /// ```
+    pub fn prune(&self, policy: &RetentionPolicy, dry_run: bool) -> sqlite::Result<PruneSummary> {
+        let mut summary = PruneSummary {
+            size_before: self.size()?,
+            ..Default::default()
+        };
+
+        self.connection.execute("BEGIN")?;
+        match self.prune_in_transaction(policy, &mut summary) {
+            // A dry run rolls back the changes it counted
+            Ok(_) if dry_run => self.connection.execute("ROLLBACK")?,
+            Ok(_) => self.connection.execute("COMMIT")?,
+            Err(err) => {
+                self.connection.execute("ROLLBACK")?;
+                return Err(err);
+            }
+        }
+
+        if !dry_run && (summary.contents_dropped > 0 || summary.items_deleted > 0) {
+            self.connection.execute("VACUUM")?;
+        }
+        summary.size_after = self.size()?;
+        Ok(summary)
+    }
+
+    fn prune_in_transaction(&self, policy: &RetentionPolicy, summary: &mut PruneSummary) -> sqlite::Result<()> {
+        let cutoff = |days: u32| (chrono::Utc::now() - chrono::Duration::days(days as i64)).to_rfc3339();
+
+        if let Some(days) = policy.delete_days {
+            {
+                let mut statement = self.connection.prepare(format!(
+                    "CREATE TEMP TABLE expired AS SELECT link FROM archive_item WHERE last_seen < :cutoff AND {}",
+                    UNPROTECTED
+                ))?;
+                statement.bind((":cutoff", cutoff(days).as_str()))?;
+                statement.next()?;
+            }
+            // The count of the last statement is the number of archived items deleted
+            self.connection.execute(
+                "DELETE FROM news_item WHERE link IN (SELECT link FROM expired);
+                 DELETE FROM news_item_revision WHERE link IN (SELECT link FROM expired);
+                 DELETE FROM run_item WHERE link IN (SELECT link FROM expired);
+                 DELETE FROM snapshot_item WHERE link IN (SELECT link FROM expired);
+                 DELETE FROM archive_item WHERE link IN (SELECT link FROM expired)",
+            )?;
+            summary.items_deleted = self.connection.change_count();
+            self.connection.execute(
+                "DROP TABLE expired;
+                 DELETE FROM snapshot WHERE hash NOT IN (SELECT hash FROM snapshot_item)",
+            )?;
+        }
+
+        // The items deleted above are not counted as dropped contents
+        if let Some(days) = policy.content_days {
+            let mut statement = self.connection.prepare(format!(
+                "UPDATE news_item SET clean_content = NULL
+                 WHERE clean_content IS NOT NULL AND clean_content != ''
+                   AND link IN (SELECT link FROM archive_item WHERE last_seen < :cutoff AND {})",
+                UNPROTECTED
+            ))?;
+            statement.bind((":cutoff", cutoff(days).as_str()))?;
+            statement.next()?;
+            summary.contents_dropped = self.connection.change_count();
+        }
+
+        Ok(())
+    }
+
+    /// Function that returns the history of a link in the archive
+    pub fn history(&self, link: &str) -> sqlite::Result<Option<ArchiveHistory>> {
+        let link = canonical_link(link);
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT first_seen, last_seen FROM archive_item WHERE link = :link")?;
+        statement.bind((":link", link.as_str()))?;
+        let (first_seen, last_seen) = match statement.next()? {
+            State::Row => (statement.read::<String, _>(0)?, statement.read::<String, _>(1)?),
+            State::Done => return Ok(None),
+        };
+
+        let mut statement = self
+            .connection
+            .prepare("SELECT run_id, relevance FROM run_item WHERE link = :link ORDER BY run_id")?;
+        statement.bind((":link", link.as_str()))?;
+        let mut runs = Vec::new();
+        let mut relevance = Vec::new();
+        while let State::Row = statement.next()? {
+            let run_id = statement.read::<i64, _>(0)?;
+            runs.push(run_id);
+            if let Some(value) = statement.read::<Option<i64>, _>(1)? {
+                relevance.push((run_id, value as u64));
+            }
+        }
+
+        Ok(Some(ArchiveHistory {
+            first_seen,
+            last_seen,
+            runs,
+            relevance,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn news_item(link: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: "Other".to_string(),
+            title: title.to_string(),
+            link: link.to_string(),
+            description: "Description".to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_canonical_link() {
+        assert_eq!(canonical_link("https://www.acme.es/"), "https://www.acme.es/");
+        assert_eq!(
+            canonical_link("https://www.acme.es/a.html#top"),
+            "https://www.acme.es/a.html"
+        );
+        assert_eq!(canonical_link("not a link "), "not a link");
+
+        // Only the exact tracking parameters are removed
+        assert_eq!(
+            canonical_link("https://www.acme.es/a.html?reference=7&rss=1&utm_campaign=x"),
+            "https://www.acme.es/a.html?reference=7"
+        );
+        assert_ne!(
+            canonical_link("https://www.acme.es/a.html?reference=7"),
+            canonical_link("https://www.acme.es/a.html?reference=8")
+        );
+        assert_eq!(
+            canonical_link("https://www.acme.es/a.html?refid=1&rssid=2&rss_section=home"),
+            "https://www.acme.es/a.html?refid=1&rssid=2&rss_section=home"
+        );
+    }
+
+    #[test]
+    fn test_record_news_items_across_runs() {
+        let archive = Archive::open(":memory:").unwrap();
+
+        let run_1 = archive.start_run("relevance").unwrap();
+        let summary = archive
+            .record_news_items(
+                run_1,
+                &[
+                    news_item("https://www.acme.es/a.html", "A", 10),
+                    news_item("https://www.acme.es/b.html", "B", 5),
+                ],
+            )
+            .unwrap();
+        assert_eq!(
+            summary,
+            ArchiveSummary {
+                new_items: 2,
+                seen_items: 0,
+                updated_items: 0
+            }
+        );
+        assert!(archive.contains("https://www.acme.es/a.html?utm_source=rss").unwrap());
+        assert!(!archive.contains("https://www.acme.es/c.html").unwrap());
+
+        let run_2 = archive.start_run("dossier").unwrap();
+        let mut item = news_item("https://www.acme.es/a.html?utm_medium=rss", "A (updated)", 20);
+        item.clean_content = Some("Content".to_string());
+        let summary = archive.record_news_items(run_2, &[item]).unwrap();
+        assert_eq!(
+            summary,
+            ArchiveSummary {
+                new_items: 0,
+                seen_items: 1,
+                updated_items: 1
+            }
+        );
+
+        let history = archive.history("https://www.acme.es/a.html").unwrap().unwrap();
+        assert_eq!(history.runs, vec![run_1, run_2]);
+        assert_eq!(history.relevance, vec![(run_1, 10), (run_2, 20)]);
+        assert!(history.first_seen <= history.last_seen);
+
+        let history = archive.history("https://www.acme.es/b.html").unwrap().unwrap();
+        assert_eq!(history.runs, vec![run_1]);
+        assert!(archive.history("https://www.acme.es/c.html").unwrap().is_none());
+
+        let items = NewsItem::query_all(archive.connection()).unwrap();
+        assert_eq!(items.len(), 2);
+        let item = items.iter().find(|item| item.title == "A (updated)").unwrap();
+        assert_eq!(item.clean_content, Some("Content".to_string()));
+    }
+
+    // Ages an archived link as if it was last seen some days ago
+    fn age(archive: &Archive, link: &str, days: i64) {
+        let last_seen = (chrono::Utc::now() - chrono::Duration::days(days)).to_rfc3339();
+        let mut statement = archive
+            .connection()
+            .prepare("UPDATE archive_item SET last_seen = :last_seen WHERE link = :link")
+            .unwrap();
+        statement.bind(&[(":last_seen", last_seen.as_str()), (":link", link)][..]).unwrap();
+        statement.next().unwrap();
+    }
+
+    #[test]
+    fn test_prune_archive() {
+        let archive = Archive::open(":memory:").unwrap();
+        let run_id = archive.start_run("dossier").unwrap();
+        let links = [
+            "https://www.acme.es/new.html",
+            "https://www.acme.es/old.html",
+            "https://www.acme.es/older.html",
+            "https://www.acme.es/starred.html",
+            "https://www.acme.es/rated.html",
+        ];
+        let items = links
+            .iter()
+            .map(|link| NewsItem {
+                clean_content: Some("Content".to_string()),
+                ..news_item(link, "Title", 1)
+            })
+            .collect::<Vec<NewsItem>>();
+        archive.record_news_items(run_id, &items).unwrap();
+        age(&archive, links[1], 60);
+        for link in &links[2..] {
+            age(&archive, link, 400);
+        }
+        assert!(archive.star(links[3], true).unwrap());
+        assert!(archive.rate(links[4], 5).unwrap());
+        assert!(!archive.star("https://www.acme.es/unknown.html", true).unwrap());
+
+        let policy = RetentionPolicy {
+            content_days: Some(30),
+            delete_days: Some(365),
+        };
+
+        // A dry run does not change anything
+        let summary = archive.prune(&policy, true).unwrap();
+        assert_eq!(summary.contents_dropped, 1);
+        assert_eq!(summary.items_deleted, 1);
+        assert_eq!(NewsItem::query_all(archive.connection()).unwrap().len(), 5);
+
+        let summary = archive.prune(&policy, false).unwrap();
+        assert_eq!(summary.contents_dropped, 1);
+        assert_eq!(summary.items_deleted, 1);
+
+        let items = NewsItem::query_all(archive.connection()).unwrap();
+        assert_eq!(items.len(), 4);
+        let content = |link: &str| {
+            items
+                .iter()
+                .find(|item| item.link == link)
+                .map(|item| item.clean_content.is_some())
+        };
+        assert_eq!(content(links[0]), Some(true));
+        assert_eq!(content(links[1]), Some(false));
+        assert_eq!(content(links[2]), None);
+        assert_eq!(content(links[3]), Some(true));
+        assert_eq!(content(links[4]), Some(true));
+        assert!(archive.history(links[2]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_snapshots_and_reextract() {
+        let archive = Archive::open(":memory:").unwrap().with_snapshots(true);
+        let html = "<html><body><p>Primer párrafo.</p><p>Segundo párrafo.</p></body></html>";
+        let item = NewsItem {
+            clean_content: Some("Old extraction".to_string()),
+            raw_html: Some(html.to_string()),
+            ..news_item("https://www.acme.es/a.html", "A", 1)
+        };
+
+        // The same content is stored once
+        for _ in 0..2 {
+            let run_id = archive.start_run("dossier").unwrap();
+            archive.record_news_items(run_id, std::slice::from_ref(&item)).unwrap();
+        }
+        let mut statement = archive.connection().prepare("SELECT COUNT(*) FROM snapshot").unwrap();
+        statement.next().unwrap();
+        assert_eq!(statement.read::<i64, _>(0).unwrap(), 1);
+        drop(statement);
+        assert_eq!(
+            archive.latest_snapshot("https://www.acme.es/a.html").unwrap(),
+            Some(html.to_string())
+        );
+
+        let summary = archive.reextract().unwrap();
+        assert_eq!(summary.updated, 1);
+        let stored = NewsItem::query_by_link(archive.connection(), "https://www.acme.es/a.html")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            stored.clean_content,
+            Some("Primer párrafo.\n\nSegundo párrafo.\n".to_string())
+        );
+        assert_eq!(NewsItem::revisions(archive.connection(), &stored.link).unwrap().len(), 1);
+
+        // Running it again with the same rules changes nothing
+        assert_eq!(archive.reextract().unwrap().unchanged, 1);
+    }
+
+    #[test]
+    fn test_snapshots_are_optional() {
+        let archive = Archive::open(":memory:").unwrap();
+        let item = NewsItem {
+            raw_html: Some("<p>Hola</p>".to_string()),
+            ..news_item("https://www.acme.es/a.html", "A", 1)
+        };
+        let run_id = archive.start_run("dossier").unwrap();
+        archive.record_news_items(run_id, &[item]).unwrap();
+        assert!(archive.latest_snapshot("https://www.acme.es/a.html").unwrap().is_none());
+    }
+}
@@ -1,6 +1,23 @@
 use std::path::Path;
 
 use hemeroteca::prelude::*;
+use hemeroteca::archive::{default_archive_path, RetentionPolicy};
+use hemeroteca::diff::{diff_reports, load_report_items, report_diff_to_json, report_diff_to_markdown};
+use hemeroteca::epub::generate_dossier_epub;
+use hemeroteca::health::{
+    feed_health, feed_health_to_json, feed_health_to_markdown, DEFAULT_FLAG_RUNS, DEFAULT_HEALTH_RUNS,
+};
+use hemeroteca::json::{generate_json_report, RunMetadata};
+use hemeroteca::html::generate_sectioned_dossier_html;
+use hemeroteca::mail::{dossier_message, sectioned_dossier_message, send_message, MailDelivery, MailOptions};
+use hemeroteca::section::{flatten_sections, section_news_items, Grouping, NewsSection, SectionOptions};
+use hemeroteca::publish::{publish_feed, FeedFormat, FeedOptions};
+use hemeroteca::export::{CsvColumn, CsvOptions, DEFAULT_CSV_COLUMNS};
+use hemeroteca::search::{search_results_to_json, search_results_to_markdown};
+use hemeroteca::warc;
+use hemeroteca::watchlist::{Alert, ALERTS_EXIT_CODE};
+
+use chrono::NaiveDate;
 
 use clap::{Parser, Subcommand, ValueEnum, CommandFactory};
 
@@ -9,19 +26,188 @@ use env_logger::Env;
 // OptInOperator enum
 #[derive(Debug, Clone, ValueEnum)]
 enum OptInOperator {
-    AND,
-    OR,
+    And,
+    Or,
 }
 
 impl OptInOperator {
     fn as_wrapper(&self) -> Operator {
         match self {
-            OptInOperator::AND => Operator::AND,
-            OptInOperator::OR => Operator::OR,
+            OptInOperator::And => Operator::AND,
+            OptInOperator::Or => Operator::OR,
         }
     }
 }
 
+// Grouping of the sections of the dossier
+#[derive(Debug, Clone, ValueEnum)]
+enum SectionBy {
+    /// A section per watchlist, used as interest profile
+    Topic,
+    Channel,
+    Category,
+    /// A section per cluster of items about the same story
+    Story,
+}
+
+impl SectionBy {
+    fn as_wrapper(&self) -> Grouping {
+        match self {
+            SectionBy::Topic => Grouping::Topic,
+            SectionBy::Channel => Grouping::Channel,
+            SectionBy::Category => Grouping::Category,
+            SectionBy::Story => Grouping::Story,
+        }
+    }
+}
+
+// Source of the news items of the dossier and relevance commands
+enum ItemSource {
+    /// Fetch the feeds and the articles
+    Feeds(Vec<String>),
+    /// Load the items stored in a report or archive database
+    Database(String),
+    /// Load the items recorded in a WARC file
+    Warc(String),
+}
+
+impl ItemSource {
+    fn is_offline(&self) -> bool {
+        !matches!(self, ItemSource::Feeds(_))
+    }
+
+    fn feed_urls(&self) -> &[String] {
+        match self {
+            ItemSource::Feeds(feed_urls) => feed_urls,
+            _ => &[],
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            ItemSource::Feeds(_) => "feeds".to_string(),
+            ItemSource::Database(db) => format!("database {}", db),
+            ItemSource::Warc(warc_file) => format!("warc {}", warc_file),
+        }
+    }
+}
+
+// Output format of the search results
+#[derive(Debug, Clone, ValueEnum)]
+enum SearchFormat {
+    Markdown,
+    Json,
+}
+
+// Output format of the report diff
+#[derive(Debug, Clone, ValueEnum)]
+enum DiffFormat {
+    Markdown,
+    Json,
+}
+
+// Output format of the feed health report
+#[derive(Debug, Clone, ValueEnum)]
+enum HealthFormat {
+    Markdown,
+    Json,
+}
+
+// Output format of the dossier
+#[derive(Debug, Clone, ValueEnum)]
+enum DossierFormat {
+    Markdown,
+    Html,
+    Epub,
+    /// Publish the items in a rolling RSS 2.0 feed of the root folder
+    Rss,
+    /// Publish the items in a rolling Atom feed of the root folder
+    Atom,
+    /// Schema-versioned JSON document with the run, the feeds and the ranking
+    Json,
+    /// Multipart MIME email with the Markdown and HTML dossiers
+    Eml,
+}
+
+impl DossierFormat {
+    fn extension(&self) -> &str {
+        match self {
+            DossierFormat::Markdown => "md",
+            DossierFormat::Html => "html",
+            DossierFormat::Epub => "epub",
+            DossierFormat::Rss => "rss",
+            DossierFormat::Atom => "atom",
+            DossierFormat::Json => "json",
+            DossierFormat::Eml => "eml",
+        }
+    }
+
+    // Function that writes the dossier to a new file and returns the number of
+    // items written, the feeds keep the items of the previous runs
+    fn write(&self, news_items: &[NewsItem], sections: &[NewsSection], file: &Path, run: RunMetadata, feeds: &[FeedStatus], mail: &MailOptions) -> Result<usize, Box<dyn std::error::Error>> {
+        let report = match self {
+            DossierFormat::Markdown if sections.is_empty() => generate_dossier_report(news_items).into_bytes(),
+            DossierFormat::Markdown => generate_sectioned_dossier_report(sections).into_bytes(),
+            DossierFormat::Html if sections.is_empty() => generate_dossier_html(news_items).into_bytes(),
+            DossierFormat::Html => generate_sectioned_dossier_html(sections).into_bytes(),
+            DossierFormat::Epub => generate_dossier_epub(news_items)?,
+            DossierFormat::Rss => return publish_feed(news_items, file, FeedFormat::Rss, &FeedOptions::default()),
+            DossierFormat::Atom => return publish_feed(news_items, file, FeedFormat::Atom, &FeedOptions::default()),
+            DossierFormat::Json => generate_json_report(run, feeds, news_items)?.into_bytes(),
+            DossierFormat::Eml => mail_message(news_items, sections, mail)?.formatted(),
+        };
+        std::fs::write(file, report)?;
+        Ok(news_items.len())
+    }
+
+    fn is_feed(&self) -> bool {
+        matches!(self, DossierFormat::Rss | DossierFormat::Atom)
+    }
+}
+
+// Function that builds the dossier email, grouped in sections if there are any
+fn mail_message(news_items: &[NewsItem], sections: &[NewsSection], mail: &MailOptions) -> Result<lettre::Message, Box<dyn std::error::Error>> {
+    if sections.is_empty() {
+        dossier_message(news_items, mail)
+    } else {
+        sectioned_dossier_message(sections, mail)
+    }
+}
+
+// Output format of the relevance report
+#[derive(Debug, Clone, ValueEnum)]
+enum ReportFormat {
+    Markdown,
+    Html,
+    /// Schema-versioned JSON document with the run, the feeds and the ranking
+    Json,
+}
+
+impl ReportFormat {
+    fn extension(&self) -> &str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+            ReportFormat::Json => "json",
+        }
+    }
+
+    fn relevance(&self, news_items: &[NewsItem], run: RunMetadata, feeds: &[FeedStatus]) -> Result<String, Box<dyn std::error::Error>> {
+        match self {
+            ReportFormat::Markdown => Ok(generate_relevance_report(news_items)),
+            ReportFormat::Html => Ok(generate_relevance_html(news_items)),
+            ReportFormat::Json => Ok(generate_json_report(run, feeds, news_items)?),
+        }
+    }
+}
+
+// Format of the exported and imported news items
+#[derive(Debug, Clone, ValueEnum)]
+enum ExportFormat {
+    Jsonl,
+    Csv,
+}
+
 // CLAP Arguments Parsing
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
@@ -34,7 +220,8 @@ struct Args {
     #[arg(short, long)]
     threads: Option<usize>,
 
-    /// List of categories or keywords to opt in
+    /// List of categories, keywords or entities to opt in. Each one is a query, e.g.
+    /// `(economía OR inflación) AND NOT deportes`, `title:ley*` or `channel:"el país"`
     #[arg(short, long)]
     opt_in: Vec<String>,
 
@@ -46,6 +233,26 @@ struct Args {
     #[arg(short, long, default_value = ".")]
     root: String,
 
+    /// JSON file with the watchlists to evaluate against the items new to the archive, defaults to ~/.hemeroteca/watchlists.json
+    #[arg(long)]
+    watchlists: Option<String>,
+
+    /// Record the news items of the run in the archive database ~/.hemeroteca/archive.db
+    #[arg(short, long)]
+    archive: bool,
+
+    /// Archive database to use instead of the default one, implies --archive
+    #[arg(long)]
+    archive_db: Option<String>,
+
+    /// Store the raw HTML of the fetched pages as compressed snapshots in the archive
+    #[arg(long)]
+    snapshots: bool,
+
+    /// Record every fetched feed and article in a WARC file of the run in the root folder
+    #[arg(long)]
+    warc: bool,
+
     // Subcommands
     #[command(subcommand)]
     command: Option<Commands>,
@@ -66,6 +273,46 @@ enum Commands {
         /// Log to database
         #[arg(short, long)]
         db: bool,
+
+        /// Regenerate the dossier offline from a report or archive database instead of the feeds
+        #[arg(long)]
+        from_db: Option<String>,
+
+        /// Regenerate the dossier offline from a WARC file recorded with --warc instead of the feeds
+        #[arg(long, conflicts_with = "from_db")]
+        from_warc: Option<String>,
+
+        /// Output format of the dossier
+        #[arg(long, default_value = "markdown")]
+        format: DossierFormat,
+
+        /// Group the dossier in sections
+        #[arg(long)]
+        sections: Option<SectionBy>,
+
+        /// Maximum number of items of each section
+        #[arg(long, default_value = "5", requires = "sections")]
+        section_top_k: usize,
+
+        /// Sender of the dossier email
+        #[arg(long)]
+        mail_from: Option<String>,
+
+        /// Recipient of the dossier email, it can be repeated
+        #[arg(long)]
+        mail_to: Vec<String>,
+
+        /// Subject of the dossier email [default: Dossier <report name>]
+        #[arg(long)]
+        mail_subject: Option<String>,
+
+        /// Send the dossier email through the SMTP server at host[:port]
+        #[arg(long)]
+        smtp: Option<String>,
+
+        /// Send the dossier email through sendmail or the given sendmail compatible binary
+        #[arg(long, num_args = 0..=1, conflicts_with = "smtp")]
+        sendmail: Option<Option<String>>,
     },
 
     // Relevance
@@ -73,6 +320,18 @@ enum Commands {
         /// Report name
         #[arg(short, long, default_value = "report")]
         report_name: String,
+
+        /// Rescore offline the items of a report or archive database instead of the feeds
+        #[arg(long)]
+        from_db: Option<String>,
+
+        /// Rescore offline the items of a WARC file recorded with --warc instead of the feeds
+        #[arg(long, conflicts_with = "from_db")]
+        from_warc: Option<String>,
+
+        /// Output format of the relevance report
+        #[arg(long, default_value = "markdown")]
+        format: ReportFormat,
     },
 
      // Feedback
@@ -85,6 +344,151 @@ enum Commands {
         #[arg(short, long, default_value = "feedback.csv")]
         file_name: String,
     },
+
+    /// It manages the archive database
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommands,
+    },
+
+    /// It reruns the current extraction rules over the snapshots stored in the archive
+    Reextract,
+
+    /// It reports on the feeds recorded in the archive database
+    Feeds {
+        #[command(subcommand)]
+        command: FeedsCommands,
+    },
+
+    /// It searches the stored news items, e.g. `inflación AND "banco central"` or `econom*`
+    Search {
+        /// Full-text query
+        query: String,
+
+        /// Database to search, defaults to the archive database ~/.hemeroteca/archive.db
+        #[arg(long)]
+        db: Option<String>,
+
+        /// Only items published on or after this date (YYYY-MM-DD)
+        #[arg(long)]
+        since: Option<NaiveDate>,
+
+        /// Only items published on or before this date (YYYY-MM-DD)
+        #[arg(long)]
+        until: Option<NaiveDate>,
+
+        /// Only items whose channel contains this text
+        #[arg(short, long)]
+        channel: Option<String>,
+
+        /// Maximum number of results, 0 for no limit
+        #[arg(short, long, default_value = "20")]
+        limit: usize,
+
+        /// Output format
+        #[arg(long, default_value = "markdown")]
+        format: SearchFormat,
+    },
+
+    /// It compares the news items of two report databases or JSON reports
+    Diff {
+        /// Old report database or JSON report
+        old: String,
+
+        /// New report database or JSON report
+        new: String,
+
+        /// Number of most relevant items of each report compared as the top-k
+        #[arg(short, long, default_value = "20")]
+        top_k: usize,
+
+        /// Output format
+        #[arg(long, default_value = "markdown")]
+        format: DiffFormat,
+    },
+
+    /// It exports the stored news items as JSON Lines or CSV
+    Export {
+        /// Database to export, defaults to the archive database ~/.hemeroteca/archive.db
+        #[arg(long)]
+        db: Option<String>,
+
+        /// Only export the items matching this query, e.g. `category:economía AND NOT title:fútbol`
+        #[arg(short, long)]
+        query: Option<String>,
+
+        /// Output file, defaults to the standard output
+        #[arg(short, long)]
+        output: Option<String>,
+
+        /// Output format
+        #[arg(long, default_value = "jsonl")]
+        format: ExportFormat,
+
+        /// CSV columns to write, e.g. `title,link,relevance`, defaults to all the fields
+        #[arg(long)]
+        columns: Option<String>,
+    },
+
+    /// It imports news items exported as JSON Lines or CSV into a database
+    Import {
+        /// File to import, `-` for the standard input
+        file: String,
+
+        /// Database to import into, defaults to the archive database ~/.hemeroteca/archive.db
+        #[arg(long)]
+        db: Option<String>,
+
+        /// Input format
+        #[arg(long, default_value = "jsonl")]
+        format: ExportFormat,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum ArchiveCommands {
+    /// It applies the retention policy to the archive and compacts it
+    Prune {
+        /// Drop the clean content of the items not seen in this number of days, keeping the metadata
+        #[arg(long, default_value = "90")]
+        content_days: u32,
+
+        /// Delete the items not seen in this number of days
+        #[arg(long, default_value = "365")]
+        delete_days: u32,
+
+        /// Only show what would be pruned
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// It stars an archived item so it is never pruned
+    Star {
+        /// Link of the item
+        link: String,
+
+        /// Remove the star instead
+        #[arg(long)]
+        unstar: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum FeedsCommands {
+    /// It shows the trends of the feeds and flags the ones broken or empty for several runs
+    Health {
+        /// Number of latest runs to show
+        #[arg(long, default_value_t = DEFAULT_HEALTH_RUNS)]
+        runs: usize,
+
+        /// Number of runs in a row a feed has to be broken or empty to be flagged
+        #[arg(long, default_value_t = DEFAULT_FLAG_RUNS)]
+        flag_runs: usize,
+
+        /// Output format
+        #[arg(long, default_value = "markdown")]
+        format: HealthFormat,
+    },
 }
 
 /// Main function
@@ -104,6 +508,57 @@ fn main() {
         std::process::exit(0);
     }
 
+    // The search only reads the database so it does not need the feeds
+    if let Some(Commands::Search {query, db, since, until, channel, limit, format}) = args.command {
+        let options = SearchOptions { since, until, channel, limit };
+        let db = db.unwrap_or_else(|| default_archive_path().to_string_lossy().to_string());
+        search_command(&db, &query, &options, &format);
+        return;
+    }
+
+    // The diff only reads the reports
+    if let Some(Commands::Diff {old, new, top_k, format}) = args.command {
+        diff_command(&old, &new, top_k, &format);
+        return;
+    }
+
+    // The export and import only use the database
+    if let Some(Commands::Export {db, query, output, format, columns}) = args.command {
+        let db = db.unwrap_or_else(|| default_archive_path().to_string_lossy().to_string());
+        let options = match columns.as_deref().map(CsvColumn::parse_list).transpose() {
+            Ok(columns) => CsvOptions {
+                columns: columns.unwrap_or(DEFAULT_CSV_COLUMNS.to_vec()),
+                ..Default::default()
+            },
+            Err(err) => {
+                log::error!("Could not parse the columns {:?}. ERROR: {}", columns, err);
+                return;
+            }
+        };
+        export_command(&db, query.as_deref(), output.as_deref(), &format, &options);
+        return;
+    }
+    if let Some(Commands::Import {file, db, format}) = args.command {
+        let db = db.unwrap_or_else(|| default_archive_path().to_string_lossy().to_string());
+        import_command(&file, &db, &format);
+        return;
+    }
+
+    // The archive commands only use the archive database
+    if let Some(Commands::Archive {..}) | Some(Commands::Reextract) | Some(Commands::Feeds {..}) = args.command {
+        let archive = match args.archive_db {
+            Some(archive_db) => Archive::open(&archive_db).map_err(|e| e.into()),
+            None => Archive::open_default(),
+        };
+        match (archive, args.command) {
+            (Ok(archive), Some(Commands::Archive {command})) => archive_command(&archive, command),
+            (Ok(archive), Some(Commands::Feeds {command})) => feeds_command(&archive, command),
+            (Ok(archive), _) => reextract_command(&archive),
+            (Err(err), _) => log::error!("Could not open the archive database. ERROR: {}", err),
+        }
+        return;
+    }
+
     // Get the root folder for the reports
     let root_folder = args.root;
 
@@ -113,12 +568,8 @@ fn main() {
     // If the number of threads is not provided, use the number of cores
     let max_threads = args.threads.unwrap_or(num_cpus::get() as usize);
 
-    // Get the categories to filter out in lowercase
-    let opt_in = args
-        .opt_in
-        .into_iter()
-        .map(|s| s.to_lowercase())
-        .collect::<Vec<String>>();
+    // Get the opt in queries, the terms are lowercased by the query parser
+    let opt_in = args.opt_in;
     log::info!("Filtering in: {:?}", opt_in);
 
     // If no opt_in is provided, print a warning and exit
@@ -126,25 +577,94 @@ fn main() {
         log::info!("No categories provided to filter out. Getting all the news items from feeds.");
     }
 
-    // Get the operator to use for filtering
+    // Get the operator to use for filtering and build the opt in query
     let operator = args.operator;
+    let query = match Query::from_opt_in(&opt_in, operator.as_wrapper()) {
+        Ok(query) => query,
+        Err(err) => {
+            log::error!("Could not parse the opt in query {:?}. ERROR: {}", opt_in, err);
+            return;
+        }
+    };
 
     // Start the timer
     let start = std::time::Instant::now();
 
+    // The commands regenerated from a database or a WARC file do not read the feeds
+    let (from_db, from_warc) = match &args.command {
+        Some(Commands::Dossier {from_db, from_warc, ..}) | Some(Commands::Relevance {from_db, from_warc, ..}) => {
+            (from_db.clone(), from_warc.clone())
+        }
+        _ => (None, None),
+    };
+
     // Read the feed urls from the file
-    let feed_urls = read_urls(&feeds_file);
-    log::info!("Reading feed urls from the file: {}", feeds_file);
+    let source = if let Some(db) = from_db {
+        ItemSource::Database(db)
+    } else if let Some(warc_file) = from_warc {
+        ItemSource::Warc(warc_file)
+    } else {
+        let feed_urls = read_urls(&feeds_file);
+        log::info!("Reading feed urls from the file: {}", feeds_file);
 
-    // If we could not read the urls from the file, print the error and return
-    if let Err(err) = feed_urls {
-        log::error!("Could not read the urls from {}. ERROR: {}", feeds_file, err);
-        return;
+        // If we could not read the urls from the file, print the error and return
+        if let Err(err) = feed_urls {
+            log::error!("Could not read the urls from {}. ERROR: {}", feeds_file, err);
+            return;
+        }
+
+        // Unwrap urls
+        let feed_urls = feed_urls.unwrap();
+        log::info!("Feed urls to read: {:?}", feed_urls);
+        ItemSource::Feeds(feed_urls)
+    };
+
+    // Record the fetched responses in a WARC file of the run
+    if args.warc && !source.is_offline() {
+        let current_date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
+        let warc_file = Path::new(&root_folder).join(format!("hemeroteca-{}.warc.gz", current_date));
+        if let Err(err) = std::fs::create_dir_all(&root_folder).map_err(|e| e.into()).and_then(|_| warc::start_recording(&warc_file)) {
+            log::error!("Could not create the WARC file {}. ERROR: {}", warc_file.display(), err);
+            return;
+        }
     }
 
-    // Unwrap urls
-    let feed_urls = feed_urls.unwrap();
-    log::info!("Feed urls to read: {:?}", feed_urls);
+    // Read the watchlists to evaluate against the news items
+    let watchlists = match args.watchlists {
+        Some(watchlists_file) => match read_watchlists(&watchlists_file) {
+            Ok(watchlists) => watchlists,
+            Err(err) => {
+                log::error!("Could not read the watchlists from {}. ERROR: {}", watchlists_file, err);
+                return;
+            }
+        },
+        None => read_default_watchlists(),
+    };
+    log::info!("Watchlists to evaluate: {}", watchlists.len());
+
+    // Open the archive database if requested
+    let archive = match (args.archive, args.archive_db) {
+        (_, Some(archive_db)) => Some(Archive::open(&archive_db).map_err(|e| e.into())),
+        (true, None) => Some(Archive::open_default()),
+        (false, None) => None,
+    };
+    let archive = match archive {
+        // Offline runs do not record the stored items again
+        Some(_) if source.is_offline() => {
+            log::warn!("The items loaded from the {} are not recorded in the archive", source.describe());
+            None
+        }
+        Some(Ok(archive)) => Some(archive.with_snapshots(args.snapshots)),
+        Some(Err(err)) => {
+            log::error!("Could not open the archive database. ERROR: {}", err);
+            return;
+        }
+        None => None,
+    };
+    let store = archive.as_ref().map(|archive| archive as &dyn NewsStore);
+
+    // Number of alerts raised by the watchlists
+    let mut alerts = 0;
 
     // Create a tokio runtime
     let rt = tokio::runtime::Builder::new_multi_thread()
@@ -155,29 +675,58 @@ fn main() {
     
     // Match the command
     match args.command {
-        Some(Commands::Dossier {report_name, log, db}) => {
+        Some(Commands::Dossier {report_name, log, db, format, sections, section_top_k, mail_from, mail_to, mail_subject, smtp, sendmail, ..}) => {
             log::info!("Generating dossier with the report name: {}", report_name);
-            rt.block_on( async {
-                generate_dossier_command(&root_folder, &feed_urls, &report_name, opt_in, operator.as_wrapper(), log, db).await;
+
+            // Sections of the dossier, the topics are the watchlists
+            let sections = sections.map(|sections| SectionOptions {
+                grouping: sections.as_wrapper(),
+                top_k: section_top_k,
+                topics: watchlists.clone(),
+            });
+            if sections.as_ref().is_some_and(|sections| sections.grouping == Grouping::Topic && sections.topics.is_empty()) {
+                log::warn!("There are no watchlists to use as topics of the sections");
+            }
+
+            // Headers and delivery of the dossier email
+            let mail = MailOptions {
+                from: mail_from.unwrap_or_else(|| MailOptions::default().from),
+                to: mail_to,
+                subject: mail_subject.unwrap_or_else(|| format!("Dossier {}", report_name)),
+            };
+            let delivery = match (smtp, sendmail) {
+                (Some(address), _) => match address.parse::<MailDelivery>() {
+                    Ok(delivery) => Some(delivery),
+                    Err(err) => {
+                        log::error!("Could not parse the SMTP server {}. ERROR: {}", address, err);
+                        return;
+                    }
+                },
+                (None, Some(command)) => Some(MailDelivery::Sendmail(command)),
+                (None, None) => None,
+            };
+
+            alerts = rt.block_on( async {
+                generate_dossier_command(&root_folder, &source, &report_name, query.as_ref(), log, db, &format, sections.as_ref(), &mail, delivery.as_ref(), &watchlists, store).await
             });
             let end: std::time::Duration = start.elapsed();
             log::info!("Time elapsed: {:?}", end);
         }
-        Some(Commands::Relevance {report_name}) => {
+        Some(Commands::Relevance {report_name, format, ..}) => {
             log::info!("Generating relevance with the report name: {}", report_name);
-            rt.block_on( async {
-                generate_relevance_command(&root_folder, &feed_urls, &report_name).await;
+            alerts = rt.block_on( async {
+                generate_relevance_command(&root_folder, &source, &report_name, &format, &watchlists, store).await
             });
             let end: std::time::Duration = start.elapsed();
             log::info!("Time elapsed: {:?}", end);
         }
         Some(Commands::Feedback {number, file_name}) => {
-            let number = usize::from_str_radix(&number, 10);
+            let number = number.parse::<usize>();
             // If the number could be parsed
             if let Ok(number) = number {
                 log::info!("Requesting feedback for {} items", number);
                 rt.block_on( async {
-                    request_feedback_command(&root_folder, &feed_urls, number, &file_name).await;
+                    request_feedback_command(&root_folder, source.feed_urls(), number, &file_name, store).await;
                 });
                 let end: std::time::Duration = start.elapsed();
                 log::info!("Time elapsed: {:?}", end);
@@ -185,11 +734,318 @@ fn main() {
                 log::error!("Could not parse the number of items to request feedback for! Exiting...");
             }
         }
-        None => {
+        Some(Commands::Search {..}) | Some(Commands::Diff {..}) | Some(Commands::Export {..}) | Some(Commands::Import {..}) | Some(Commands::Archive {..}) | Some(Commands::Reextract) | Some(Commands::Feeds {..}) | None => {
             log::error!("No subcommand provided! Exiting...");
         }
     }
-    
+
+    // Signal through the exit code that the watchlists raised alerts
+    if alerts > 0 {
+        log::warn!("Watchlists raised {} alerts", alerts);
+        std::process::exit(ALERTS_EXIT_CODE);
+    }
+}
+
+/// Function that implements the archive commands
+fn archive_command(archive: &Archive, command: ArchiveCommands) {
+    match command {
+        ArchiveCommands::Prune {content_days, delete_days, dry_run} => {
+            let policy = RetentionPolicy {
+                content_days: Some(content_days),
+                delete_days: Some(delete_days),
+            };
+            match archive.prune(&policy, dry_run) {
+                Ok(summary) => {
+                    let prefix = if dry_run { "[dry run] Would prune" } else { "Pruned" };
+                    println!(
+                        "{} -> contents dropped: {} items deleted: {} size: {} -> {} bytes",
+                        prefix,
+                        summary.contents_dropped,
+                        summary.items_deleted,
+                        summary.size_before,
+                        summary.size_after
+                    );
+                }
+                Err(err) => log::error!("Could not prune the archive. ERROR: {}", err),
+            }
+        }
+        ArchiveCommands::Star {link, unstar} => match archive.star(&link, !unstar) {
+            Ok(true) => log::info!("{} {}", if unstar { "Unstarred" } else { "Starred" }, link),
+            Ok(false) => log::error!("The link {} is not archived", link),
+            Err(err) => log::error!("Could not star {}. ERROR: {}", link, err),
+        },
+    }
+}
+
+/// Function that implements the feeds commands
+fn feeds_command(archive: &dyn NewsStore, command: FeedsCommands) {
+    match command {
+        FeedsCommands::Health {runs, flag_runs, format} => {
+            let history = match archive.feed_history(runs) {
+                Ok(history) => history,
+                Err(err) => {
+                    log::error!("Could not read the feed history. ERROR: {}", err);
+                    return;
+                }
+            };
+            if history.is_empty() {
+                log::warn!("There are no feeds recorded in the archive, run a dossier or relevance with --archive first");
+            }
+
+            let health = feed_health(&history, flag_runs);
+            match format {
+                HealthFormat::Markdown => println!("{}", feed_health_to_markdown(&health, flag_runs)),
+                HealthFormat::Json => match feed_health_to_json(&health) {
+                    Ok(json) => println!("{}", json),
+                    Err(err) => log::error!("Could not format the feed health as JSON. ERROR: {}", err),
+                },
+            }
+        }
+    }
+}
+
+/// Function that implements the reextract command
+fn reextract_command(archive: &Archive) {
+    match archive.reextract() {
+        Ok(summary) => log::info!(
+            "Reextracted items -> updated: {} unchanged: {} failed: {}",
+            summary.updated,
+            summary.unchanged,
+            summary.failed
+        ),
+        Err(err) => log::error!("Could not reextract the snapshots. ERROR: {}", err),
+    }
+}
+
+/// Function that searches a database and prints the results
+fn search_command(db: &str, query: &str, options: &SearchOptions, format: &SearchFormat) {
+    if !Path::new(db).exists() {
+        log::error!("The database {} does not exist", db);
+        return;
+    }
+
+    // Open the database migrating it so the full-text index exists
+    let conn = match sqlite::open(db) {
+        Ok(conn) => conn,
+        Err(err) => {
+            log::error!("Could not open the database {}. ERROR: {}", db, err);
+            return;
+        }
+    };
+    if let Err(err) = NewsItem::create_table(&conn) {
+        log::error!("Could not migrate the database {}. ERROR: {}", db, err);
+        return;
+    }
+
+    let results = match search_news_items(&conn, query, options) {
+        Ok(results) => results,
+        Err(err) => {
+            log::error!("Could not search for {:?}. ERROR: {}", query, err);
+            return;
+        }
+    };
+    log::info!("Found {} results for {:?}", results.len(), query);
+
+    match format {
+        SearchFormat::Markdown => println!("{}", search_results_to_markdown(query, &results)),
+        SearchFormat::Json => match search_results_to_json(&results) {
+            Ok(json) => println!("{}", json),
+            Err(err) => log::error!("Could not format the results as JSON. ERROR: {}", err),
+        },
+    }
+}
+
+/// Function that implements the diff command
+fn diff_command(old: &str, new: &str, top_k: usize, format: &DiffFormat) {
+    let (old_items, new_items) = match (load_report_items(old), load_report_items(new)) {
+        (Ok(old_items), Ok(new_items)) => (old_items, new_items),
+        (Err(err), _) | (_, Err(err)) => {
+            log::error!("Could not load the reports to compare. ERROR: {}", err);
+            return;
+        }
+    };
+    log::info!("Comparing {} items of {} with {} items of {}", old_items.len(), old, new_items.len(), new);
+
+    let diff = diff_reports(&old_items, &new_items, top_k);
+    match format {
+        DiffFormat::Markdown => println!("{}", report_diff_to_markdown(old, new, &diff)),
+        DiffFormat::Json => match report_diff_to_json(&diff) {
+            Ok(json) => println!("{}", json),
+            Err(err) => log::error!("Could not format the diff as JSON. ERROR: {}", err),
+        },
+    }
+}
+
+/// Function that implements the export command
+fn export_command(db: &str, query: Option<&str>, output: Option<&str>, format: &ExportFormat, options: &CsvOptions) {
+    let query = match query.map(Query::parse).transpose() {
+        Ok(query) => query,
+        Err(err) => {
+            log::error!("Could not parse the query {:?}. ERROR: {}", query, err);
+            return;
+        }
+    };
+    let news_items = match load_news_items_from_db(db, query.as_ref()) {
+        Ok(news_items) => news_items,
+        Err(err) => {
+            log::error!("Could not load the news items from {}. ERROR: {}", db, err);
+            return;
+        }
+    };
+
+    let writer: Box<dyn std::io::Write> = match output {
+        Some(output) => match std::fs::File::create(output) {
+            Ok(file) => Box::new(std::io::BufWriter::new(file)),
+            Err(err) => {
+                log::error!("Could not create the file {}. ERROR: {}", output, err);
+                return;
+            }
+        },
+        None => Box::new(std::io::stdout().lock()),
+    };
+    let written = match format {
+        ExportFormat::Jsonl => write_news_items_to_jsonl(&news_items, writer),
+        ExportFormat::Csv => write_news_items_to_csv(&news_items, writer, options),
+    };
+    match written {
+        Ok(count) => log::info!("Items exported: {}", count),
+        Err(err) => log::error!("Could not export the news items. ERROR: {}", err),
+    }
+}
+
+/// Function that implements the import command
+fn import_command(file: &str, db: &str, format: &ExportFormat) {
+    let reader: Box<dyn std::io::BufRead> = if file == "-" {
+        Box::new(std::io::stdin().lock())
+    } else {
+        match std::fs::File::open(file) {
+            Ok(file) => Box::new(std::io::BufReader::new(file)),
+            Err(err) => {
+                log::error!("Could not open the file {}. ERROR: {}", file, err);
+                return;
+            }
+        }
+    };
+    let news_items = match format {
+        ExportFormat::Jsonl => read_news_items_from_jsonl(reader),
+        ExportFormat::Csv => read_news_items_from_csv(reader),
+    };
+    let news_items = match news_items {
+        Ok(news_items) => news_items,
+        Err(err) => {
+            log::error!("Could not read the news items from {}. ERROR: {}", file, err);
+            return;
+        }
+    };
+
+    let store = match Archive::open(db) {
+        Ok(store) => store,
+        Err(err) => {
+            log::error!("Could not open the database {}. ERROR: {}", db, err);
+            return;
+        }
+    };
+
+    // Import in a run of its own so the links are canonical and the items
+    // are archived as the fetched ones
+    let run_id = start_archive_run(Some(&store), "import");
+    record_in_archive(Some(&store), run_id, &news_items);
+}
+
+/// Function that writes the alerts raised by the watchlists to a JSON file in
+/// the report folder and returns the number of alerts
+fn log_alerts_to_file(alerts: &[Alert], folder_path: &Path, report_name: &str, current_date: &str) -> usize {
+    if !alerts.is_empty() {
+        std::fs::create_dir_all(folder_path).expect("Could not create the report folder!");
+        let alerts_file = folder_path.join(format!("alerts-{}_{}.json", report_name, current_date));
+        log::info!("Logging {} alerts to file: {}", alerts.len(), alerts_file.to_str().unwrap());
+        if let Err(err) = write_alerts_to_file(alerts, alerts_file.to_str().unwrap()) {
+            log::error!("Failed to log alerts to file: {}", err);
+        }
+    }
+    alerts.len()
+}
+
+/// Function that records the news items of a run in the archive database
+fn record_in_archive(archive: Option<&dyn NewsStore>, run_id: Option<i64>, news_items: &[NewsItem]) {
+    if let (Some(archive), Some(run_id)) = (archive, run_id) {
+        match archive.record_run(run_id, news_items) {
+            Ok(summary) => log::info!(
+                "Archived items -> new: {} seen before: {} updated: {}",
+                summary.new_items,
+                summary.seen_items,
+                summary.updated_items
+            ),
+            Err(err) => log::error!(
+                "Could not record the news items in the archive. ERROR: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Function that returns the news items whose links were not recorded in the
+/// archive yet, or all of them when there is no archive
+fn new_news_items(archive: Option<&dyn NewsStore>, news_items: &[NewsItem]) -> Vec<NewsItem> {
+    let Some(archive) = archive else {
+        return news_items.to_vec();
+    };
+    news_items
+        .iter()
+        .filter(|news_item| match archive.contains(&news_item.link) {
+            Ok(contains) => !contains,
+            Err(err) => {
+                log::error!("Could not look up {} in the archive. ERROR: {}", news_item.link, err);
+                true
+            }
+        })
+        .cloned()
+        .collect()
+}
+
+/// Function that records the status of the feeds fetched in a run in the
+/// archive database
+fn record_feeds_in_archive(archive: Option<&dyn NewsStore>, run_id: Option<i64>, feeds: &[FeedStatus]) {
+    if let (Some(archive), Some(run_id), false) = (archive, run_id, feeds.is_empty()) {
+        match archive.record_feeds(run_id, feeds) {
+            Ok(()) => log::info!(
+                "Archived feeds -> read: {} failed: {}",
+                feeds.iter().filter(|feed| feed.is_ok()).count(),
+                feeds.iter().filter(|feed| !feed.is_ok()).count()
+            ),
+            Err(err) => log::error!("Could not record the feeds in the archive. ERROR: {}", err),
+        }
+    }
+}
+
+/// Function that reads the news items matching the query from the feeds or
+/// loads them from a database or WARC file, along with the status of the
+/// feeds fetched
+async fn read_news_items(source: &ItemSource, query: Option<&Query>) -> (Option<Vec<NewsItem>>, Vec<FeedStatus>) {
+    let (file, items) = match source {
+        ItemSource::Feeds(feed_urls) => return fetch_news_items_with_status(feed_urls, query).await,
+        ItemSource::Database(db) => (db, load_news_items_from_db(db, query)),
+        ItemSource::Warc(warc_file) => (warc_file, load_news_items_from_warc(warc_file, query)),
+    };
+    let items = match items {
+        Ok(items) if !items.is_empty() => Some(items),
+        Ok(_) => None,
+        Err(err) => {
+            log::error!("Could not load the news items from {}. ERROR: {}", file, err);
+            None
+        }
+    };
+    (items, Vec::new())
+}
+
+/// Function that starts a run in the archive database and returns its id
+fn start_archive_run(archive: Option<&dyn NewsStore>, command: &str) -> Option<i64> {
+    archive.and_then(|archive| {
+        archive
+            .start_run(command)
+            .map_err(|err| log::error!("Could not start the archive run. ERROR: {}", err))
+            .ok()
+    })
 }
 
 /// Function that implements the feedback command
@@ -198,9 +1054,10 @@ fn main() {
 /// - feed_urls: Vec<String> - The feed urls to read
 /// - number: String - The number of items to request feedback for
 /// - file_name: String - The name of the file to save the feedback as CSV
-async fn request_feedback_command(root_folder: &str, feed_urls: &[String], number: usize, file_name: &str) {
+/// - archive: Option<&dyn NewsStore> - The archive database to record the ratings in
+async fn request_feedback_command(root_folder: &str, feed_urls: &[String], number: usize, file_name: &str, archive: Option<&dyn NewsStore>) {
     // Vector to store the items read from the feeds
-    let items = fetch_news_items_opted_in(feed_urls, &vec![], Operator::OR).await;
+    let items = fetch_news_items_opted_in(feed_urls, &[], Operator::OR).await;
 
     // If there are items
     if let Some(items) = items {
@@ -219,25 +1076,47 @@ async fn request_feedback_command(root_folder: &str, feed_urls: &[String], numbe
         std::fs::create_dir_all(&folder_path).expect("Could not create the report folder!");
 
         // Create the feedback file name
-        let feedback_file = folder_path.join(&file_name);
+        let feedback_file = folder_path.join(file_name);
 
         // Take the first n items and for each item, request relevance feedback and return a new Vec<NewsItem> with the new relevance
         let feedback_items = items.iter().take(number).filter_map(|item| {
-            if let Some(relevance) = request_relevance_feedback(item) {
-                Some(NewsItem {
-                    relevance: Some(relevance),
-                    ..item.clone()
-                })
-            } else {
-                None
-            }
+            request_relevance_feedback(item).map(|relevance| NewsItem {
+                relevance: Some(relevance),
+                ..item.clone()
+            })
         }).collect::<Vec<NewsItem>>();
         
         // Write the feedback items to a CSV file
         log::info!("Writing records to file: {}", feedback_file.to_str().unwrap());
-        if let Err(err) = write_news_items_to_csv(&feedback_items, feedback_file.to_str().unwrap()) {
+        let options = CsvOptions {
+            columns: [&DEFAULT_CSV_COLUMNS[..], &[CsvColumn::FeedbackDate]].concat(),
+            // Feedback date with format: Sun, 01 Jan 2017 12:00:00 +0000
+            feedback_date: Some(chrono::Utc::now().to_rfc2822()),
+        };
+        let written = std::fs::File::create(&feedback_file)
+            .map_err(|err| err.into())
+            .and_then(|file| write_news_items_to_csv(&feedback_items, file, &options));
+        if let Err(err) = written {
             log::error!("Failed to write feedback to CSV file: {}", err);
         }
+
+        // Record the ratings in the archive so the rated items are never pruned,
+        // the items are recorded as read so the ratings do not replace their relevance
+        if let Some(archive) = archive {
+            let run_id = start_archive_run(Some(archive), "feedback");
+            let rated_items = items
+                .iter()
+                .take(number)
+                .filter(|item| feedback_items.iter().any(|rated| rated.link == item.link))
+                .cloned()
+                .collect::<Vec<NewsItem>>();
+            record_in_archive(Some(archive), run_id, &rated_items);
+            for item in feedback_items.iter() {
+                if let Err(err) = archive.rate(&item.link, item.relevance.unwrap_or_default()) {
+                    log::error!("Could not record the rating of {}. ERROR: {}", item.link, err);
+                }
+            }
+        }
     } else {
         log::error!("No news items found! Exiting...");
     }
@@ -259,7 +1138,7 @@ fn request_relevance_feedback(item: &NewsItem) -> Option<u64> {
     // Calculate the number of days since publication
     let days_since_publication = match &item.pub_date {
         Some(date) => {
-            let date = chrono::DateTime::parse_from_rfc2822(&date).expect("Could not parse the date");
+            let date = chrono::DateTime::parse_from_rfc2822(date).expect("Could not parse the date");
             let now = chrono::Local::now();
             let duration = now.signed_duration_since(date);
             duration.num_days()
@@ -296,7 +1175,7 @@ fn request_relevance_feedback(item: &NewsItem) -> Option<u64> {
         let relevance = relevance.trim().parse::<u64>();
         match relevance {
             Ok(relevance) => {
-                if relevance >= 1 && relevance <= 5 {
+                if (1..=5).contains(&relevance) {
                     break Some(relevance);
                 } else {
                     println!("Relevance feedback must be between 1 and 5!");
@@ -315,11 +1194,22 @@ fn request_relevance_feedback(item: &NewsItem) -> Option<u64> {
 /// Function that implements the relevance command
 /// Arguments:
 /// - root_folder: &String - The root folder for the reports
-/// - feed_urls: Vec<String> - The feed urls to read
+/// - source: &ItemSource - The feeds to read or the database or WARC file to load the items from
 /// - report_name: String - The name of the report
-async fn generate_relevance_command(root_folder: &String, feed_urls: &Vec<String>, report_name: &String) {
-    // Vector to store the items read from the feeds
-    let items = fetch_news_items_opted_in(feed_urls, &vec![], Operator::OR).await;
+/// - format: &ReportFormat - The output format of the relevance report
+/// - watchlists: &[Watchlist] - The watchlists to evaluate
+/// - archive: Option<&dyn NewsStore> - The archive database to record the items in
+///
+/// Returns the number of alerts raised by the watchlists
+async fn generate_relevance_command(root_folder: &str, source: &ItemSource, report_name: &str, format: &ReportFormat, watchlists: &[Watchlist], archive: Option<&dyn NewsStore>) -> usize {
+    // Start the run in the archive
+    let run_id = start_archive_run(archive, "relevance");
+
+    // Vector to store the items read from the feeds or the database
+    let (items, feeds) = read_news_items(source, None).await;
+
+    // Record the status of the feeds in the archive
+    record_feeds_in_archive(archive, run_id, &feeds);
 
     // Get the current date in the format YYYY-MM-DD-HH-MM-SS
     let current_date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
@@ -331,6 +1221,12 @@ async fn generate_relevance_command(root_folder: &String, feed_urls: &Vec<String
         // Update all the items with the calculated relevance
         let updated_items = update_news_items_with_relevance(&mut items).await.expect("Should not happen");
 
+        // Evaluate the watchlists against the items new to the archive
+        let alerts = evaluate_watchlists(watchlists, &new_news_items(archive, &updated_items));
+
+        // Record the items in the archive
+        record_in_archive(archive, run_id, &updated_items);
+
         // Create the report folder name
         let report_folder = format!("{}_{}", report_name, current_date);
 
@@ -341,57 +1237,95 @@ async fn generate_relevance_command(root_folder: &String, feed_urls: &Vec<String
         std::fs::create_dir_all(&folder_path).expect("Could not create the report folder!");
 
         // Create the report file name
-        let report_file = folder_path.join(format!("relevance-{}_{}.md", report_name, current_date));
+        let report_file = folder_path.join(format!("relevance-{}_{}.{}", report_name, current_date, format.extension()));
 
         // Logging to file
         log::info!("Logging to the report log file: {}", report_file.to_str().unwrap());
 
         // Generate the relevance report
-        let relevance_report = generate_relevance_report(&updated_items);
+        let run = RunMetadata::new("relevance", report_name, &source.describe());
+        let relevance_report = format.relevance(&updated_items, run, &feeds);
 
         // Log relevance report to output file
-        if let Err(err) = log_report_to_file(&relevance_report, report_file.to_str().unwrap()).await {
+        let written = match relevance_report {
+            Ok(report) => log_report_to_file(&report, report_file.to_str().unwrap()).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = written {
             log::error!("Failed to log relevance report to file: {}", err);
         }
+
+        // Log the alerts raised by the watchlists
+        log_alerts_to_file(&alerts, &folder_path, report_name, &current_date)
     } else {
         log::error!("No news items found! Exiting...");
+        0
     }
 }
 
 /// Function that implements the dossier command
 /// Arguments:
-/// - feed_urls: Vec<String> - The feed urls to read
+/// - source: &ItemSource - The feeds to read or the database or WARC file to load the items from
 /// - report_name: String - The name of the report
-/// - opt_in: Vec<String> - The categories to filter in
-/// - operator: Operator - The operator to use for filtering
+/// - query: Option<&Query> - The query to filter in
 /// - log: bool - Whether to log to file
 /// - db: bool - Whether to log to database
-async fn generate_dossier_command(root_folder: &String, feed_urls: &Vec<String>, report_name: &String, opt_in: Vec<String>, operator: Operator, log: bool, db: bool) {
-    // Vector to store the items read from the feeds
-    let items = fetch_news_items_opted_in(feed_urls, &opt_in, operator).await;
+/// - format: &DossierFormat - The output format of the dossier
+/// - sections: Option<&SectionOptions> - How to group the dossier in sections, if it is sectioned
+/// - mail: &MailOptions - The headers of the dossier email
+/// - delivery: Option<&MailDelivery> - How to send the dossier email, if it is sent
+/// - watchlists: &[Watchlist] - The watchlists to evaluate
+/// - archive: Option<&dyn NewsStore> - The archive database to record the items in
+///
+/// Returns the number of alerts raised by the watchlists
+#[allow(clippy::too_many_arguments)]
+async fn generate_dossier_command(root_folder: &str, source: &ItemSource, report_name: &str, query: Option<&Query>, log: bool, db: bool, format: &DossierFormat, sections: Option<&SectionOptions>, mail: &MailOptions, delivery: Option<&MailDelivery>, watchlists: &[Watchlist], archive: Option<&dyn NewsStore>) -> usize {
+    // Start the run in the archive
+    let run_id = start_archive_run(archive, "dossier");
+
+    // Vector to store the items read from the feeds or the database
+    let (items, mut feeds) = read_news_items(source, query).await;
 
     // if we could read the items from the feeds
-    if let Some(mut items) = items {
+    let alerts = if let Some(mut items) = items {
         log::info!("Items read from the feeds: {:?}", items.len());
 
+        // Evaluate the watchlists against all the items read that are new to the archive
+        let alerts = evaluate_watchlists(watchlists, &new_news_items(archive, &items));
+
+        // Get the current date in the format YYYY-MM-DD-HH-MM-SS
+        let current_date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
+
+        // Create the report folder name
+        let report_folder = format!("{}_{}", report_name, current_date);
+
+        // Define the folder path
+        let folder_path = Path::new(&root_folder).join(&report_folder);
+
         // Update all the items with the calculated relevance and return the top k items
         let mut top_k_items = update_news_items_with_relevance_top_k(&mut items, 100).await;
 
-        // Fill the news items with clean contents
-        let clean_news_items = fill_news_items_with_clean_contents(&mut top_k_items).await;
+        // Fill the news items with clean contents, the loaded items already have them
+        let clean_news_items = if source.is_offline() {
+            Some(top_k_items)
+        } else {
+            fill_news_items_with_clean_contents(&mut top_k_items).await
+        };
+
+        // Count the contents extracted per feed
+        for feed in feeds.iter_mut() {
+            feed.count_extractions(clean_news_items.as_deref().unwrap_or_default());
+        }
 
         // Write intermediate results to the file
         if let Some(mut clean_news_items) = clean_news_items {
             log::info!("Clean news items: {:?}", clean_news_items.len());
 
-            // Get the current date in the format YYYY-MM-DD-HH-MM-SS
-            let current_date = chrono::Local::now().format("%Y-%m-%d-%H-%M-%S").to_string();
+            // Extract the entities again now that the clean contents are present
+            update_news_items_with_entities(&mut clean_news_items);
 
-            // Create the report folder name
-            let report_folder = format!("{}_{}", report_name, current_date);
-
-            // Define the folder path
-            let folder_path = Path::new(&root_folder).join(&report_folder);
+            // Record the clean items in the archive
+            record_in_archive(archive, run_id, &clean_news_items);
 
             // Create the report folder
             std::fs::create_dir_all(&folder_path).expect("Could not create the report folder!");
@@ -417,17 +1351,37 @@ async fn generate_dossier_command(root_folder: &String, feed_urls: &Vec<String>,
                 log::info!("Logging to the report log database: {}", report_db_file.to_str().unwrap());
 
                 // Insert the news items into the database
-                let unique_inserted_items = log_news_items_to_db(&clean_news_items, report_db_file.to_str().unwrap()).await;
-                log::info!("Unique inserted items: {:?}", unique_inserted_items);
+                let summary = log_news_items_to_db(&clean_news_items, report_db_file.to_str().unwrap()).await;
+                log::info!(
+                    "Items inserted: {} updated: {} unchanged: {} failed: {}",
+                    summary.inserted,
+                    summary.updated,
+                    summary.unchanged,
+                    summary.failed
+                );
             }
 
             // Now that the contents are present and clean pdate again all the items with the calculated relevance 
-            // and return the top k items
-            let top_k_items = update_news_items_with_relevance_top_k(&mut clean_news_items, 20).await;
+            // and return the top k items, each section keeps its own top k items
+            let (top_k_items, sections) = match sections {
+                Some(options) => {
+                    let scored_items = update_news_items_with_relevance_top_k(&mut clean_news_items, usize::MAX).await;
+                    let sections = section_news_items(&scored_items, options);
+                    log::info!("Sections in the dossier: {}", sections.len());
+                    (flatten_sections(&sections), sections)
+                }
+                None => (update_news_items_with_relevance_top_k(&mut clean_news_items, 20).await, Vec::new()),
+            };
 
+            // Record the relevance of the top k items now that it includes the contents
+            record_in_archive(archive, run_id, &top_k_items);
             
-            // Create the dossier file name
-            let report_file = folder_path.join(format!("dossier-{}_{}.md", report_name, current_date));
+            // Create the dossier file name, the feeds roll across runs in the root folder
+            let report_file = if format.is_feed() {
+                Path::new(&root_folder).join(format!("dossier-{}.{}", report_name, format.extension()))
+            } else {
+                folder_path.join(format!("dossier-{}_{}.{}", report_name, current_date, format.extension()))
+            };
 
             // Generating dossier
             log::info!("Generating dossier: {}", report_file.to_str().unwrap());
@@ -435,17 +1389,32 @@ async fn generate_dossier_command(root_folder: &String, feed_urls: &Vec<String>,
             // // Generate the dossier with the top k items
             // generate_dossier(&top_k_items, report_file.to_str().unwrap());
 
-            // Generate the dossier report
-            let report = generate_dossier_report(&top_k_items);
+            // Generate the dossier report and write it to the output file
+            let run = RunMetadata::new("dossier", report_name, &source.describe());
+            match format.write(&top_k_items, &sections, &report_file, run, &feeds, mail) {
+                Ok(count) => log::info!("Items in the dossier: {}", count),
+                Err(err) => log::error!("Failed to write the dossier to file: {}", err),
+            }
 
-            // Log report to output file
-            if let Err(err) = log_report_to_file(&report, report_file.to_str().unwrap()).await {
-                log::error!("Failed to log relevance report to file: {}", err);
+            // Send the dossier email if requested
+            if let Some(delivery) = delivery {
+                match mail_message(&top_k_items, &sections, mail).and_then(|message| send_message(&message, delivery)) {
+                    Ok(()) => log::info!("Dossier email sent to: {}", mail.to.join(", ")),
+                    Err(err) => log::error!("Failed to send the dossier email: {}", err),
+                }
             }
-            
         } else {
             log::error!("No news items survived the cleaning phase! Exiting...");
         }
-    }
+
+        // Log the alerts raised by the watchlists, even if no item survived the cleaning
+        log_alerts_to_file(&alerts, &folder_path, report_name, &current_date)
+    } else {
+        0
+    };
+
+    // Record the status of the feeds in the archive
+    record_feeds_in_archive(archive, run_id, &feeds);
+    alerts
 }
 
@@ -1,9 +1,17 @@
 /// Common types and utilities used across the library
 use std::{error::Error, str::FromStr};
 
+use serde::{Deserialize, Serialize};
+
+use crate::entities::Entity;
+
 /// Constants
 pub const DEFAULT_CONFIG_FOLDER_NAME: &str = ".hemeroteca";
 pub const DEFAULT_ROOT_WORDS_FILE: &str = "root_words.txt";
+pub const DEFAULT_GAZETTEERS_FOLDER_NAME: &str = "gazetteers";
+pub const DEFAULT_WATCHLISTS_FILE: &str = "watchlists.json";
+pub const DEFAULT_ARCHIVE_FILE: &str = "archive.db";
+pub const DEFAULT_TEMPLATES_FOLDER_NAME: &str = "templates";
 
 // OptInOperator enum
 #[derive(Debug, Clone)]
@@ -23,7 +31,7 @@ pub enum ChannelType {
 }
 
 /// Struct that represents a News Item
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct NewsItem {
     pub channel: String,
     pub title: String,
@@ -36,10 +44,92 @@ pub struct NewsItem {
     pub clean_content: Option<String>,
     pub error: Option<PipelineError>,
     pub relevance: Option<u64>,
+    #[serde(default)]
+    pub entities: Vec<Entity>,
+    /// Raw response body of the link, only kept in memory to store snapshots
+    #[serde(skip)]
+    pub raw_html: Option<String>,
+}
+
+/// Struct that represents the outcome of fetching a feed: the title of its
+/// channel and the number of items read, or the error if it could not be read,
+/// along with the statistics of the fetch and of the items of the feed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeedStatus {
+    pub url: String,
+    pub channel: Option<String>,
+    pub items: usize,
+    pub error: Option<String>,
+    /// HTTP status code of the response, if there was a response
+    #[serde(default)]
+    pub http_status: Option<u16>,
+    /// Milliseconds until the whole response was read or the fetch failed
+    #[serde(default)]
+    pub latency_ms: Option<u64>,
+    /// Size of the response body
+    #[serde(default)]
+    pub bytes: usize,
+    /// Items of the feed that could not be converted to NewsItems
+    #[serde(default)]
+    pub dropped_items: usize,
+    /// Items of the feed whose content was fetched
+    #[serde(default)]
+    pub content_items: usize,
+    /// Items of the feed whose clean content could be extracted
+    #[serde(default)]
+    pub extracted_items: usize,
+}
+
+impl FeedStatus {
+    /// Function that returns whether the feed could be read
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Function that returns the number of items of the feed converted to
+    /// NewsItems
+    pub fn usable_items(&self) -> usize {
+        self.items.saturating_sub(self.dropped_items)
+    }
+
+    /// Function that returns the ratio of items whose clean content could be
+    /// extracted, None if no content was fetched
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    ///
+    /// let status = FeedStatus {
+    ///     content_items: 4,
+    ///     extracted_items: 3,
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(status.extraction_rate(), Some(0.75));
+    /// assert_eq!(FeedStatus::default().extraction_rate(), None);
+    /// ```
+    pub fn extraction_rate(&self) -> Option<f64> {
+        if self.content_items == 0 {
+            None
+        } else {
+            Some(self.extracted_items as f64 / self.content_items as f64)
+        }
+    }
+
+    /// Function that counts the items of the feed whose content was fetched
+    /// and the ones whose clean content could be extracted
+    pub fn count_extractions(&mut self, news_items: &[NewsItem]) {
+        let channel = match &self.channel {
+            Some(channel) => channel,
+            None => return,
+        };
+        let items = news_items.iter().filter(|item| &item.channel == channel);
+        self.content_items = items.clone().count();
+        self.extracted_items = items.filter(|item| item.error.is_none() && item.clean_content.is_some()).count();
+    }
 }
 
 // Define a custom error type for the pipeline
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub enum PipelineError {
     EmptyString,
     ParsingError(String),
@@ -60,19 +150,15 @@ impl FromStr for PipelineError {
         // - NetworkError(.*) => NetworkError
         // - EmptyString
         // - NoContent
-        let re = regex::Regex::new(r"^(ParsingError\((.*)\)|NetworkError\((.*)\)|EmptyString|NoContent)$")?;
+        // - UnknownError
+        let re = regex::Regex::new(r"^(?s)(ParsingError\((.*)\)|NetworkError\((.*)\)|EmptyString|NoContent|UnknownError)$")?;
         let caps = re.captures(error).ok_or("No match")?;
-        match caps.get(1).map(|m| m.as_str()) {
-            Some("EmptyString") => Ok(PipelineError::EmptyString),
-            Some("NoContent") => Ok(PipelineError::NoContent),
-            Some("ParsingError") => {
-                let msg = caps.get(2).ok_or("No match")?.as_str().to_string();
-                Ok(PipelineError::ParsingError(msg))
-            }
-            Some("NetworkError") => {
-                let msg = caps.get(3).ok_or("No match")?.as_str().to_string();
-                Ok(PipelineError::NetworkError(msg))
-            }
+        match (caps.get(2), caps.get(3), caps.get(1).map(|m| m.as_str())) {
+            (Some(msg), _, _) => Ok(PipelineError::ParsingError(msg.as_str().to_string())),
+            (_, Some(msg), _) => Ok(PipelineError::NetworkError(msg.as_str().to_string())),
+            (_, _, Some("EmptyString")) => Ok(PipelineError::EmptyString),
+            (_, _, Some("NoContent")) => Ok(PipelineError::NoContent),
+            (_, _, Some("UnknownError")) => Ok(PipelineError::UnknownError),
             _ => Err("No match".into()),
         }
     }
@@ -164,6 +250,8 @@ impl NewsItem {
             clean_content: None,
             error: None,
             relevance: None,
+            entities: Vec::new(),
+            raw_html: None,
         })
     }
 }
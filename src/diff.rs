@@ -0,0 +1,305 @@
+/// Module for comparing the news items of two report runs: the items that
+/// entered or left the top-k, the relevance changes, the edited headlines and
+/// the new stories. The items of both runs are matched by their canonical link
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::archive::canonical_link;
+use crate::common::NewsItem;
+use crate::json::JsonReport;
+use crate::load_news_items_from_db;
+
+/// Struct that represents a news item in the diff with its rank and relevance
+/// in each run, missing when the item is not in that run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiffItem {
+    pub channel: String,
+    pub title: String,
+    pub link: String,
+    pub old_rank: Option<usize>,
+    pub new_rank: Option<usize>,
+    pub old_relevance: Option<u64>,
+    pub new_relevance: Option<u64>,
+}
+
+/// Struct that represents a headline edited between the runs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HeadlineEdit {
+    pub channel: String,
+    pub link: String,
+    pub old_title: String,
+    pub new_title: String,
+}
+
+/// Struct that represents the differences between two report runs
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ReportDiff {
+    pub top_k: usize,
+    /// Items in the top-k of the new run that were not in the old one
+    pub entered: Vec<DiffItem>,
+    /// Items in the top-k of the old run that are not in the new one
+    pub left: Vec<DiffItem>,
+    /// Items of both runs whose relevance changed, by largest change
+    pub relevance_changes: Vec<DiffItem>,
+    pub edited_headlines: Vec<HeadlineEdit>,
+    /// Items of the new run that were not in the old one
+    pub new_stories: Vec<DiffItem>,
+}
+
+// Function that ranks the items by relevance and indexes them by canonical
+// link, the duplicated links keep their best rank
+fn rank(news_items: &[NewsItem]) -> (Vec<(String, &NewsItem)>, HashMap<String, usize>) {
+    let mut ranked = news_items.iter().collect::<Vec<&NewsItem>>();
+    ranked.sort_by_key(|item| std::cmp::Reverse(item.relevance));
+
+    let mut items = Vec::new();
+    let mut ranks = HashMap::new();
+    for item in ranked {
+        let link = canonical_link(&item.link);
+        if let Entry::Vacant(entry) = ranks.entry(link.clone()) {
+            items.push((link, item));
+            entry.insert(items.len());
+        }
+    }
+    (items, ranks)
+}
+
+/// Function that compares the NewsItems of an old and a new run taking the
+/// top_k most relevant of each one as their top-k
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::diff::diff_reports;
+///
+/// let item = |link: &str, relevance: u64| NewsItem {
+///     link: link.to_string(),
+///     relevance: Some(relevance),
+///     ..Default::default()
+/// };
+/// let old = vec![item("https://www.acme.es/1", 10), item("https://www.acme.es/2", 5)];
+/// let new = vec![item("https://www.acme.es/2", 20), item("https://www.acme.es/3", 1)];
+/// let diff = diff_reports(&old, &new, 1);
+/// assert_eq!(diff.entered[0].link, "https://www.acme.es/2");
+/// assert_eq!(diff.left[0].link, "https://www.acme.es/1");
+/// assert_eq!(diff.new_stories[0].link, "https://www.acme.es/3");
+/// ```
+pub fn diff_reports(old: &[NewsItem], new: &[NewsItem], top_k: usize) -> ReportDiff {
+    let (old_items, old_ranks) = rank(old);
+    let (new_items, new_ranks) = rank(new);
+    let old_by_link = old_items.iter().map(|(link, item)| (link.as_str(), *item)).collect::<HashMap<&str, &NewsItem>>();
+    let new_by_link = new_items.iter().map(|(link, item)| (link.as_str(), *item)).collect::<HashMap<&str, &NewsItem>>();
+
+    let diff_item = |link: &str, item: &NewsItem| DiffItem {
+        channel: item.channel.clone(),
+        title: item.title.clone(),
+        link: item.link.clone(),
+        old_rank: old_ranks.get(link).copied(),
+        new_rank: new_ranks.get(link).copied(),
+        old_relevance: old_by_link.get(link).and_then(|item| item.relevance),
+        new_relevance: new_by_link.get(link).and_then(|item| item.relevance),
+    };
+    let in_top_k = |rank: Option<&usize>| rank.is_some_and(|rank| *rank <= top_k);
+
+    let mut diff = ReportDiff {
+        top_k,
+        ..Default::default()
+    };
+    for (link, item) in new_items.iter() {
+        if !in_top_k(old_ranks.get(link)) && in_top_k(new_ranks.get(link)) {
+            diff.entered.push(diff_item(link, item));
+        }
+        match old_by_link.get(link.as_str()) {
+            Some(old_item) => {
+                if old_item.relevance != item.relevance {
+                    diff.relevance_changes.push(diff_item(link, item));
+                }
+                if old_item.title != item.title {
+                    diff.edited_headlines.push(HeadlineEdit {
+                        channel: item.channel.clone(),
+                        link: item.link.clone(),
+                        old_title: old_item.title.clone(),
+                        new_title: item.title.clone(),
+                    });
+                }
+            }
+            None => diff.new_stories.push(diff_item(link, item)),
+        }
+    }
+    for (link, item) in old_items.iter() {
+        if in_top_k(old_ranks.get(link)) && !in_top_k(new_ranks.get(link)) {
+            diff.left.push(diff_item(link, item));
+        }
+    }
+
+    // The largest relevance changes first
+    diff.relevance_changes.sort_by_key(|item| {
+        let change = item.new_relevance.unwrap_or_default() as i64 - item.old_relevance.unwrap_or_default() as i64;
+        std::cmp::Reverse(change.abs())
+    });
+    diff
+}
+
+/// Function that loads the NewsItems of a report run from a JSON report, when
+/// the file ends with `.json`, or from a report or archive database
+pub fn load_report_items(path: &str) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    if !Path::new(path).exists() {
+        return Err(format!("The report {} does not exist", path).into());
+    }
+    if path.ends_with(".json") {
+        let report: JsonReport = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        Ok(report.items.into_iter().map(|item| item.item).collect())
+    } else {
+        load_news_items_from_db(path, None)
+    }
+}
+
+// Function that formats the rank and relevance of an item in a run
+fn position(rank: Option<usize>, relevance: Option<u64>) -> String {
+    match rank {
+        Some(rank) => format!("#{} ({})", rank, relevance.unwrap_or_default()),
+        None => "-".to_string(),
+    }
+}
+
+/// Function that formats the report diff as Markdown
+pub fn report_diff_to_markdown(old: &str, new: &str, diff: &ReportDiff) -> String {
+    let mut report = format!("# Report diff\n\n- Old: {}\n- New: {}\n- Top-k: {}\n\n", old, new, diff.top_k);
+
+    let sections = [
+        ("Entered the top-k", &diff.entered),
+        ("Left the top-k", &diff.left),
+        ("Relevance changes", &diff.relevance_changes),
+        ("New stories", &diff.new_stories),
+    ];
+    for (name, items) in sections {
+        report.push_str(&format!("## {} ({})\n\n", name, items.len()));
+        for item in items.iter() {
+            report.push_str(&format!(
+                "- [{}]({}) | {} | {} → {}\n",
+                item.title,
+                item.link,
+                item.channel,
+                position(item.old_rank, item.old_relevance),
+                position(item.new_rank, item.new_relevance)
+            ));
+        }
+        if !items.is_empty() {
+            report.push('\n');
+        }
+    }
+
+    report.push_str(&format!("## Edited headlines ({})\n\n", diff.edited_headlines.len()));
+    for edit in diff.edited_headlines.iter() {
+        report.push_str(&format!("- [{}]({}) | {}\n  - was: {}\n", edit.new_title, edit.link, edit.channel, edit.old_title));
+    }
+    report
+}
+
+/// Function that formats the report diff as JSON
+pub fn report_diff_to_json(diff: &ReportDiff) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(diff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::json::{generate_json_report, RunMetadata};
+
+    fn news_item(title: &str, link: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: "El País".to_string(),
+            title: title.to_string(),
+            link: link.to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    fn runs() -> (Vec<NewsItem>, Vec<NewsItem>) {
+        let old = vec![
+            news_item("A", "https://www.acme.es/a", 30),
+            news_item("B", "https://www.acme.es/b", 20),
+            news_item("C", "https://www.acme.es/c", 10),
+            news_item("D", "https://www.acme.es/d", 5),
+        ];
+        let new = vec![
+            news_item("A", "https://www.acme.es/a?utm_source=rss", 30),
+            news_item("B editado", "https://www.acme.es/b", 20),
+            news_item("C", "https://www.acme.es/c", 1),
+            news_item("D", "https://www.acme.es/d", 25),
+            news_item("E", "https://www.acme.es/e", 2),
+        ];
+        (old, new)
+    }
+
+    #[test]
+    fn test_diff_reports() {
+        let (old, new) = runs();
+        let diff = diff_reports(&old, &new, 3);
+
+        // D entered the top-k and C left it
+        assert_eq!(diff.entered.len(), 1);
+        assert_eq!(diff.entered[0].title, "D");
+        assert_eq!((diff.entered[0].old_rank, diff.entered[0].new_rank), (Some(4), Some(2)));
+        assert_eq!(diff.left.len(), 1);
+        assert_eq!(diff.left[0].title, "C");
+        assert_eq!((diff.left[0].old_rank, diff.left[0].new_rank), (Some(3), Some(5)));
+
+        // The largest change first, A matches by its canonical link
+        let changes = diff.relevance_changes.iter().map(|item| item.title.as_str()).collect::<Vec<&str>>();
+        assert_eq!(changes, vec!["D", "C"]);
+
+        assert_eq!(diff.edited_headlines.len(), 1);
+        assert_eq!(diff.edited_headlines[0].old_title, "B");
+        assert_eq!(diff.edited_headlines[0].new_title, "B editado");
+
+        assert_eq!(diff.new_stories.len(), 1);
+        assert_eq!(diff.new_stories[0].title, "E");
+        assert_eq!(diff.new_stories[0].old_rank, None);
+
+        // Identical runs have no differences
+        let diff = diff_reports(&old, &old, 3);
+        assert_eq!(diff, ReportDiff { top_k: 3, ..Default::default() });
+    }
+
+    #[test]
+    fn test_report_diff_to_markdown() {
+        let (old, new) = runs();
+        let markdown = report_diff_to_markdown("old.db", "new.json", &diff_reports(&old, &new, 3));
+        assert!(markdown.contains("## Entered the top-k (1)\n\n- [D](https://www.acme.es/d) | El País | #4 (5) → #2 (25)\n"));
+        assert!(markdown.contains("## New stories (1)\n\n- [E](https://www.acme.es/e) | El País | - → #4 (2)\n"));
+        assert!(markdown.contains("- [B editado](https://www.acme.es/b) | El País\n  - was: B\n"));
+    }
+
+    #[test]
+    fn test_load_report_items() {
+        let (old, new) = runs();
+
+        // A report database
+        let db = ".test-diff-old.db";
+        let _ = std::fs::remove_file(db);
+        let conn = sqlite::open(db).unwrap();
+        NewsItem::create_table(&conn).unwrap();
+        for item in old.iter() {
+            item.insert(&conn).unwrap();
+        }
+        assert_eq!(load_report_items(db).unwrap().len(), 4);
+        std::fs::remove_file(db).unwrap();
+
+        // A JSON report
+        let json = ".test-diff-new.json";
+        let run = RunMetadata::new("dossier", "report", "feeds");
+        std::fs::write(json, generate_json_report(run, &[], &new).unwrap()).unwrap();
+        let items = load_report_items(json).unwrap();
+        assert_eq!(items.len(), 5);
+        assert_eq!(items[0].relevance, Some(30));
+        std::fs::remove_file(json).unwrap();
+
+        assert!(load_report_items(".test-diff-missing.db").is_err());
+    }
+}
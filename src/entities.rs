@@ -0,0 +1,391 @@
+/// Module for rule based named entity extraction
+use once_cell::sync::Lazy;
+
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fmt::Display;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{NewsItem, DEFAULT_CONFIG_FOLDER_NAME, DEFAULT_GAZETTEERS_FOLDER_NAME};
+
+/// Lowercase words allowed inside a capitalized span, e.g. "Banco de España"
+const CONNECTORS: [&str; 3] = ["de", "del", "of"];
+
+/// Capitalized words that usually start a sentence and are not part of an entity
+const DETERMINERS: [&str; 12] = ["El", "La", "Los", "Las", "Un", "Una", "Unos", "Unas", "The", "A", "An", "En"];
+
+#[rustfmt::skip]
+static DEFAULT_PLACES: Lazy<Vec<&'static str>> = Lazy::new(|| {
+    vec![
+        "España", "Francia", "Alemania", "Italia", "Portugal", "Reino Unido", "Estados Unidos", "China", "Rusia",
+        "Ucrania", "Israel", "Palestina", "Gaza", "México", "Argentina", "Venezuela", "Colombia", "Marruecos",
+        "Madrid", "Barcelona", "Bruselas", "París", "Londres", "Washington", "Moscú", "Pekín", "Kiev",
+        "Spain", "France", "Germany", "Italy", "United Kingdom", "United States", "Russia", "Ukraine", "Mexico",
+        "Brussels", "Paris", "London", "Moscow", "Beijing", "Kyiv",
+    ]
+});
+
+/// Enum that represents the different kinds of entities
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum EntityKind {
+    Person,
+    Organization,
+    Place,
+    Other,
+}
+
+impl EntityKind {
+    pub fn as_str(&self) -> &str {
+        match self {
+            EntityKind::Person => "Person",
+            EntityKind::Organization => "Organization",
+            EntityKind::Place => "Place",
+            EntityKind::Other => "Other",
+        }
+    }
+}
+
+impl FromStr for EntityKind {
+    type Err = Box<dyn Error>;
+
+    /// Function that returns an EntityKind from a &str
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "Person" => Ok(EntityKind::Person),
+            "Organization" => Ok(EntityKind::Organization),
+            "Place" => Ok(EntityKind::Place),
+            "Other" => Ok(EntityKind::Other),
+            _ => Err(format!("Unknown entity kind: {}", kind).into()),
+        }
+    }
+}
+
+/// Struct that represents an entity mentioned in a NewsItem
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    pub kind: EntityKind,
+}
+
+impl Display for Entity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.kind.as_str(), self.name)
+    }
+}
+
+impl FromStr for Entity {
+    type Err = Box<dyn Error>;
+
+    /// Function that returns an Entity from a &str with the format `Kind:Name`
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::entities::{Entity, EntityKind};
+    ///
+    /// let entity: Entity = "Person:Pedro Sánchez".parse().unwrap();
+    /// assert_eq!(entity.kind, EntityKind::Person);
+    /// assert_eq!(entity.name, "Pedro Sánchez");
+    /// ```
+    fn from_str(entity: &str) -> Result<Self, Self::Err> {
+        let (kind, name) = entity.split_once(':').ok_or("No kind separator")?;
+        Ok(Entity {
+            name: name.to_string(),
+            kind: EntityKind::from_str(kind)?,
+        })
+    }
+}
+
+/// Struct that holds the known names of people, organizations and places
+#[derive(Debug, Clone, Default)]
+pub struct Gazetteer {
+    // Lowercase name or alias -> (canonical name, kind)
+    entries: HashMap<String, (String, EntityKind)>,
+}
+
+impl Gazetteer {
+    /// Function that adds a name to the gazetteer. Aliases can be added after
+    /// the canonical name separated by `|`, e.g. `Pedro Sánchez|Sánchez`
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::entities::{EntityKind, Gazetteer};
+    ///
+    /// let mut gazetteer = Gazetteer::default();
+    /// gazetteer.add("Pedro Sánchez|Sánchez", EntityKind::Person);
+    /// assert_eq!(gazetteer.lookup("sánchez").unwrap().0, "Pedro Sánchez");
+    /// ```
+    pub fn add(&mut self, line: &str, kind: EntityKind) {
+        let mut names = line.split('|').map(|name| name.trim()).filter(|name| !name.is_empty());
+        if let Some(canonical) = names.next() {
+            self.entries.insert(canonical.to_lowercase(), (canonical.to_string(), kind));
+            for alias in names {
+                self.entries.insert(alias.to_lowercase(), (canonical.to_string(), kind));
+            }
+        }
+    }
+
+    /// Function that reads a gazetteer file, one name per line, ignoring empty
+    /// lines and lines starting with `#`
+    pub fn add_file(&mut self, file: &Path, kind: EntityKind) -> Result<(), Box<dyn Error>> {
+        let content = std::fs::read_to_string(file)?;
+        for line in content.lines() {
+            if !line.trim().is_empty() && !line.starts_with('#') {
+                self.add(line, kind);
+            }
+        }
+        Ok(())
+    }
+
+    /// Function that returns the canonical name and kind of a name if known
+    pub fn lookup(&self, name: &str) -> Option<&(String, EntityKind)> {
+        self.entries.get(&name.to_lowercase())
+    }
+
+    /// Function that returns the gazetteer built from the default places and
+    /// the files found in ~/.hemeroteca/gazetteers: `people.txt`,
+    /// `organizations.txt` and `places.txt`
+    pub fn load() -> Self {
+        let mut gazetteer = Gazetteer::default();
+        for place in DEFAULT_PLACES.iter() {
+            gazetteer.add(place, EntityKind::Place);
+        }
+
+        if let Some(home_dir) = dirs::home_dir() {
+            let folder = home_dir.join(DEFAULT_CONFIG_FOLDER_NAME).join(DEFAULT_GAZETTEERS_FOLDER_NAME);
+            for (file, kind) in [
+                ("people.txt", EntityKind::Person),
+                ("organizations.txt", EntityKind::Organization),
+                ("places.txt", EntityKind::Place),
+            ] {
+                let file = folder.join(file);
+                if file.exists() {
+                    if let Err(err) = gazetteer.add_file(&file, kind) {
+                        log::error!("Could not read the gazetteer {}. ERROR: {}", file.display(), err);
+                    }
+                }
+            }
+        }
+
+        gazetteer
+    }
+}
+
+/// Function that returns true if a word starts with an uppercase letter
+fn is_capitalized(word: &str) -> bool {
+    word.chars().next().map(|c| c.is_uppercase()).unwrap_or(false)
+}
+
+/// Function that splits a text into the maximal runs of capitalized words,
+/// allowing connectors such as "de" inside a run
+fn capitalized_spans(text: &str) -> Vec<Vec<&str>> {
+    let mut spans = Vec::new();
+
+    // Punctuation breaks a span, so split the text in segments first
+    for segment in text.split(|c: char| ".,;:!?()[]{}\"«»“”\n".contains(c)) {
+        let mut span: Vec<&str> = Vec::new();
+        for word in segment.split_whitespace() {
+            let word = word.trim_matches(|c: char| !c.is_alphanumeric());
+            if word.is_empty() {
+                continue;
+            }
+            if is_capitalized(word) || (!span.is_empty() && CONNECTORS.contains(&word)) {
+                span.push(word);
+            } else if !span.is_empty() {
+                spans.push(std::mem::take(&mut span));
+            }
+        }
+        if !span.is_empty() {
+            spans.push(span);
+        }
+    }
+
+    // Drop leading determiners and trailing connectors
+    spans
+        .into_iter()
+        .map(|mut span| {
+            while span.first().map(|w| DETERMINERS.contains(w)).unwrap_or(false) {
+                span.remove(0);
+            }
+            while span.last().map(|w| !is_capitalized(w)).unwrap_or(false) {
+                span.pop();
+            }
+            span
+        })
+        .filter(|span| !span.is_empty())
+        .collect()
+}
+
+/// Function that extracts the entities of a text. Capitalized spans (and the
+/// sub spans of them) found in the gazetteer get its kind, the remaining
+/// multi-word spans are returned as `EntityKind::Other`
+///
+/// Example:
+/// ```
+/// use hemeroteca::entities::{extract_entities, EntityKind, Gazetteer};
+///
+/// let mut gazetteer = Gazetteer::default();
+/// gazetteer.add("Pedro Sánchez", EntityKind::Person);
+/// gazetteer.add("Francia", EntityKind::Place);
+///
+/// let entities = extract_entities("Pedro Sánchez viaja a Francia con Ana María López", &gazetteer);
+/// assert_eq!(entities.len(), 3);
+/// assert_eq!(entities[0].name, "Ana María López");
+/// assert_eq!(entities[0].kind, EntityKind::Other);
+/// ```
+pub fn extract_entities(text: &str, gazetteer: &Gazetteer) -> Vec<Entity> {
+    let mut entities = Vec::new();
+
+    for span in capitalized_spans(text) {
+        let mut matched = false;
+        let mut start = 0;
+        // Look for the longest sub spans known by the gazetteer
+        while start < span.len() {
+            let found = (start + 1..=span.len())
+                .rev()
+                .find_map(|end| gazetteer.lookup(&span[start..end].join(" ")).map(|entry| (end, entry)));
+            if let Some((end, (name, kind))) = found {
+                entities.push(Entity {
+                    name: name.clone(),
+                    kind: *kind,
+                });
+                matched = true;
+                start = end;
+            } else {
+                start += 1;
+            }
+        }
+
+        // Unknown spans are only entities if they have more than one capitalized word
+        if !matched && span.iter().filter(|w| is_capitalized(w)).count() > 1 {
+            entities.push(Entity {
+                name: span.join(" "),
+                kind: EntityKind::Other,
+            });
+        }
+    }
+
+    entities.sort();
+    entities.dedup();
+    entities
+}
+
+impl NewsItem {
+    /// Function that extracts the entities from the title, description and
+    /// clean content of the NewsItem and stores them in the entities field
+    pub fn update_entities(&mut self, gazetteer: &Gazetteer) {
+        let description = html2text::config::plain()
+            .string_from_read(self.description.as_bytes(), 1000)
+            .unwrap_or_default();
+        let text = format!(
+            "{}\n{}\n{}",
+            self.title,
+            description,
+            self.clean_content.as_deref().unwrap_or("")
+        );
+        self.entities = extract_entities(&text, gazetteer);
+    }
+
+    /// Function that returns true if the NewsItem mentions an entity with the
+    /// given name (case insensitive)
+    pub fn has_entity(&self, name: &str) -> bool {
+        self.entities.iter().any(|entity| entity.name.to_lowercase() == name.to_lowercase())
+    }
+}
+
+/// Function that extracts the entities of a slice of NewsItems using the
+/// default gazetteer
+pub fn update_news_items_with_entities(news_items: &mut [NewsItem]) {
+    let gazetteer = Gazetteer::load();
+    for news_item in news_items.iter_mut() {
+        news_item.update_entities(&gazetteer);
+    }
+}
+
+/// Function that builds an index of the entities mentioned in a slice of
+/// NewsItems. For each entity it returns the positions of the items that
+/// mention it
+pub fn entity_index(news_items: &[NewsItem]) -> BTreeMap<Entity, Vec<usize>> {
+    let mut index: BTreeMap<Entity, Vec<usize>> = BTreeMap::new();
+    for (i, item) in news_items.iter().enumerate() {
+        for entity in item.entities.iter() {
+            index.entry(entity.clone()).or_default().push(i);
+        }
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_gazetteer() -> Gazetteer {
+        let mut gazetteer = Gazetteer::default();
+        gazetteer.add("Pedro Sánchez|Sánchez", EntityKind::Person);
+        gazetteer.add("Banco de España", EntityKind::Organization);
+        gazetteer.add("Repsol", EntityKind::Organization);
+        gazetteer.add("Francia", EntityKind::Place);
+        gazetteer
+    }
+
+    #[test]
+    fn test_capitalized_spans() {
+        let spans = capitalized_spans("El Banco de España avisa. La Comisión Europea responde a Francia");
+        assert_eq!(
+            spans,
+            vec![vec!["Banco", "de", "España"], vec!["Comisión", "Europea"], vec!["Francia"]]
+        );
+    }
+
+    #[test]
+    fn test_extract_entities_with_gazetteer() {
+        let gazetteer = test_gazetteer();
+        let entities = extract_entities("Sánchez se reúne con el Banco de España y Repsol en Francia", &gazetteer);
+        assert_eq!(
+            entities,
+            vec![
+                Entity {
+                    name: "Banco de España".to_string(),
+                    kind: EntityKind::Organization
+                },
+                Entity {
+                    name: "Francia".to_string(),
+                    kind: EntityKind::Place
+                },
+                Entity {
+                    name: "Pedro Sánchez".to_string(),
+                    kind: EntityKind::Person
+                },
+                Entity {
+                    name: "Repsol".to_string(),
+                    kind: EntityKind::Organization
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_entities_ignores_single_unknown_words() {
+        let gazetteer = test_gazetteer();
+        let entities = extract_entities("Hoy llueve. Mañana la Agencia Estatal de Meteorología avisa", &gazetteer);
+        assert_eq!(
+            entities,
+            vec![Entity {
+                name: "Agencia Estatal de Meteorología".to_string(),
+                kind: EntityKind::Other
+            }]
+        );
+    }
+
+    #[test]
+    fn test_entity_from_str() {
+        let entity = Entity {
+            name: "Banco de España".to_string(),
+            kind: EntityKind::Organization,
+        };
+        assert_eq!(Entity::from_str(&entity.to_string()).unwrap(), entity);
+        assert!(Entity::from_str("Banco de España").is_err());
+    }
+}
@@ -0,0 +1,267 @@
+/// Module for packaging the dossiers as EPUB 3 books, one chapter per news
+/// item, to read them on e-readers
+use std::error::Error;
+use std::io::{Cursor, Seek, Write};
+
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+use crate::common::NewsItem;
+use crate::snapshot::snapshot_hash;
+use crate::template::{ReportItem, ReportModel};
+
+const CONTAINER: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+  <rootfiles>
+    <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+  </rootfiles>
+</container>
+"#;
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; }\n\
+                          h1 { font-size: 1.4em; }\n\
+                          dl { font-size: 0.8em; color: #555; }\n\
+                          dt { font-weight: bold; }\n\
+                          dd { margin: 0 0 0.3em 0; }\n";
+
+/// Function that escapes the special XML characters of a text
+///
+/// Example:
+/// ```
+/// use hemeroteca::epub::escape_xml;
+///
+/// assert_eq!(escape_xml("<b>\"AT&T\"</b>"), "&lt;b&gt;&quot;AT&amp;T&quot;&lt;/b&gt;");
+/// ```
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            // Control characters are not allowed in XML 1.0
+            c if c.is_control() && c != '\n' && c != '\t' && c != '\r' => {}
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Function that returns the file name of the chapter of an item
+fn chapter_file(item: &ReportItem) -> String {
+    format!("chapter-{}.xhtml", item.position)
+}
+
+// Function that returns a XHTML document with a title and a body
+fn xhtml(title: &str, body: &str) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n\
+         <html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"es\" xml:lang=\"es\">\n\
+         <head>\n<meta charset=\"utf-8\"/>\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\"/>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_xml(title),
+        body
+    )
+}
+
+// Function that returns the navigation document with the table of contents
+fn navigation(model: &ReportModel) -> String {
+    let mut body = format!("<nav epub:type=\"toc\" id=\"toc\">\n<h1>{}</h1>\n<ol>\n", escape_xml(&model.title));
+    for item in model.items.iter() {
+        body.push_str(&format!(
+            "<li><a href=\"{}\">{}</a></li>\n",
+            chapter_file(item),
+            escape_xml(&item.title)
+        ));
+    }
+    body.push_str("</ol>\n</nav>\n");
+    xhtml(&model.title, &body)
+}
+
+// Function that returns the chapter of an item with its title, metadata and
+// clean content
+fn chapter(item: &ReportItem) -> String {
+    let mut body = format!("<h1>{}</h1>\n<dl>\n", escape_xml(&item.title));
+    let entities = item
+        .entities
+        .iter()
+        .map(|entity| format!("{} ({})", entity.name, entity.kind.as_str()))
+        .collect::<Vec<String>>()
+        .join(", ");
+    let fields = [
+        ("Channel", Some(item.channel.clone())),
+        ("Relevance", Some(item.relevance.to_string())),
+        ("Link", Some(item.link.clone())),
+        ("Publish Date", item.pub_date.clone()),
+        ("Categories", item.categories.clone()),
+        ("Keywords", item.keywords.clone()),
+        ("Entities", Some(entities).filter(|entities| !entities.is_empty())),
+    ];
+    for (name, value) in fields.iter() {
+        if let Some(value) = value {
+            body.push_str(&format!("<dt>{}</dt><dd>{}</dd>\n", name, escape_xml(value)));
+        }
+    }
+    body.push_str("</dl>\n");
+
+    // One paragraph per line of the clean content, the description otherwise
+    let content = item.clean_content.as_deref().unwrap_or(&item.description);
+    for paragraph in content.lines().map(str::trim).filter(|line| !line.is_empty()) {
+        body.push_str(&format!("<p>{}</p>\n", escape_xml(paragraph)));
+    }
+    xhtml(&item.title, &body)
+}
+
+// Function that returns the package document with the metadata, the manifest
+// and the reading order
+fn package(model: &ReportModel, identifier: &str) -> String {
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n\
+         <item id=\"style\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    for item in model.items.iter() {
+        manifest.push_str(&format!(
+            "<item id=\"chapter-{}\" href=\"{}\" media-type=\"application/xhtml+xml\"/>\n",
+            item.position,
+            chapter_file(item)
+        ));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", item.position));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"id\" xml:lang=\"es\">\n\
+         <metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n\
+         <dc:identifier id=\"id\">{}</dc:identifier>\n\
+         <dc:title>{} {}</dc:title>\n\
+         <dc:language>es</dc:language>\n\
+         <dc:creator>hemeroteca</dc:creator>\n\
+         <meta property=\"dcterms:modified\">{}</meta>\n\
+         </metadata>\n<manifest>\n{}</manifest>\n<spine>\n{}</spine>\n</package>\n",
+        escape_xml(identifier),
+        escape_xml(&model.title),
+        escape_xml(&model.date),
+        modified,
+        manifest,
+        spine
+    )
+}
+
+/// Function that writes a dossier of the NewsItems as an EPUB 3 book with a
+/// chapter per item and the table of contents of the dossier
+pub fn write_dossier_epub<W: Write + Seek>(news_items: &[NewsItem], writer: W) -> Result<(), Box<dyn Error>> {
+    let model = ReportModel::new("Dossier", news_items);
+
+    // The identifier is derived from the links so the same dossier keeps it
+    let links = news_items.iter().map(|item| item.link.as_str()).collect::<Vec<&str>>();
+    let identifier = format!("urn:sha256:{}", snapshot_hash(&links.join("\n")));
+
+    let mut zip = ZipWriter::new(writer);
+
+    // The mimetype must be the first entry and must not be compressed
+    zip.start_file("mimetype", SimpleFileOptions::default().compression_method(CompressionMethod::Stored))?;
+    zip.write_all(b"application/epub+zip")?;
+
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let mut add = |name: &str, contents: &str| -> Result<(), Box<dyn Error>> {
+        zip.start_file(name, options)?;
+        zip.write_all(contents.as_bytes())?;
+        Ok(())
+    };
+    add("META-INF/container.xml", CONTAINER)?;
+    add("OEBPS/content.opf", &package(&model, &identifier))?;
+    add("OEBPS/nav.xhtml", &navigation(&model))?;
+    add("OEBPS/style.css", STYLESHEET)?;
+    for item in model.items.iter() {
+        add(&format!("OEBPS/{}", chapter_file(item)), &chapter(item))?;
+    }
+
+    zip.finish()?;
+    Ok(())
+}
+
+/// Function that generates a dossier of the NewsItems as the bytes of an
+/// EPUB 3 book
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::epub::generate_dossier_epub;
+///
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     clean_content: Some("Contenido".to_string()),
+///     ..Default::default()
+/// };
+/// let epub = generate_dossier_epub(&[news_item]).unwrap();
+/// assert_eq!(&epub[30..38], b"mimetype");
+/// ```
+pub fn generate_dossier_epub(news_items: &[NewsItem]) -> Result<Vec<u8>, Box<dyn Error>> {
+    let mut buffer = Cursor::new(Vec::new());
+    write_dossier_epub(news_items, &mut buffer)?;
+    Ok(buffer.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use zip::ZipArchive;
+
+    fn read_entry(archive: &mut ZipArchive<Cursor<Vec<u8>>>, name: &str) -> String {
+        let mut contents = String::new();
+        archive.by_name(name).unwrap().read_to_string(&mut contents).unwrap();
+        contents
+    }
+
+    #[test]
+    fn test_generate_dossier_epub() {
+        let news_items = vec![
+            NewsItem {
+                channel: "El País".to_string(),
+                title: "Inflación & <precios>".to_string(),
+                link: "https://www.acme.es/1.html?a=1&b=2".to_string(),
+                clean_content: Some("Primer párrafo.\n\n  Segundo párrafo.\u{0001}\n".to_string()),
+                relevance: Some(10),
+                ..Default::default()
+            },
+            NewsItem {
+                title: "Fútbol".to_string(),
+                link: "https://www.acme.es/2.html".to_string(),
+                description: "Solo la descripción".to_string(),
+                ..Default::default()
+            },
+        ];
+        let epub = generate_dossier_epub(&news_items).unwrap();
+        let mut archive = ZipArchive::new(Cursor::new(epub)).unwrap();
+
+        // The mimetype is the first entry and it is stored
+        let mimetype = archive.by_index(0).unwrap();
+        assert_eq!(mimetype.name(), "mimetype");
+        assert_eq!(mimetype.compression(), CompressionMethod::Stored);
+        drop(mimetype);
+        assert_eq!(read_entry(&mut archive, "mimetype"), "application/epub+zip");
+
+        // The navigation document lists the chapters in the dossier order
+        let nav = read_entry(&mut archive, "OEBPS/nav.xhtml");
+        let first = nav.find("<a href=\"chapter-1.xhtml\">Inflación &amp; &lt;precios&gt;</a>").unwrap();
+        let second = nav.find("<a href=\"chapter-2.xhtml\">Fútbol</a>").unwrap();
+        assert!(first < second);
+
+        // The package lists every chapter in the spine
+        let package = read_entry(&mut archive, "OEBPS/content.opf");
+        assert!(package.contains("<itemref idref=\"chapter-1\"/>\n<itemref idref=\"chapter-2\"/>"));
+        assert!(package.contains("properties=\"nav\""));
+        assert!(package.contains("<dc:identifier id=\"id\">urn:sha256:"));
+
+        // The chapters have the metadata and a paragraph per line
+        let chapter = read_entry(&mut archive, "OEBPS/chapter-1.xhtml");
+        assert!(chapter.contains("<dt>Link</dt><dd>https://www.acme.es/1.html?a=1&amp;b=2</dd>"));
+        assert!(chapter.contains("<p>Primer párrafo.</p>\n<p>Segundo párrafo.</p>"));
+        let chapter = read_entry(&mut archive, "OEBPS/chapter-2.xhtml");
+        assert!(chapter.contains("<p>Solo la descripción</p>"));
+    }
+}
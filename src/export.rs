@@ -0,0 +1,432 @@
+/// Module for exporting and importing NewsItems as newline-delimited JSON and
+/// CSV.
+///
+/// CSV schema version 1: the first row is the header with the titles of the
+/// selected columns, in any order. The optional fields are empty when missing,
+/// the error is written with `PipelineError::as_string`, the entities as a
+/// JSON array, as in the archive, and the feedback date in RFC 2822. The reader
+/// matches the columns by their title, ignores the unknown ones and leaves
+/// the missing ones with their default value
+use std::error::Error;
+use std::io::{BufRead, Read, Write};
+use std::str::FromStr;
+
+use crate::common::{NewsItem, PipelineError};
+
+/// Version of the CSV schema written by write_news_items_to_csv
+pub const CSV_SCHEMA_VERSION: u32 = 1;
+
+/// Enum that represents the columns of a CSV export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CsvColumn {
+    Channel,
+    Title,
+    Link,
+    Description,
+    Creators,
+    PubDate,
+    Categories,
+    Keywords,
+    CleanContent,
+    Error,
+    Relevance,
+    Entities,
+    FeedbackDate,
+}
+
+/// Columns written by default, every field of a NewsItem
+pub const DEFAULT_CSV_COLUMNS: [CsvColumn; 12] = [
+    CsvColumn::Channel,
+    CsvColumn::Title,
+    CsvColumn::Link,
+    CsvColumn::Description,
+    CsvColumn::Creators,
+    CsvColumn::PubDate,
+    CsvColumn::Categories,
+    CsvColumn::Keywords,
+    CsvColumn::CleanContent,
+    CsvColumn::Error,
+    CsvColumn::Relevance,
+    CsvColumn::Entities,
+];
+
+impl CsvColumn {
+    /// Function that returns the title of the column in the header row
+    pub fn title(&self) -> &str {
+        match self {
+            CsvColumn::Channel => "Channel",
+            CsvColumn::Title => "Title",
+            CsvColumn::Link => "Link",
+            CsvColumn::Description => "Description",
+            CsvColumn::Creators => "Creators",
+            CsvColumn::PubDate => "Publication Date",
+            CsvColumn::Categories => "Categories",
+            CsvColumn::Keywords => "Keywords",
+            CsvColumn::CleanContent => "Clean Content",
+            CsvColumn::Error => "Error",
+            CsvColumn::Relevance => "Relevance",
+            CsvColumn::Entities => "Entities",
+            CsvColumn::FeedbackDate => "Feedback Date",
+        }
+    }
+
+    /// Function that returns the column with the title passed, if any
+    pub fn from_title(title: &str) -> Option<CsvColumn> {
+        DEFAULT_CSV_COLUMNS
+            .iter()
+            .chain([CsvColumn::FeedbackDate].iter())
+            .find(|column| column.title() == title.trim())
+            .copied()
+    }
+
+    /// Function that parses a comma separated list of column names
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::export::CsvColumn;
+    ///
+    /// let columns = CsvColumn::parse_list("title, link,pub_date").unwrap();
+    /// assert_eq!(columns, vec![CsvColumn::Title, CsvColumn::Link, CsvColumn::PubDate]);
+    /// assert!(CsvColumn::parse_list("title,unknown").is_err());
+    /// ```
+    pub fn parse_list(columns: &str) -> Result<Vec<CsvColumn>, Box<dyn Error>> {
+        columns.split(',').map(|column| column.trim().parse()).collect()
+    }
+
+    // Function that returns the value of the column for a NewsItem
+    fn value(&self, news_item: &NewsItem, feedback_date: Option<&str>) -> String {
+        match self {
+            CsvColumn::Channel => news_item.channel.clone(),
+            CsvColumn::Title => news_item.title.clone(),
+            CsvColumn::Link => news_item.link.clone(),
+            CsvColumn::Description => news_item.description.clone(),
+            CsvColumn::Creators => news_item.creators.clone(),
+            CsvColumn::PubDate => news_item.pub_date.clone().unwrap_or_default(),
+            CsvColumn::Categories => news_item.categories.clone().unwrap_or_default(),
+            CsvColumn::Keywords => news_item.keywords.clone().unwrap_or_default(),
+            CsvColumn::CleanContent => news_item.clean_content.clone().unwrap_or_default(),
+            CsvColumn::Error => news_item.error.as_ref().map(|e| e.as_string()).unwrap_or_default(),
+            CsvColumn::Relevance => news_item.relevance.map(|r| r.to_string()).unwrap_or_default(),
+            CsvColumn::Entities if news_item.entities.is_empty() => String::new(),
+            CsvColumn::Entities => serde_json::to_string(&news_item.entities).unwrap_or_default(),
+            CsvColumn::FeedbackDate => feedback_date.unwrap_or_default().to_string(),
+        }
+    }
+
+    // Function that sets the field of the column in a NewsItem
+    fn set_value(&self, news_item: &mut NewsItem, value: &str) -> Result<(), Box<dyn Error>> {
+        let optional = if value.is_empty() { None } else { Some(value.to_string()) };
+        match self {
+            CsvColumn::Channel => news_item.channel = value.to_string(),
+            CsvColumn::Title => news_item.title = value.to_string(),
+            CsvColumn::Link => news_item.link = value.to_string(),
+            CsvColumn::Description => news_item.description = value.to_string(),
+            CsvColumn::Creators => news_item.creators = value.to_string(),
+            CsvColumn::PubDate => news_item.pub_date = optional,
+            CsvColumn::Categories => news_item.categories = optional,
+            CsvColumn::Keywords => news_item.keywords = optional,
+            CsvColumn::CleanContent => news_item.clean_content = optional,
+            CsvColumn::Error => news_item.error = parse_error(value)?,
+            CsvColumn::Relevance => news_item.relevance = optional.map(|r| r.parse()).transpose()?,
+            CsvColumn::Entities if value.is_empty() => news_item.entities = vec![],
+            CsvColumn::Entities => news_item.entities = serde_json::from_str(value)?,
+            CsvColumn::FeedbackDate => {}
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for CsvColumn {
+    type Err = Box<dyn Error>;
+
+    /// Function that returns a CsvColumn from its snake case name
+    fn from_str(column: &str) -> Result<Self, Self::Err> {
+        match column {
+            "channel" => Ok(CsvColumn::Channel),
+            "title" => Ok(CsvColumn::Title),
+            "link" => Ok(CsvColumn::Link),
+            "description" => Ok(CsvColumn::Description),
+            "creators" => Ok(CsvColumn::Creators),
+            "pub_date" => Ok(CsvColumn::PubDate),
+            "categories" => Ok(CsvColumn::Categories),
+            "keywords" => Ok(CsvColumn::Keywords),
+            "clean_content" => Ok(CsvColumn::CleanContent),
+            "error" => Ok(CsvColumn::Error),
+            "relevance" => Ok(CsvColumn::Relevance),
+            "entities" => Ok(CsvColumn::Entities),
+            "feedback_date" => Ok(CsvColumn::FeedbackDate),
+            _ => Err(format!("Unknown CSV column: {}", column).into()),
+        }
+    }
+}
+
+/// Struct with the options of a CSV export
+#[derive(Debug, Clone)]
+pub struct CsvOptions {
+    /// Columns to write, in order
+    pub columns: Vec<CsvColumn>,
+    /// Date written in the feedback date column, if selected
+    pub feedback_date: Option<String>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            columns: DEFAULT_CSV_COLUMNS.to_vec(),
+            feedback_date: None,
+        }
+    }
+}
+
+// Function that parses the error column, accepting the Debug format of the
+// files written before the schema version 1, e.g. `Some(NoContent)` or `None`
+fn parse_error(value: &str) -> Result<Option<PipelineError>, Box<dyn Error>> {
+    let value = match value.strip_prefix("Some(").and_then(|value| value.strip_suffix(')')) {
+        Some(legacy) => legacy.replace("(\"", "(").replace("\")", ")"),
+        None if value == "None" => String::new(),
+        None => value.to_string(),
+    };
+    if value.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(value.parse()?))
+    }
+}
+
+/// Function that writes the NewsItems to a writer as CSV with the columns of
+/// the options and returns the number of items written
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::export::{write_news_items_to_csv, CsvColumn, CsvOptions};
+///
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     relevance: Some(10),
+///     ..Default::default()
+/// };
+/// let options = CsvOptions {
+///     columns: vec![CsvColumn::Title, CsvColumn::Relevance],
+///     ..Default::default()
+/// };
+/// let mut buffer = Vec::new();
+/// write_news_items_to_csv(&[news_item], &mut buffer, &options).unwrap();
+/// assert_eq!(String::from_utf8(buffer).unwrap(), "Title,Relevance\nLa inflación baja,10\n");
+/// ```
+pub fn write_news_items_to_csv<W: Write>(news_items: &[NewsItem], writer: W, options: &CsvOptions) -> Result<usize, Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(writer);
+
+    // Write the header row
+    writer.write_record(options.columns.iter().map(|column| column.title()))?;
+
+    // Iterate over each NewsItem and write the selected fields
+    for news_item in news_items {
+        writer.write_record(
+            options
+                .columns
+                .iter()
+                .map(|column| column.value(news_item, options.feedback_date.as_deref())),
+        )?;
+    }
+
+    // Flush and finish writing
+    writer.flush()?;
+    Ok(news_items.len())
+}
+
+/// Function that reads the NewsItems from a reader with CSV written by
+/// write_news_items_to_csv, including the feedback files. The error names the
+/// line that could not be parsed
+pub fn read_news_items_from_csv<R: Read>(reader: R) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let columns = reader
+        .headers()?
+        .iter()
+        .map(CsvColumn::from_title)
+        .collect::<Vec<Option<CsvColumn>>>();
+
+    let mut news_items = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |position| position.line());
+        let mut news_item = NewsItem::default();
+        for (column, value) in columns.iter().zip(record.iter()) {
+            if let Some(column) = column {
+                column
+                    .set_value(&mut news_item, value)
+                    .map_err(|err| format!("Line {}, column {}: {}", line, column.title(), err))?;
+            }
+        }
+        news_items.push(news_item);
+    }
+    Ok(news_items)
+}
+
+/// Function that writes the NewsItems to a writer as JSON Lines, one item per
+/// line, and returns the number of items written
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::export::write_news_items_to_jsonl;
+///
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     link: "https://www.acme.es/1.html".to_string(),
+///     ..Default::default()
+/// };
+/// let mut buffer = Vec::new();
+/// let count = write_news_items_to_jsonl(&[news_item], &mut buffer).unwrap();
+/// assert_eq!(count, 1);
+/// assert!(String::from_utf8(buffer).unwrap().ends_with("}\n"));
+/// ```
+pub fn write_news_items_to_jsonl<W: Write>(news_items: &[NewsItem], mut writer: W) -> Result<usize, Box<dyn Error>> {
+    for news_item in news_items {
+        serde_json::to_writer(&mut writer, news_item)?;
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()?;
+    Ok(news_items.len())
+}
+
+/// Function that reads the NewsItems from a reader with JSON Lines, skipping
+/// the blank lines. The error names the line that could not be parsed
+///
+/// Example:
+/// ```
+/// use hemeroteca::export::read_news_items_from_jsonl;
+///
+/// let jsonl = r#"{"channel":"Other","title":"A","link":"https://www.acme.es/a.html","description":"","creators":""}"#;
+/// let news_items = read_news_items_from_jsonl(jsonl.as_bytes()).unwrap();
+/// assert_eq!(news_items[0].title, "A");
+/// assert_eq!(news_items[0].relevance, None);
+/// ```
+pub fn read_news_items_from_jsonl<R: BufRead>(reader: R) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    let mut news_items = Vec::new();
+    for (number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let news_item = serde_json::from_str(&line).map_err(|err| format!("Line {}: {}", number + 1, err))?;
+        news_items.push(news_item);
+    }
+    Ok(news_items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityKind};
+
+    #[test]
+    fn test_jsonl_round_trip() {
+        let news_items = vec![
+            NewsItem {
+                channel: "El País".to_string(),
+                title: "Título con \"comillas\"\ny salto".to_string(),
+                link: "https://www.acme.es/1.html".to_string(),
+                pub_date: Some("Mon, 01 Jan 2024 10:00:00 +0000".to_string()),
+                clean_content: Some("Contenido".to_string()),
+                error: Some(PipelineError::NetworkError("timeout".to_string())),
+                relevance: Some(42),
+                entities: vec![Entity {
+                    name: "Banco de España".to_string(),
+                    kind: EntityKind::Organization,
+                }],
+                raw_html: Some("<p>Contenido</p>".to_string()),
+                ..Default::default()
+            },
+            NewsItem {
+                link: "https://www.acme.es/2.html".to_string(),
+                error: Some(PipelineError::NoContent),
+                ..Default::default()
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        assert_eq!(write_news_items_to_jsonl(&news_items, &mut buffer).unwrap(), 2);
+        let jsonl = String::from_utf8(buffer).unwrap();
+        assert_eq!(jsonl.lines().count(), 2);
+        assert!(!jsonl.contains("raw_html"));
+
+        let read = read_news_items_from_jsonl(jsonl.as_bytes()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert_eq!(read[0].title, news_items[0].title);
+        assert_eq!(read[0].relevance, Some(42));
+        assert_eq!(read[0].entities, news_items[0].entities);
+        assert_eq!(read[0].error.as_ref().map(|e| e.as_string()), Some("NetworkError(timeout)".to_string()));
+        assert_eq!(read[0].raw_html, None);
+        assert!(matches!(read[1].error, Some(PipelineError::NoContent)));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let news_items = vec![
+            NewsItem {
+                channel: "El País".to_string(),
+                title: "Título, con \"comillas\"\ny salto".to_string(),
+                link: "https://www.acme.es/1.html".to_string(),
+                description: "Descripción".to_string(),
+                pub_date: Some("Mon, 01 Jan 2024 10:00:00 +0000".to_string()),
+                categories: Some("economía,política".to_string()),
+                clean_content: Some("Contenido".to_string()),
+                error: Some(PipelineError::ParsingError("no body".to_string())),
+                relevance: Some(42),
+                entities: vec![
+                    Entity {
+                        name: "Banco de España".to_string(),
+                        kind: EntityKind::Organization,
+                    },
+                    Entity {
+                        name: "Sánchez; Feijóo".to_string(),
+                        kind: EntityKind::Person,
+                    },
+                ],
+                ..Default::default()
+            },
+            NewsItem {
+                link: "https://www.acme.es/2.html".to_string(),
+                error: Some(PipelineError::UnknownError),
+                ..Default::default()
+            },
+        ];
+
+        let mut buffer = Vec::new();
+        write_news_items_to_csv(&news_items, &mut buffer, &CsvOptions::default()).unwrap();
+        let read = read_news_items_from_csv(buffer.as_slice()).unwrap();
+        assert_eq!(read.len(), 2);
+        for (read, written) in read.iter().zip(news_items.iter()) {
+            assert!(read.same_values(written));
+            assert_eq!(read.entities, written.entities);
+            assert_eq!(read.error.as_ref().map(|e| e.as_string()), written.error.as_ref().map(|e| e.as_string()));
+        }
+    }
+
+    #[test]
+    fn test_read_legacy_feedback_csv() {
+        let csv = "Channel,Title,Link,Description,Creators,Publication Date,Categories,Keywords,Clean Content,Error,Feedback Date,Relevance\n\
+                   Other,A,https://www.acme.es/a.html,D,,,,,,None,\"Sun, 01 Jan 2017 12:00:00 +0000\",4\n\
+                   Other,B,https://www.acme.es/b.html,D,,,,,,\"Some(NetworkError(\"\"timeout\"\"))\",\"Sun, 01 Jan 2017 12:00:00 +0000\",2\n";
+        let read = read_news_items_from_csv(csv.as_bytes()).unwrap();
+        assert_eq!(read.len(), 2);
+        assert!(read[0].error.is_none());
+        assert_eq!(read[0].relevance, Some(4));
+        assert_eq!(read[1].error.as_ref().map(|e| e.as_string()), Some("NetworkError(timeout)".to_string()));
+    }
+
+    #[test]
+    fn test_read_invalid_csv() {
+        let csv = "Title,Relevance\nA,1\nB,high\n";
+        let err = read_news_items_from_csv(csv.as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("Line 3, column Relevance:"));
+    }
+
+    #[test]
+    fn test_read_invalid_jsonl() {
+        let jsonl = "{\"channel\":\"Other\",\"title\":\"A\",\"link\":\"a\",\"description\":\"\",\"creators\":\"\"}\n\nnot json\n";
+        let err = read_news_items_from_jsonl(jsonl.as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("Line 3:"));
+    }
+}
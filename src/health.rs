@@ -0,0 +1,386 @@
+/// Module for the health of the feeds across runs: the trends of the statuses
+/// recorded in the archive and the feeds that have been broken or empty for
+/// several runs in a row
+use serde::Serialize;
+
+use crate::common::FeedStatus;
+
+/// Default number of latest runs to compute the health of the feeds
+pub const DEFAULT_HEALTH_RUNS: usize = 10;
+
+/// Default number of runs in a row a feed has to be broken or empty to be
+/// flagged
+pub const DEFAULT_FLAG_RUNS: usize = 3;
+
+/// Levels of the trend sparklines from no items to the most items
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Mark of the runs in which the feed could not be read
+const FAILED_SPARK: char = '✗';
+
+/// Struct that represents the status of a feed recorded in a run
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedRun {
+    pub run_id: i64,
+    pub started_at: String,
+    pub status: FeedStatus,
+}
+
+/// Enum that represents the health of a feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum FeedCondition {
+    /// The feed could not be read in the latest runs
+    Broken,
+    /// The feed was read without usable items in the latest runs
+    Empty,
+    /// The feed failed or was empty in some of the runs
+    Flaky,
+    Healthy,
+}
+
+impl std::fmt::Display for FeedCondition {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            FeedCondition::Broken => write!(f, "broken"),
+            FeedCondition::Empty => write!(f, "empty"),
+            FeedCondition::Flaky => write!(f, "flaky"),
+            FeedCondition::Healthy => write!(f, "healthy"),
+        }
+    }
+}
+
+/// Struct that represents the health of a feed over the latest runs
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FeedHealth {
+    pub url: String,
+    /// Title of the channel the last time the feed could be read
+    pub channel: Option<String>,
+    pub condition: FeedCondition,
+    pub runs: usize,
+    pub failed_runs: usize,
+    pub empty_runs: usize,
+    /// Latest runs in a row in which the feed was broken or empty
+    pub streak: usize,
+    pub last_http_status: Option<u16>,
+    pub average_latency_ms: Option<u64>,
+    pub average_bytes: usize,
+    /// Average of the items converted to NewsItems with one decimal
+    pub average_items: String,
+    pub dropped_items: usize,
+    /// Percentage of the fetched contents that could be extracted with one
+    /// decimal, None if no content was fetched
+    pub extraction_rate: Option<String>,
+    pub last_error: Option<String>,
+    /// Sparkline of the items per run from the oldest run, failed runs are
+    /// marked with ✗
+    pub trend: String,
+}
+
+// Function that returns the health of a feed from its runs ordered from the
+// oldest
+fn health_of_feed(runs: &[&FeedRun], flag_runs: usize) -> FeedHealth {
+    let statuses = runs.iter().map(|run| &run.status).collect::<Vec<&FeedStatus>>();
+    let last = statuses[statuses.len() - 1];
+
+    let failed_runs = statuses.iter().filter(|status| !status.is_ok()).count();
+    let empty_runs = statuses
+        .iter()
+        .filter(|status| status.is_ok() && status.usable_items() == 0)
+        .count();
+    let streak = statuses
+        .iter()
+        .rev()
+        .take_while(|status| !status.is_ok() || status.usable_items() == 0)
+        .count();
+    let condition = if streak >= flag_runs.max(1) {
+        if last.is_ok() {
+            FeedCondition::Empty
+        } else {
+            FeedCondition::Broken
+        }
+    } else if failed_runs + empty_runs > 0 {
+        FeedCondition::Flaky
+    } else {
+        FeedCondition::Healthy
+    };
+
+    let latencies = statuses
+        .iter()
+        .filter_map(|status| status.latency_ms)
+        .collect::<Vec<u64>>();
+    let max_items = statuses
+        .iter()
+        .map(|status| status.usable_items())
+        .max()
+        .unwrap_or_default();
+    let trend = statuses
+        .iter()
+        .map(|status| match (status.is_ok(), max_items) {
+            (false, _) => FAILED_SPARK,
+            (true, 0) => SPARKS[0],
+            (true, max) => SPARKS[status.usable_items() * (SPARKS.len() - 1) / max],
+        })
+        .collect();
+    let (content_items, extracted_items) = statuses.iter().fold((0, 0), |(content, extracted), status| {
+        (content + status.content_items, extracted + status.extracted_items)
+    });
+
+    FeedHealth {
+        url: last.url.clone(),
+        channel: statuses.iter().rev().find_map(|status| status.channel.clone()),
+        condition,
+        runs: statuses.len(),
+        failed_runs,
+        empty_runs,
+        streak,
+        last_http_status: last.http_status,
+        average_latency_ms: (!latencies.is_empty()).then(|| latencies.iter().sum::<u64>() / latencies.len() as u64),
+        average_bytes: statuses.iter().map(|status| status.bytes).sum::<usize>() / statuses.len(),
+        average_items: format!(
+            "{:.1}",
+            statuses.iter().map(|status| status.usable_items()).sum::<usize>() as f64 / statuses.len() as f64
+        ),
+        dropped_items: statuses.iter().map(|status| status.dropped_items).sum(),
+        extraction_rate: (content_items > 0)
+            .then(|| format!("{:.1}", extracted_items as f64 / content_items as f64 * 100.0)),
+        last_error: statuses.iter().rev().find_map(|status| status.error.clone()),
+        trend,
+    }
+}
+
+/// Function that returns the health of every feed in the runs, the flagged
+/// feeds first. A feed is flagged as broken or empty when it could not be read
+/// or had no usable items in the latest `flag_runs` runs in a row
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::health::{feed_health, FeedCondition, FeedRun};
+///
+/// let run = |run_id: i64, url: &str, items: usize, error: Option<&str>| FeedRun {
+///     run_id,
+///     started_at: String::new(),
+///     status: FeedStatus {
+///         url: url.to_string(),
+///         items,
+///         error: error.map(|error| error.to_string()),
+///         ..Default::default()
+///     },
+/// };
+/// let history = vec![
+///     run(1, "https://www.acme.es/rss", 10, None),
+///     run(1, "https://www.acme.es/broken", 5, None),
+///     run(2, "https://www.acme.es/rss", 12, None),
+///     run(2, "https://www.acme.es/broken", 0, Some("HTTP status 404 Not Found")),
+///     run(3, "https://www.acme.es/rss", 8, None),
+///     run(3, "https://www.acme.es/broken", 0, Some("HTTP status 404 Not Found")),
+/// ];
+/// let health = feed_health(&history, 2);
+/// assert_eq!(health[0].url, "https://www.acme.es/broken");
+/// assert_eq!(health[0].condition, FeedCondition::Broken);
+/// assert_eq!(health[0].trend, "█✗✗");
+/// assert_eq!(health[1].condition, FeedCondition::Healthy);
+/// ```
+pub fn feed_health(history: &[FeedRun], flag_runs: usize) -> Vec<FeedHealth> {
+    // Group the runs by feed keeping the order of the urls
+    let mut feeds: Vec<(&str, Vec<&FeedRun>)> = Vec::new();
+    for run in history.iter() {
+        match feeds.iter_mut().find(|(url, _)| *url == run.status.url) {
+            Some((_, runs)) => runs.push(run),
+            None => feeds.push((&run.status.url, vec![run])),
+        }
+    }
+
+    let mut health = feeds
+        .iter_mut()
+        .map(|(_, runs)| {
+            runs.sort_by_key(|run| run.run_id);
+            health_of_feed(runs, flag_runs)
+        })
+        .collect::<Vec<FeedHealth>>();
+    health.sort_by_key(|feed| feed.condition);
+    health
+}
+
+/// Function that formats the health of the feeds as Markdown
+pub fn feed_health_to_markdown(health: &[FeedHealth], flag_runs: usize) -> String {
+    let flagged = health
+        .iter()
+        .filter(|feed| matches!(feed.condition, FeedCondition::Broken | FeedCondition::Empty))
+        .collect::<Vec<&FeedHealth>>();
+    let runs = health.iter().map(|feed| feed.runs).max().unwrap_or_default();
+    let mut report = format!(
+        "# Feed health\n\n- Feeds: {}\n- Runs: {}\n- Flagged after: {} runs\n\n",
+        health.len(),
+        runs,
+        flag_runs
+    );
+
+    report.push_str(&format!("## Flagged feeds ({})\n\n", flagged.len()));
+    for feed in flagged.iter() {
+        report.push_str(&format!(
+            "- {} {} for {} runs | {}{}\n",
+            feed.url,
+            feed.condition,
+            feed.streak,
+            feed.channel.as_deref().unwrap_or("N/A"),
+            feed.last_error
+                .as_ref()
+                .map(|error| format!(" | {}", error))
+                .unwrap_or_default()
+        ));
+    }
+    if !flagged.is_empty() {
+        report.push('\n');
+    }
+
+    report.push_str("## Feeds\n\n");
+    report.push_str("| Feed | Channel | Condition | Trend | Failed | Empty | HTTP | Latency (ms) | Bytes | Items | Dropped | Extracted |\n");
+    report.push_str("|---|---|---|---|---:|---:|---:|---:|---:|---:|---:|---:|\n");
+    let or_na = |value: Option<String>| value.unwrap_or_else(|| "N/A".to_string());
+    for feed in health.iter() {
+        report.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} | {} |\n",
+            feed.url,
+            feed.channel.as_deref().unwrap_or("N/A"),
+            feed.condition,
+            feed.trend,
+            feed.failed_runs,
+            feed.empty_runs,
+            or_na(feed.last_http_status.map(|status| status.to_string())),
+            or_na(feed.average_latency_ms.map(|latency| latency.to_string())),
+            feed.average_bytes,
+            feed.average_items,
+            feed.dropped_items,
+            or_na(feed.extraction_rate.as_ref().map(|rate| format!("{}%", rate)))
+        ));
+    }
+    report
+}
+
+/// Function that formats the health of the feeds as JSON
+pub fn feed_health_to_json(health: &[FeedHealth]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(health)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed_run(run_id: i64, url: &str, status: FeedStatus) -> FeedRun {
+        FeedRun {
+            run_id,
+            started_at: format!("2024-01-0{}T10:00:00+00:00", run_id),
+            status: FeedStatus { url: url.to_string(), ..status },
+        }
+    }
+
+    fn read(items: usize, dropped_items: usize, latency_ms: u64) -> FeedStatus {
+        FeedStatus {
+            channel: Some("El País".to_string()),
+            items,
+            dropped_items,
+            http_status: Some(200),
+            latency_ms: Some(latency_ms),
+            bytes: 1000,
+            content_items: items - dropped_items,
+            extracted_items: (items - dropped_items) / 2,
+            ..Default::default()
+        }
+    }
+
+    fn failed(http_status: Option<u16>, error: &str) -> FeedStatus {
+        FeedStatus { http_status, error: Some(error.to_string()), ..Default::default() }
+    }
+
+    #[test]
+    fn test_feed_health() {
+        let elpais = "https://www.acme.es/elpais.xml";
+        let empty = "https://www.acme.es/empty.xml";
+        let flaky = "https://www.acme.es/flaky.xml";
+        let history = vec![
+            feed_run(1, elpais, read(8, 0, 100)),
+            feed_run(1, empty, read(4, 0, 50)),
+            feed_run(1, flaky, read(4, 0, 50)),
+            feed_run(2, elpais, read(4, 1, 200)),
+            feed_run(2, empty, read(2, 2, 50)),
+            feed_run(2, flaky, failed(None, "timeout")),
+            // Out of order runs are sorted
+            feed_run(4, elpais, read(8, 0, 300)),
+            feed_run(4, empty, read(0, 0, 50)),
+            feed_run(4, flaky, read(4, 0, 50)),
+            feed_run(3, elpais, read(6, 0, 200)),
+            feed_run(3, empty, read(0, 0, 50)),
+            feed_run(3, flaky, failed(Some(500), "HTTP status 500 Internal Server Error")),
+        ];
+
+        let health = feed_health(&history, 3);
+        let conditions = health
+            .iter()
+            .map(|feed| (feed.url.as_str(), feed.condition))
+            .collect::<Vec<(&str, FeedCondition)>>();
+        assert_eq!(
+            conditions,
+            vec![
+                (empty, FeedCondition::Empty),
+                (flaky, FeedCondition::Flaky),
+                (elpais, FeedCondition::Healthy)
+            ]
+        );
+
+        // The items dropped by the conversion do not count as usable items
+        let empty_health = &health[0];
+        assert_eq!(
+            (empty_health.runs, empty_health.empty_runs, empty_health.streak),
+            (4, 3, 3)
+        );
+        assert_eq!(empty_health.trend, "█▁▁▁");
+
+        let flaky_health = &health[1];
+        assert_eq!(flaky_health.failed_runs, 2);
+        assert_eq!(flaky_health.streak, 0);
+        assert_eq!(flaky_health.trend, "█✗✗█");
+        assert_eq!(flaky_health.last_http_status, Some(200));
+        assert_eq!(
+            flaky_health.last_error.as_deref(),
+            Some("HTTP status 500 Internal Server Error")
+        );
+
+        let elpais_health = &health[2];
+        assert_eq!(elpais_health.trend, "█▃▆█");
+        assert_eq!(elpais_health.average_latency_ms, Some(200));
+        assert_eq!(elpais_health.average_items, "6.2");
+        assert_eq!(elpais_health.dropped_items, 1);
+        assert_eq!(elpais_health.extraction_rate.as_deref(), Some("48.0"));
+
+        // With a lower threshold the empty feed stays flagged
+        let health = feed_health(&history, 2);
+        assert_eq!(health[0].condition, FeedCondition::Empty);
+        assert_eq!(health[1].condition, FeedCondition::Flaky);
+    }
+
+    #[test]
+    fn test_feed_health_to_markdown() {
+        let history = vec![
+            feed_run(
+                1,
+                "https://www.acme.es/broken.xml",
+                failed(Some(404), "HTTP status 404 Not Found"),
+            ),
+            feed_run(1, "https://www.acme.es/elpais.xml", read(8, 0, 100)),
+        ];
+        let health = feed_health(&history, 1);
+        let markdown = feed_health_to_markdown(&health, 1);
+        assert!(markdown.contains("- Feeds: 2\n- Runs: 1\n"));
+        assert!(markdown.contains("## Flagged feeds (1)\n\n- https://www.acme.es/broken.xml broken for 1 runs | N/A | HTTP status 404 Not Found\n"));
+        assert!(markdown.contains(
+            "| https://www.acme.es/broken.xml | N/A | broken | ✗ | 1 | 0 | 404 | N/A | 0 | 0.0 | 0 | N/A |\n"
+        ));
+        assert!(markdown.contains(
+            "| https://www.acme.es/elpais.xml | El País | healthy | █ | 0 | 0 | 200 | 100 | 1000 | 8.0 | 0 | 50.0% |\n"
+        ));
+
+        let json = feed_health_to_json(&health).unwrap();
+        assert!(json.contains("\"condition\": \"Broken\""));
+    }
+}
@@ -0,0 +1,148 @@
+/// Module for rendering the dossiers and relevance reports as self-contained
+/// HTML pages with the dossier.html and relevance.html templates
+use crate::common::NewsItem;
+use crate::section::{flatten_sections, NewsSection};
+use crate::template::{render_report, ReportModel};
+
+/// Function that returns the color of the tag of a channel, always the same
+/// for the same channel
+///
+/// Example:
+/// ```
+/// use hemeroteca::html::channel_color;
+///
+/// assert_eq!(channel_color("El País"), channel_color("El País"));
+/// assert!(channel_color("El País").starts_with("hsl("));
+/// ```
+pub fn channel_color(channel: &str) -> String {
+    // FNV-1a hash of the channel name mapped to a hue
+    let hash = channel
+        .bytes()
+        .fold(0xcbf29ce484222325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x100000001b3));
+    format!("hsl({}, 55%, 40%)", hash % 360)
+}
+
+/// Function that generates a dossier from a slice of NewsItems as a
+/// self-contained HTML page
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::html::generate_dossier_html;
+///
+/// let news_item = NewsItem {
+///     channel: "El País".to_string(),
+///     title: "La inflación <baja>".to_string(),
+///     clean_content: Some("Contenido".to_string()),
+///     ..Default::default()
+/// };
+/// let html = generate_dossier_html(&[news_item]);
+/// assert!(html.starts_with("<!DOCTYPE html>"));
+/// assert!(html.contains("La inflación &lt;baja&gt;"));
+/// assert!(html.contains("<details>"));
+/// ```
+pub fn generate_dossier_html(news_items: &[NewsItem]) -> String {
+    let model = ReportModel::new("Dossier", news_items).with_relevance_breakdown(news_items);
+    render_report("dossier.html", &model)
+}
+
+/// Function that generates a dossier grouped in sections as a self-contained
+/// HTML page
+pub fn generate_sectioned_dossier_html(sections: &[NewsSection]) -> String {
+    let model = ReportModel::sectioned("Dossier", sections).with_relevance_breakdown(&flatten_sections(sections));
+    render_report("dossier.html", &model)
+}
+
+/// Function that generates a relevance report from a slice of NewsItems as a
+/// self-contained HTML page
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::html::generate_relevance_html;
+///
+/// let news_item = NewsItem {
+///     channel: "El País".to_string(),
+///     title: "La inflación baja".to_string(),
+///     relevance: Some(10),
+///     ..Default::default()
+/// };
+/// let html = generate_relevance_html(&[news_item]);
+/// assert!(html.contains("Relevance per channel"));
+/// assert!(html.contains("relevance 10"));
+/// ```
+pub fn generate_relevance_html(news_items: &[NewsItem]) -> String {
+    // Order the news items by relevance
+    let mut news_items = news_items.to_owned();
+    news_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
+
+    let model = ReportModel::new("Relevance Report", &news_items)
+        .with_relevance_breakdown(&news_items)
+        .with_statistics(&news_items);
+    render_report("relevance.html", &model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityKind};
+
+    fn news_item(channel: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            link: format!("https://www.acme.es/{}.html", relevance),
+            description: "Description".to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_dossier_html() {
+        let news_items = vec![
+            NewsItem {
+                clean_content: Some("Contenido <script>alert(1)</script>".to_string()),
+                entities: vec![Entity {
+                    name: "Banco de España".to_string(),
+                    kind: EntityKind::Organization,
+                }],
+                ..news_item("El País", "El Gobierno aprueba la reforma", 10)
+            },
+            news_item("20 Minutos", "Fútbol", 1),
+        ];
+        let html = generate_dossier_html(&news_items);
+
+        // Table of contents linking to every item
+        assert!(html.contains("<nav class=\"toc\">"));
+        assert!(html.contains("<a href=\"#item-1\">El Gobierno aprueba la reforma</a>"));
+        assert!(html.contains("<article id=\"item-2\">"));
+
+        // Channel tags with a stable color and the relevance breakdown
+        assert!(html.contains(&channel_color("El País")));
+        assert!(html.contains("title=\"core "));
+        assert!(html.contains("breakdown [creator: "));
+
+        // Collapsible and escaped clean content, entities index
+        assert!(html.contains("<summary>Clean content</summary>"));
+        assert!(html.contains("&lt;script&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("<strong>Banco de España</strong> (Organization)"));
+    }
+
+    #[test]
+    fn test_generate_relevance_html() {
+        let news_items = vec![
+            news_item("El País", "A", 5),
+            news_item("El País", "B", 15),
+            news_item("20 Minutos", "C", 1),
+        ];
+        let html = generate_relevance_html(&news_items);
+
+        let b = html.find(">B</a>").unwrap();
+        let a = html.find(">A</a>").unwrap();
+        let c = html.find(">C</a>").unwrap();
+        assert!(b < a && a < c);
+        assert!(html.contains("<td class=\"number\">2</td><td class=\"number\">20</td><td class=\"number\">10.00</td>"));
+    }
+}
@@ -0,0 +1,200 @@
+/// Module for generating the dossiers and relevance reports as JSON documents
+/// for dashboards and other tools.
+///
+/// JSON report schema version 1: an object with the `schema_version`, the
+/// `run` metadata (command, report name, source and generation date in RFC
+/// 3339), the `feeds` fetched with their status (empty when the items are
+/// loaded from a database or WARC file), the `channels` aggregates ordered by
+/// average relevance and the ranked `items`, each one with its `rank`
+/// starting at 1, the news `item` and its `relevance` with the breakdown
+use serde::{Deserialize, Serialize};
+
+use crate::common::{FeedStatus, NewsItem};
+use crate::relevance::{score_relevance, Relevance};
+use crate::relevance_per_channel;
+
+/// Version of the JSON report schema written by generate_json_report
+pub const JSON_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Struct that represents the metadata of the run that generated a report
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunMetadata {
+    /// Command that generated the report, e.g. dossier or relevance
+    pub command: String,
+    pub report_name: String,
+    /// Source of the news items, e.g. feeds or the database file
+    pub source: String,
+    /// Date of the report in RFC 3339
+    pub generated_at: String,
+}
+
+impl RunMetadata {
+    /// Function that creates the metadata of a run generated now
+    pub fn new(command: &str, report_name: &str, source: &str) -> RunMetadata {
+        RunMetadata {
+            command: command.to_string(),
+            report_name: report_name.to_string(),
+            source: source.to_string(),
+            generated_at: chrono::Local::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Struct that represents the aggregated relevance of a channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelAggregate {
+    pub channel: String,
+    pub items: usize,
+    pub total_relevance: f64,
+    pub average_relevance: f64,
+}
+
+/// Struct that represents a news item in the ranking of a report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedItem {
+    /// Position of the item in the report starting at 1
+    pub rank: usize,
+    pub item: NewsItem,
+    pub relevance: Relevance,
+}
+
+/// Struct that represents a report as a JSON document
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonReport {
+    pub schema_version: u32,
+    pub run: RunMetadata,
+    pub feeds: Vec<FeedStatus>,
+    pub channels: Vec<ChannelAggregate>,
+    pub items: Vec<RankedItem>,
+}
+
+impl JsonReport {
+    /// Function that builds the report of the NewsItems ranked by relevance
+    pub fn new(run: RunMetadata, feeds: &[FeedStatus], news_items: &[NewsItem]) -> JsonReport {
+        // Order the news items by relevance, the ties keep their order
+        let mut news_items = news_items.to_owned();
+        news_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
+
+        let channels = relevance_per_channel(&news_items)
+            .into_iter()
+            .map(|(channel, (total, count))| ChannelAggregate {
+                channel,
+                items: count,
+                total_relevance: total,
+                average_relevance: total / count as f64,
+            })
+            .collect();
+
+        let items = news_items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| RankedItem {
+                rank: i + 1,
+                relevance: score_relevance(&item),
+                item,
+            })
+            .collect();
+
+        JsonReport {
+            schema_version: JSON_REPORT_SCHEMA_VERSION,
+            run,
+            feeds: feeds.to_vec(),
+            channels,
+            items,
+        }
+    }
+}
+
+/// Function that generates a report of the NewsItems as a pretty printed JSON
+/// document
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::json::{generate_json_report, RunMetadata};
+///
+/// let news_item = NewsItem {
+///     channel: "El País".to_string(),
+///     title: "La inflación baja".to_string(),
+///     relevance: Some(10),
+///     ..Default::default()
+/// };
+/// let run = RunMetadata::new("dossier", "report", "feeds");
+/// let json = generate_json_report(run, &[], &[news_item]).unwrap();
+/// assert!(json.contains("\"schema_version\": 1"));
+/// assert!(json.contains("\"breakdown\""));
+/// ```
+pub fn generate_json_report(run: RunMetadata, feeds: &[FeedStatus], news_items: &[NewsItem]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(&JsonReport::new(run, feeds, news_items))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn news_item(channel: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            link: format!("https://www.acme.es/{}.html", relevance),
+            description: "Description".to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_generate_json_report() {
+        let news_items = vec![
+            news_item("El País", "A", 5),
+            news_item("El País", "El Gobierno aprueba la reforma", 15),
+            news_item("20 Minutos", "C", 1),
+        ];
+        let feeds = vec![
+            FeedStatus {
+                url: "https://www.acme.es/rss".to_string(),
+                channel: Some("El País".to_string()),
+                items: 2,
+                http_status: Some(200),
+                latency_ms: Some(120),
+                bytes: 2048,
+                ..Default::default()
+            },
+            FeedStatus {
+                url: "https://www.acme.es/broken".to_string(),
+                error: Some("timeout".to_string()),
+                ..Default::default()
+            },
+        ];
+        let run = RunMetadata::new("relevance", "report", "feeds");
+        let json = generate_json_report(run.clone(), &feeds, &news_items).unwrap();
+
+        // The document reads back with the same run and feeds
+        let report: JsonReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(report.schema_version, JSON_REPORT_SCHEMA_VERSION);
+        assert_eq!(report.run, run);
+        assert_eq!(report.feeds, feeds);
+        assert!(!report.feeds[1].is_ok());
+
+        // The channels are ordered by average relevance
+        assert_eq!(report.channels.len(), 2);
+        assert_eq!(report.channels[0].channel, "El País");
+        assert_eq!(report.channels[0].items, 2);
+        assert_eq!(report.channels[0].total_relevance, 20.0);
+        assert_eq!(report.channels[0].average_relevance, 10.0);
+
+        // The items are ranked by relevance with their breakdown
+        let ranking = report.items.iter().map(|item| (item.rank, item.item.title.as_str())).collect::<Vec<_>>();
+        assert_eq!(ranking, vec![(1, "El Gobierno aprueba la reforma"), (2, "A"), (3, "C")]);
+        let relevance = &report.items[0].relevance;
+        assert!(relevance.breakdown.title > 0);
+        assert_eq!(
+            relevance.relevance_core,
+            relevance.breakdown.creator
+                + relevance.breakdown.categories
+                + relevance.breakdown.keywords
+                + relevance.breakdown.title
+                + relevance.breakdown.content
+        );
+    }
+}
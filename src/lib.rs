@@ -1,39 +1,94 @@
 //! Library that provides functions to read and parse RSS feeds
 
+pub mod archive;
 pub mod common;
+pub mod diff;
+pub mod entities;
+pub mod epub;
+pub mod export;
+pub mod health;
+pub mod html;
+pub mod json;
+pub mod mail;
 pub mod openai;
+pub mod publish;
+pub mod query;
 pub mod relevance;
+pub mod search;
+pub mod section;
+pub mod snapshot;
+pub mod stats;
 pub mod storage;
+pub mod store;
+pub mod template;
+pub mod warc;
+pub mod watchlist;
 
 // Re-export commonly used items in a prelude module
 pub mod prelude {
+    pub use crate::archive::Archive;
     pub use crate::clean_content;
     pub use crate::common::ChannelType;
+    pub use crate::common::FeedStatus;
     pub use crate::common::NewsItem;
     pub use crate::common::Operator;
     pub use crate::common::PipelineError;
+    pub use crate::entities::update_news_items_with_entities;
+    pub use crate::entities::Entity;
+    pub use crate::entities::EntityKind;
+    pub use crate::entities::Gazetteer;
+    pub use crate::export::read_news_items_from_csv;
+    pub use crate::export::read_news_items_from_jsonl;
+    pub use crate::export::write_news_items_to_csv;
+    pub use crate::export::write_news_items_to_jsonl;
+    pub use crate::fetch_news_items_matching;
     pub use crate::fetch_news_items_opted_in;
+    pub use crate::fetch_news_items_with_status;
     pub use crate::fill_news_item_content;
     pub use crate::fill_news_items_with_clean_contents;
     pub use crate::get_channel_type;
+    pub use crate::html::generate_dossier_html;
+    pub use crate::html::generate_relevance_html;
     pub use crate::generate_relevance_report;
     pub use crate::log_report_to_file;
     pub use crate::insert_news_items;
+    pub use crate::load_news_items_from_db;
+    pub use crate::load_news_items_from_store;
+    pub use crate::load_news_items_from_warc;
     pub use crate::log_news_items_to_file;
     pub use crate::log_news_items_to_db;
     pub use crate::generate_dossier_report;
+    pub use crate::generate_sectioned_dossier_report;
     pub use crate::openai::summarize;
+    pub use crate::query::Query;
     pub use crate::read_feed;
+    pub use crate::read_feed_with_status;
     pub use crate::read_urls;
     pub use crate::relevance::calculate_relevance;
+    pub use crate::search::search_news_items;
+    pub use crate::search::SearchOptions;
+    pub use crate::storage::NewsItemQuery;
+    pub use crate::store::MemoryStore;
+    pub use crate::store::NewsStore;
     pub use crate::top_k_news_items;
     pub use crate::update_news_items_with_relevance;
     pub use crate::update_news_items_with_relevance_top_k;
-    pub use crate::write_news_items_to_csv;
+    pub use crate::watchlist::evaluate_watchlists;
+    pub use crate::watchlist::read_default_watchlists;
+    pub use crate::watchlist::read_watchlists;
+    pub use crate::watchlist::write_alerts_to_file;
+    pub use crate::watchlist::Watchlist;
 }
 
+use crate::entities::Gazetteer;
+use crate::query::Query;
+use crate::section::NewsSection;
+use crate::template::{render_report, ReportModel};
 use crate::relevance::calculate_relevance;
-use common::{ChannelType, NewsItem, Operator, PipelineError};
+use crate::archive::Archive;
+use crate::storage::{NewsItemQuery, UpsertOutcome, UpsertSummary};
+use crate::store::NewsStore;
+use common::{ChannelType, FeedStatus, NewsItem, Operator, PipelineError};
 
 use std::{
     error::Error,
@@ -46,18 +101,54 @@ use select::{document::Document, node};
 use html2text::config;
 use rand::seq::SliceRandom;
 use regex::Regex;
-use rss::{Channel, Item};
+use rss::Channel;
 
-use std::fs::File;
-use csv::Writer;
 
 /// Function that reads a feed from a URL
 pub async fn read_feed(feed_url: &str) -> Result<Channel, Box<dyn Error>> {
-    let content = reqwest::get(feed_url).await?.bytes().await?;
+    let content = warc::read_response_bytes(feed_url, reqwest::get(feed_url).await?).await?;
     let channel = Channel::read_from(&content[..])?;
     Ok(channel)
 }
 
+/// Function that reads a feed from a URL along with the status of the fetch:
+/// the HTTP status code, the latency, the size of the response and the number
+/// of items, or the error if the feed could not be read
+pub async fn read_feed_with_status(feed_url: &str) -> (Option<Channel>, FeedStatus) {
+    let start = std::time::Instant::now();
+    let mut status = FeedStatus {
+        url: feed_url.to_string(),
+        ..Default::default()
+    };
+
+    let channel = async {
+        let response = reqwest::get(feed_url).await.map_err(|err| err.to_string())?;
+        let http_status = response.status();
+        status.http_status = Some(http_status.as_u16());
+        let content = warc::read_response_bytes(feed_url, response).await.map_err(|err| err.to_string())?;
+        status.bytes = content.len();
+        if !http_status.is_success() {
+            return Err(format!("HTTP status {}", http_status));
+        }
+        Channel::read_from(&content[..]).map_err(|err| err.to_string())
+    }
+    .await;
+    status.latency_ms = Some(start.elapsed().as_millis() as u64);
+
+    match channel {
+        Ok(channel) => {
+            status.channel = Some(channel.title().to_string());
+            status.items = channel.items().len();
+            (Some(channel), status)
+        }
+        Err(err) => {
+            log::error!("Could not read the feed from {}. ERROR: {}", feed_url, err);
+            status.error = Some(err);
+            (None, status)
+        }
+    }
+}
+
 /// Function that reads feed urls from a file
 ///
 /// Example:
@@ -96,94 +187,87 @@ pub fn read_urls(file: &str) -> Result<Vec<String>, Box<dyn Error>> {
 }
 
 /// Function that returns NewsItems from a vector of feed urls matching the
-/// categories or keywords passed as a reference
+/// categories, keywords or entities passed as a reference. Each opt in item is
+/// parsed as a query (see [`query`]) and they are combined with the operator
 pub async fn fetch_news_items_opted_in(
     feed_urls: &[String],
     opt_in: &[String],
     operator: Operator,
 ) -> Option<Vec<NewsItem>> {
-    let mut channels = Vec::new();
+    match Query::from_opt_in(opt_in, operator) {
+        Ok(query) => fetch_news_items_matching(feed_urls, query.as_ref()).await,
+        Err(err) => {
+            log::error!("Could not parse the opt in query {:?}. ERROR: {}", opt_in, err);
+            None
+        }
+    }
+}
 
+/// Function that returns NewsItems from a vector of feed urls matching the
+/// query passed as a reference or all of them if there is no query
+pub async fn fetch_news_items_matching(feed_urls: &[String], query: Option<&Query>) -> Option<Vec<NewsItem>> {
+    fetch_news_items_with_status(feed_urls, query).await.0
+}
+
+/// Function that returns NewsItems from a vector of feed urls matching the
+/// query, like [`fetch_news_items_matching`], along with the status of every
+/// feed in the order of the urls
+pub async fn fetch_news_items_with_status(feed_urls: &[String], query: Option<&Query>) -> (Option<Vec<NewsItem>>, Vec<FeedStatus>) {
     // Spawn as many thread as the minimum of max number of threads and the number
     // of urls and get the handles
     // log::trace!("Spawning {} tasks", feed_urls.len());
     let mut handles = vec![];
     for url in feed_urls.iter() {
         let url = url.clone();
-        let handle = tokio::spawn(async move {
-            let channel = read_feed(&url).await;
-            // Map the result to an option and log the error if any
-            channel.map_err(|e| {
-                log::error!("Could not read the feed from {}. ERROR: {}", url, e);
-            }).ok()
-        });
+        let handle = tokio::spawn(async move { read_feed_with_status(&url).await });
         handles.push(handle);
     }
 
-    // Wait for all the threads to finish
+    // Wait for all the threads to finish and keep the status of every feed
+    let mut channels = Vec::new();
+    let mut statuses = Vec::new();
     for handle in handles {
-        channels.push(handle.await.unwrap());
+        let (channel, status) = handle.await.unwrap();
+        channels.push(channel);
+        statuses.push(status);
     }
 
-    // Get the items from the channels
-    let items: Vec<(&str, Vec<Item>)> = channels
-        .iter()
-        .filter_map(|channel| {
-            // Maps option channel to an option tuple of the title and the items
-            channel.as_ref().map(|channel| (channel.title(), channel.items().to_vec()))
-        })
-        .collect();
-
-    // If there are no items return None
-    if items.is_empty() {
-        None
-    } else {
-        // Else, get all the items from the channels
-        let mut all_items: Vec<NewsItem> = items
-            .iter()
-            .flat_map(|(channel, items)| {
-                items
-                    .iter()
-                    .map(|item| NewsItem::from_item(channel, item))
-                    .filter_map(|result| {
-                        if let Ok(result) = result {
-                            Some(result)
-                        } else {
-                            log::error!("Could not get the item from the result: {:?}", result);
-                            None
-                        }
-                    })
-                    .collect::<Vec<NewsItem>>()
-            })
-            .collect();
-
-        // Retains the items that have any of the categories or keywords equal to the
-        // categories to opt in
-        all_items.retain(|item| {
-            let categories = item.categories.clone().unwrap_or("".to_string());
-            let keywords = item.keywords.clone().unwrap_or("".to_string());
-            log::trace!("Checking {:?} in {:?} and {:?}", opt_in, categories, keywords);
-
-            // If the opt_in is empty, return true
-            if opt_in.is_empty() {
-                true
-            } else {
-                // Return true if all the opt_in items are in the categories or keywords
-                match operator {
-                    Operator::AND => opt_in
-                        .iter()
-                        .all(|item| categories.contains(item) || keywords.contains(item)),
-                    Operator::OR => opt_in
-                        .iter()
-                        .any(|item| categories.contains(item) || keywords.contains(item)),
+    // Get the items from the channels counting the ones that cannot be converted
+    let mut all_items: Vec<NewsItem> = Vec::new();
+    for (channel, status) in channels.iter().zip(statuses.iter_mut()) {
+        if let Some(channel) = channel {
+            for item in channel.items() {
+                match NewsItem::from_item(channel.title(), item) {
+                    Ok(news_item) => all_items.push(news_item),
+                    Err(err) => {
+                        log::error!("Could not get the item from the feed {}. ERROR: {}", status.url, err);
+                        status.dropped_items += 1;
+                    }
                 }
             }
-        });
+        }
+    }
+
+    // If there are no channels return None
+    if channels.iter().all(|channel| channel.is_none()) {
+        (None, statuses)
+    } else {
+        // Extract the entities mentioned in the title and description of the items
+        let gazetteer = Gazetteer::load();
+        for item in all_items.iter_mut() {
+            item.update_entities(&gazetteer);
+        }
+
+        // Retains the items that match the query
+        if let Some(query) = query {
+            log::trace!("Filtering items with the query: {}", query);
+            all_items.retain(|item| query.matches(item));
+        }
 
         // Shuffle the items
         all_items.shuffle(&mut rand::thread_rng());
 
-        Some(all_items)
+        (Some(all_items), statuses)
     }
 }
 
@@ -374,30 +458,9 @@ pub fn clean_content(channel: &String, content: String) -> Result<String, Pipeli
 pub async fn fill_news_item_content(news_item: &mut NewsItem) {
     let response = reqwest::get(&news_item.link).await;
     if let Ok(response) = response {
-        let content = response.text().await;
+        let content = warc::read_response_text(&news_item.link, response).await;
         if let Ok(content) = content {
-            let clean_content = clean_content(&news_item.channel, content);
-            match clean_content {
-                Ok(clean_content) => {
-                    // If clean_content is not empty, assign it to the news_item
-                    if !clean_content.is_empty() {
-                        news_item.clean_content = Some(clean_content);
-                    } else {
-                        log::error!(
-                            "Could not clean the content from {}. ERROR: {}",
-                            news_item.link,
-                            "Empty content"
-                        );
-                        news_item.clean_content = None;
-                        news_item.error = Some(PipelineError::NoContent);
-                    }
-                }
-                Err(err) => {
-                    log::error!("Could not clean the content from {}. ERROR: {:?}", news_item.link, err);
-                    news_item.clean_content = None;
-                    news_item.error = Some(err);
-                }
-            }
+            set_news_item_content(news_item, content);
         } else {
             let error = content.err().unwrap().to_string();
             log::error!("Could parse the content from {}. ERROR: {}", news_item.link, error);
@@ -412,6 +475,35 @@ pub async fn fill_news_item_content(news_item: &mut NewsItem) {
     }
 }
 
+/// Function that cleans the raw content fetched from the link of a NewsItem
+/// and sets its clean_content or error
+fn set_news_item_content(news_item: &mut NewsItem, content: String) {
+    // Keep the raw content so it can be stored as a snapshot
+    news_item.raw_html = Some(content.clone());
+    let clean_content = clean_content(&news_item.channel, content);
+    match clean_content {
+        Ok(clean_content) => {
+            // If clean_content is not empty, assign it to the news_item
+            if !clean_content.is_empty() {
+                news_item.clean_content = Some(clean_content);
+            } else {
+                log::error!(
+                    "Could not clean the content from {}. ERROR: {}",
+                    news_item.link,
+                    "Empty content"
+                );
+                news_item.clean_content = None;
+                news_item.error = Some(PipelineError::NoContent);
+            }
+        }
+        Err(err) => {
+            log::error!("Could not clean the content from {}. ERROR: {:?}", news_item.link, err);
+            news_item.clean_content = None;
+            news_item.error = Some(err);
+        }
+    }
+}
+
 // /// Function that returns the top k news items from the database
 // pub async fn top_k_news_items(top_k: u8, news_items: &Vec<NewsItem>) ->
 // Vec<NewsItem> {     // The top k news items are the ones with the highest
@@ -460,6 +552,7 @@ pub async fn top_k_news_items(top_k: u8, news_items:&[NewsItem]) -> Vec<NewsItem
 }
 
 /// Function that logs a vector of NewsItems to a file appending the contents
+/// rendered with the news_items.md template
 pub fn log_news_items_to_file(news_items: &[NewsItem], file: &str) {
     let mut file = std::fs::OpenOptions::new()
         .create(true)
@@ -469,52 +562,26 @@ pub fn log_news_items_to_file(news_items: &[NewsItem], file: &str) {
 
     // Order the news items by relevance
     let mut news_items = news_items.to_owned();
-    news_items.sort_by(|a, b| b.relevance.cmp(&a.relevance));
+    news_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
 
-    // Write table of contents
-    writeln!(file, "# Table of Contents").unwrap();
-    for (i,item) in news_items.iter().enumerate() {
-        writeln!(file, "{}. [{}]({})", i + 1, item.title, generate_anchor(&item.title)).unwrap();
-    }
-    writeln!(file).unwrap();
-
-    for item in news_items {
-        writeln!(file,"---").unwrap();
-        writeln!(file, "# {}", item.title).unwrap();
-        writeln!(file, "## Data").unwrap();
-        writeln!(file, "- **Channel:** {}", item.channel).unwrap();
-        writeln!(file, "- **Relevance:** {}", item.relevance.unwrap_or_default()).unwrap();
-        writeln!(file, "- **Link:** {}", item.link).unwrap();
-        writeln!(file, "- **Publish Date:** {:?}", item.pub_date).unwrap();
-        writeln!(file, "- **Categories:** {:?}", item.categories).unwrap();
-        writeln!(file, "- **Keywords:** {:?}", item.keywords).unwrap();
-        writeln!(file, "- **Error:** {:?}", item.error).unwrap();
-        writeln!(file, "## Description\n{}", &item.description.chars().take(50).collect::<String>()).unwrap();
-        match &item.clean_content {
-            Some(clean_content) => {
-                writeln!(file, "## Clean Content \n{}", clean_content).unwrap();
-            }
-            None => {
-                writeln!(file, "## Clean Content \nN/A").unwrap();
-            }
-        }
-        writeln!(file).unwrap();
-        
-    }
+    let model = ReportModel::new("News Items", &news_items);
+    write!(file, "{}", render_report("news_items.md", &model)).unwrap();
 }
 
-/// Function that generates a relevance report from a vector of NewsItems as a String
+/// Function that generates a relevance report from a vector of NewsItems as a
+/// String rendered with the relevance.md template
 pub fn generate_relevance_report(news_items: &[NewsItem]) -> String {
-    let mut report = String::new();
-
     // Order the news items by relevance
     let mut news_items = news_items.to_owned();
-    news_items.sort_by(|a, b| b.relevance.cmp(&a.relevance));
+    news_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
 
-    // Write table of contents
-    report.push_str("# Relevance Report\n");
-    report.push('\n');
+    let model = ReportModel::new("Relevance Report", &news_items).with_statistics(&news_items);
+    render_report("relevance.md", &model)
+}
 
+/// Function that returns the total relevance and the number of the news items
+/// per channel ordered by average relevance
+pub(crate) fn relevance_per_channel(news_items: &[NewsItem]) -> Vec<(String, (f64, usize))> {
     // Prepare a bucket for the relevance of the news items per channel
     let mut relevance_per_channel = std::collections::HashMap::new();
 
@@ -535,22 +602,7 @@ pub fn generate_relevance_report(news_items: &[NewsItem]) -> String {
         let relevance_b = b.1 .0 / b.1 .1 as f64;
         relevance_b.partial_cmp(&relevance_a).unwrap()
     });
-
-    // Write the relevance per channel
-    report.push_str("## Relevance per Channel\n\n");
-    for (channel, relevance) in relevance_per_channel.iter() {
-        report.push_str(&format!("- **{}:** Items: {} Total: {} Average: {}\n", channel, relevance.1, relevance.0, relevance.0/relevance.1 as f64));
-    }
-    report.push('\n');
-
-    // Write the news items with their relevance
-    report.push_str("## Relevance list\n");
-    for (i, item) in news_items.iter().enumerate() {
-        let relevance = item.relevance.unwrap_or_default();
-        report.push_str(&format!("{}. ({}) [{}] {}\n", i, relevance, item.channel, item.title));
-    }
-
-    report
+    relevance_per_channel
 }
 
 /// Function that writes a relevance report to a file and returns a Result
@@ -565,47 +617,8 @@ pub async fn log_relevance_report_to_file(report: &str, file: &str) -> Result<()
     Ok(())
 }
 
-/// Function that writes a slice of items to a CSV file
-pub fn write_news_items_to_csv(news_items: &[NewsItem], file: &str) -> Result<(), Box<dyn Error>> {
-    // Create a CSV writer that writes to the given file
-    let file = File::create(file)?;
-    let mut writer = Writer::from_writer(file);
-
-    // Write the header row
-    writer.write_record(&[
-        "Channel", "Title", "Link", "Description", "Creators", 
-        "Publication Date", "Categories", "Keywords", "Clean Content", 
-        "Error", "Feedback Date", "Relevance"
-    ])?;
-
-    // Feedback date with format: Sun, 01 Jan 2017 12:00:00 +0000
-    let feedback_date = chrono::Utc::now().to_rfc2822();
-
-    // Iterate over each NewsItem and write its fields to the CSV
-    for item in news_items {
-        writer.write_record(&[
-            &item.channel,
-            &item.title,
-            &item.link,
-            &item.description,
-            &item.creators,
-            item.pub_date.as_deref().unwrap_or(""),
-            item.categories.as_deref().unwrap_or(""),
-            item.keywords.as_deref().unwrap_or(""),
-            item.clean_content.as_deref().unwrap_or(""),
-            &format!("{:?}", item.error),
-            &feedback_date,
-            &item.relevance.map_or(String::new(), |r| r.to_string()),
-        ])?;
-    }
-
-    // Flush and finish writing
-    writer.flush()?;
-    Ok(())
-}
-
 /// Function that generates an anchor from a title
-fn generate_anchor(title: &str) -> String {
+pub(crate) fn generate_anchor(title: &str) -> String {
     // Convert to lowercase
     let mut anchor = title.to_lowercase();
     // Transliterate to ASCII
@@ -630,93 +643,165 @@ pub async fn log_report_to_file(report: &str, file: &str) -> Result<(), Box<dyn
     Ok(())
 }
 
-/// Function that generates a dossier report with a vector of news items
-pub fn generate_dossier_report(news_items: &Vec<NewsItem>) -> String {
-    let mut report = String::new();
+/// Function that generates a dossier from a vector of NewsItems as a String
+/// rendered with the dossier.md template
+pub fn generate_dossier_report(news_items: &[NewsItem]) -> String {
+    render_report("dossier.md", &ReportModel::new("Dossier", news_items))
+}
+
+/// Function that generates a dossier grouped in sections as a String rendered
+/// with the dossier.md template
+pub fn generate_sectioned_dossier_report(sections: &[NewsSection]) -> String {
+    render_report("dossier.md", &ReportModel::sectioned("Dossier", sections))
+}
+
+/// Function that logs vector of NewsItems into a sqlite database
+pub async fn log_news_items_to_db(news_items: &[NewsItem], db_file_name: &str) -> UpsertSummary {
+    // Open a connection to the database
+    let connection = sqlite::open(db_file_name).unwrap();
 
-    // Header of the dossier
-    report.push_str("# Dossier\n\n");
+    // Create the table, the report database does not need the archive tables
+    NewsItem::create_table(&connection).unwrap();
 
-    // Write table of contents
-    report.push_str("## Table of Contents\n");
-    for (i,item) in news_items.iter().enumerate() {
-        report.push_str(&format!("{}. [{}]({})\n", i + 1, item.title, generate_anchor(&item.title)));
+    // Insert the news items into the database
+    upsert_news_items(news_items, |news_item| Ok(news_item.upsert(&connection)?))
+}
+
+/// Function that loads the NewsItems stored in a report or archive database
+/// matching the query passed as a reference or all of them if there is no
+/// query. The entities are extracted again so the items can be rescored and
+/// the reports regenerated offline
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+///
+/// let result = load_news_items_from_db("does-not-exist.db", None);
+/// assert!(result.is_err());
+/// ```
+pub fn load_news_items_from_db(db_file_name: &str, query: Option<&Query>) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    // Do not create an empty database if the path is wrong
+    if !std::path::Path::new(db_file_name).exists() {
+        return Err(format!("The database {} does not exist", db_file_name).into());
     }
-    report.push('\n');
-
-    // Write metadata of the dossier
-    report.push_str("## Metadata\n");
-    report.push_str(&format!("- **Number of items:** {}\n", news_items.len()));
-    report.push_str(&format!("- **Date:** {:?}\n", chrono::Local::now()));
-    report.push('\n');
-
-    // Write the news items
-    report.push_str("## News Items\n");
-
-    for item in news_items {
-        report.push_str("---\n");
-        report.push_str(&format!("### {}\n", item.title));
-        report.push('\n');
-
-        report.push_str("#### Data\n");
-        report.push_str(&format!("- **Channel:** {}\n", item.channel));
-        report.push_str(&format!("- **Relevance:** {}\n", item.relevance.unwrap_or_default()));
-        report.push_str(&format!("- **Link:** {}\n", item.link));
-        report.push_str(&format!("- **Publish Date:** {:?}\n", item.pub_date));
-        report.push_str(&format!("- **Categories:** {:?}\n", item.categories));
-        report.push_str(&format!("- **Keywords:** {:?}\n", item.keywords));
-        report.push_str(&format!("- **Error:** {:?}\n", item.error));
-        report.push('\n');
-        
-        // report.push_str("#### Description\n{}", &item.description);
-        // report.push_str(file);
-
-        match &item.clean_content {
-            Some(clean_content) => {
-                report.push_str(&format!("#### Clean Content \n{}", clean_content));
+
+    // Open the database and migrate it to the latest schema
+    let store = Archive::open(db_file_name)?;
+    let news_items = load_news_items_from_store(&store, query)?;
+    log::info!("Items loaded from {}: {}", db_file_name, news_items.len());
+    Ok(news_items)
+}
+
+/// Function that loads the NewsItems of a store matching the query passed as
+/// a reference or all of them if there is no query
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::store::MemoryStore;
+///
+/// let store = MemoryStore::new();
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     link: "https://www.acme.es/1.html".to_string(),
+///     ..Default::default()
+/// };
+/// insert_news_items(&vec![news_item], &store);
+/// let query = Query::parse("title:inflación").unwrap();
+/// let news_items = load_news_items_from_store(&store, Some(&query)).unwrap();
+/// assert_eq!(news_items.len(), 1);
+/// ```
+pub fn load_news_items_from_store(store: &dyn NewsStore, query: Option<&Query>) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    let mut news_items = store.query(&NewsItemQuery::new())?;
+
+    // Extract the entities with the current gazetteer and filter in
+    let gazetteer = Gazetteer::load();
+    for news_item in news_items.iter_mut() {
+        news_item.update_entities(&gazetteer);
+    }
+    if let Some(query) = query {
+        news_items.retain(|news_item| query.matches(news_item));
+    }
+
+    Ok(news_items)
+}
+
+/// Function that loads the NewsItems recorded in a WARC file matching the
+/// query passed as a reference or all of them if there is no query. The items
+/// are read from the recorded feeds and their contents from the recorded
+/// articles, so the reports can be regenerated offline
+pub fn load_news_items_from_warc(warc_file_name: &str, query: Option<&Query>) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+    let responses = warc::read_warc_responses(std::path::Path::new(warc_file_name))?;
+
+    // The responses that parse as feeds give the items, the rest the contents
+    let mut news_items = Vec::new();
+    let mut contents = std::collections::HashMap::new();
+    for (url, body) in responses {
+        match Channel::read_from(body.as_bytes()) {
+            Ok(channel) => {
+                for item in channel.items() {
+                    match NewsItem::from_item(channel.title(), item) {
+                        Ok(news_item) => news_items.push(news_item),
+                        Err(err) => log::error!("Could not get the item from {}. ERROR: {}", url, err),
+                    }
+                }
             }
-            None => {
-                report.push_str("#### Clean Content \nN/A");
+            Err(_) => {
+                contents.insert(url, body);
             }
         }
-        report.push('\n');
-        
     }
+    log::info!("Items loaded from {}: {}", warc_file_name, news_items.len());
 
-    report
-}
+    // Extract the entities and filter in before filling the contents
+    let gazetteer = Gazetteer::load();
+    for news_item in news_items.iter_mut() {
+        news_item.update_entities(&gazetteer);
+    }
+    if let Some(query) = query {
+        news_items.retain(|news_item| query.matches(news_item));
+    }
 
-/// Function that logs vector of NewsItems into a sqlite database
-pub async fn log_news_items_to_db(news_items: &Vec<NewsItem>, db_file_name: &str) -> usize {
-    // Open a connection to the database
-    let connection = sqlite::open(db_file_name).unwrap();
+    for news_item in news_items.iter_mut() {
+        if let Some(content) = contents.get(&news_item.link) {
+            set_news_item_content(news_item, content.clone());
+            news_item.update_entities(&gazetteer);
+        }
+    }
 
-    // Create the table
-    NewsItem::create_table(&connection).unwrap();
+    Ok(news_items)
+}
 
-    // Insert the news items into the database
-    insert_news_items(news_items, &connection)
+/// Function that upserts a vector of NewsItems into a store and returns the
+/// counts of inserted, updated and unchanged items
+pub fn insert_news_items(news_items: &[NewsItem], store: &dyn NewsStore) -> UpsertSummary {
+    upsert_news_items(news_items, |news_item| store.upsert(news_item))
 }
 
-/// Function that inserts a vector of NewsItems into a database
-pub fn insert_news_items(news_items: &Vec<NewsItem>, connection: &sqlite::Connection) -> usize {
-    let mut count = 0;
+/// Function that upserts a vector of NewsItems with the function passed and
+/// summarizes the outcomes
+fn upsert_news_items<F>(news_items: &[NewsItem], upsert: F) -> UpsertSummary
+where
+    F: Fn(&NewsItem) -> Result<UpsertOutcome, Box<dyn Error>>,
+{
+    let mut summary = UpsertSummary::default();
     for news_item in news_items {
-        match news_item.insert(connection) {
+        match upsert(news_item) {
             Err(err) => {
                 log::error!(
-                    "Could not insert the NewsItem -> channel: {} link: {}. ERROR: {}",
+                    "Could not upsert the NewsItem -> channel: {} link: {}. ERROR: {}",
                     news_item.channel,
                     news_item.link,
-                    err.message.unwrap_or("no message".to_string())
+                    err
                 );
+                summary.failed += 1;
             }
-            _ => {
-                count += 1;
+            Ok(outcome) => {
+                summary.add(outcome);
             }
         }
     }
-    count
+    summary
 }
 
 /// Function that given a vector of NewsItems fills the clean_content field of all of them
@@ -812,7 +897,7 @@ pub async fn update_news_items_with_relevance_top_k(
     );
 
     // Order updated items by relevance and take the top 100
-    updated_items.sort_by(|a, b| b.relevance.cmp(&a.relevance));
+    updated_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
 
     // Take the top k items
     let top_k_items = updated_items.into_iter().take(k).collect::<Vec<NewsItem>>();
@@ -1054,4 +1139,65 @@ mod tests {
             "# Welcome to Example Page\n\nThis is a paragraph with **bold** text.\n\nThis is a paragraph with *italic* text.\n\nThis is a paragraph with *italic* text and an emoji 😊.\n\nPárrafo con caracteres especiales como: ñéåîü€@.\n\n* Item one\n* Item two\n* Item three\n"
         );
     }
+
+    // Test loading the items of a report database and filtering them in
+    #[test]
+    fn test_load_news_items_from_db() {
+        let db_file = ".load_news_items.db";
+        let _ = std::fs::remove_file(db_file);
+        let connection = sqlite::open(db_file).unwrap();
+        NewsItem::create_table(&connection).unwrap();
+        for (i, title) in ["La inflación baja", "El partido"].iter().enumerate() {
+            let news_item = NewsItem {
+                channel: "Other".to_string(),
+                title: title.to_string(),
+                link: format!("https://www.acme.es/{}.html", i),
+                categories: Some(if i == 0 { "economía" } else { "deportes" }.to_string()),
+                clean_content: Some("Contenido".to_string()),
+                relevance: Some(10),
+                ..Default::default()
+            };
+            news_item.insert(&connection).unwrap();
+        }
+        drop(connection);
+
+        let news_items = load_news_items_from_db(db_file, None).unwrap();
+        assert_eq!(news_items.len(), 2);
+
+        let query = Query::parse("economía").unwrap();
+        let news_items = load_news_items_from_db(db_file, Some(&query)).unwrap();
+        std::fs::remove_file(db_file).unwrap();
+        assert_eq!(news_items.len(), 1);
+        assert_eq!(news_items[0].title, "La inflación baja");
+        assert_eq!(news_items[0].clean_content, Some("Contenido".to_string()));
+    }
+
+    // Test loading the items and contents recorded in a WARC file
+    #[test]
+    fn test_load_news_items_from_warc() {
+        let warc_file = ".load_news_items.warc.gz";
+        let feed = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0"><channel><title>Other</title><link>https://www.acme.es</link><description>Acme</description>
+            <item><title>La inflación baja</title><link>https://www.acme.es/1.html</link><description>Economía</description></item>
+            <item><title>El partido</title><link>https://www.acme.es/2.html</link><description>Deportes</description></item>
+            </channel></rss>"#;
+        let head = "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=utf-8\r\n\r\n";
+        let mut writer = warc::WarcWriter::create(std::path::Path::new(warc_file)).unwrap();
+        writer.write_exchange("https://www.acme.es/rss", head, feed.as_bytes()).unwrap();
+        writer
+            .write_exchange("https://www.acme.es/1.html", head, "<body><p>Los precios bajan.</p></body>".as_bytes())
+            .unwrap();
+        drop(writer);
+
+        let news_items = load_news_items_from_warc(warc_file, None).unwrap();
+        let query = Query::parse("title:inflación").unwrap();
+        let filtered = load_news_items_from_warc(warc_file, Some(&query)).unwrap();
+        std::fs::remove_file(warc_file).unwrap();
+
+        assert_eq!(news_items.len(), 2);
+        assert_eq!(news_items[0].clean_content, Some("Los precios bajan.\n".to_string()));
+        assert!(news_items[1].clean_content.is_none());
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(filtered[0].title, "La inflación baja");
+    }
 }
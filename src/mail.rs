@@ -0,0 +1,236 @@
+/// Module for distributing the dossiers by email as multipart/alternative MIME
+/// messages, with the Markdown dossier as text/plain and the HTML dossier as
+/// text/html, written as .eml files or delivered through SMTP or sendmail
+use std::error::Error;
+use std::str::FromStr;
+
+use lettre::message::{Mailbox, MultiPart};
+use lettre::{Message, SendmailTransport, SmtpTransport, Transport};
+
+use crate::common::NewsItem;
+use crate::html::{generate_dossier_html, generate_sectioned_dossier_html};
+use crate::section::NewsSection;
+use crate::{generate_dossier_report, generate_sectioned_dossier_report};
+
+/// Default port of the SMTP servers
+pub const DEFAULT_SMTP_PORT: u16 = 25;
+
+/// Struct that represents the headers of the dossier emails
+#[derive(Debug, Clone)]
+pub struct MailOptions {
+    pub from: String,
+    pub to: Vec<String>,
+    pub subject: String,
+}
+
+impl Default for MailOptions {
+    fn default() -> Self {
+        MailOptions {
+            from: "Hemeroteca <hemeroteca@localhost>".to_string(),
+            to: Vec::new(),
+            subject: "Hemeroteca dossier".to_string(),
+        }
+    }
+}
+
+/// Enum that represents how the dossier emails are delivered
+#[derive(Debug, Clone, PartialEq)]
+pub enum MailDelivery {
+    /// Plain SMTP server without authentication, e.g. a local relay
+    Smtp { host: String, port: u16 },
+    /// sendmail compatible binary, the one in the PATH if there is no path
+    Sendmail(Option<String>),
+}
+
+impl FromStr for MailDelivery {
+    type Err = Box<dyn Error>;
+
+    /// Function that returns the SMTP delivery of a `host[:port]` address
+    fn from_str(address: &str) -> Result<Self, Self::Err> {
+        let (host, port) = match address.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().map_err(|_| format!("Invalid SMTP port: {}", port))?),
+            None => (address, DEFAULT_SMTP_PORT),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid SMTP address: {}", address).into());
+        }
+        Ok(MailDelivery::Smtp {
+            host: host.to_string(),
+            port,
+        })
+    }
+}
+
+// Function that builds a multipart/alternative MIME message with the plain
+// and HTML bodies and the headers of the options
+fn alternative_message(plain: String, html: String, options: &MailOptions) -> Result<Message, Box<dyn Error>> {
+    if options.to.is_empty() {
+        return Err("The dossier email needs at least one recipient".into());
+    }
+
+    let mut builder = Message::builder().from(options.from.parse::<Mailbox>()?).subject(&options.subject);
+    for to in options.to.iter() {
+        builder = builder.to(to.parse::<Mailbox>()?);
+    }
+    Ok(builder.multipart(MultiPart::alternative_plain_html(plain, html))?)
+}
+
+/// Function that builds the dossier of the NewsItems as a multipart/alternative
+/// MIME message with the headers of the options
+pub fn dossier_message(news_items: &[NewsItem], options: &MailOptions) -> Result<Message, Box<dyn Error>> {
+    alternative_message(generate_dossier_report(news_items), generate_dossier_html(news_items), options)
+}
+
+/// Function that builds the dossier grouped in sections as a
+/// multipart/alternative MIME message with the headers of the options
+pub fn sectioned_dossier_message(sections: &[NewsSection], options: &MailOptions) -> Result<Message, Box<dyn Error>> {
+    alternative_message(generate_sectioned_dossier_report(sections), generate_sectioned_dossier_html(sections), options)
+}
+
+/// Function that generates the dossier of the NewsItems as the bytes of an
+/// .eml file
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::mail::{generate_dossier_eml, MailOptions};
+///
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     ..Default::default()
+/// };
+/// let options = MailOptions {
+///     to: vec!["redaccion@acme.es".to_string()],
+///     ..Default::default()
+/// };
+/// let eml = String::from_utf8(generate_dossier_eml(&[news_item], &options).unwrap()).unwrap();
+/// assert!(eml.contains("Content-Type: multipart/alternative"));
+/// assert!(eml.contains("To: redaccion@acme.es"));
+/// ```
+pub fn generate_dossier_eml(news_items: &[NewsItem], options: &MailOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    Ok(dossier_message(news_items, options)?.formatted())
+}
+
+/// Function that delivers the dossier of the NewsItems by email
+pub fn send_dossier(news_items: &[NewsItem], options: &MailOptions, delivery: &MailDelivery) -> Result<(), Box<dyn Error>> {
+    send_message(&dossier_message(news_items, options)?, delivery)
+}
+
+/// Function that delivers a message, e.g. a sectioned dossier, by email
+pub fn send_message(message: &Message, delivery: &MailDelivery) -> Result<(), Box<dyn Error>> {
+    match delivery {
+        MailDelivery::Smtp { host, port } => {
+            SmtpTransport::builder_dangerous(host).port(*port).build().send(message)?;
+        }
+        MailDelivery::Sendmail(Some(command)) => SendmailTransport::new_with_command(command).send(message)?,
+        MailDelivery::Sendmail(None) => SendmailTransport::new().send(message)?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    fn news_items() -> Vec<NewsItem> {
+        vec![NewsItem {
+            channel: "El País".to_string(),
+            title: "La inflación <baja>".to_string(),
+            link: "https://www.acme.es/1.html".to_string(),
+            clean_content: Some("Contenido".to_string()),
+            relevance: Some(10),
+            ..Default::default()
+        }]
+    }
+
+    fn options() -> MailOptions {
+        MailOptions {
+            from: "Hemeroteca <hemeroteca@acme.es>".to_string(),
+            to: vec!["redaccion@acme.es".to_string(), "Dirección <direccion@acme.es>".to_string()],
+            subject: "Dossier del día".to_string(),
+        }
+    }
+
+    // Function that starts a local SMTP stand-in accepting one message and
+    // returns its port and the handle to get the envelope and the data
+    fn smtp_stand_in() -> (u16, std::thread::JoinHandle<(Vec<String>, String)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut writer = stream.try_clone().unwrap();
+            let mut reader = BufReader::new(stream);
+            let (mut envelope, mut data) = (Vec::new(), String::new());
+            writer.write_all(b"220 localhost ESMTP\r\n").unwrap();
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let command = line.trim_end().to_string();
+                line.clear();
+                let reply: &[u8] = match command.split(' ').next().unwrap_or_default().to_uppercase().as_str() {
+                    "EHLO" | "HELO" => b"250 localhost\r\n",
+                    "MAIL" | "RCPT" => {
+                        envelope.push(command);
+                        b"250 OK\r\n"
+                    }
+                    "DATA" => {
+                        writer.write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n").unwrap();
+                        while reader.read_line(&mut line).unwrap() > 0 && line != ".\r\n" {
+                            data.push_str(&line);
+                            line.clear();
+                        }
+                        line.clear();
+                        b"250 OK\r\n"
+                    }
+                    "QUIT" => {
+                        writer.write_all(b"221 Bye\r\n").unwrap();
+                        break;
+                    }
+                    _ => b"250 OK\r\n",
+                };
+                writer.write_all(reply).unwrap();
+            }
+            (envelope, data)
+        });
+        (port, handle)
+    }
+
+    #[test]
+    fn test_generate_dossier_eml() {
+        let eml = String::from_utf8(generate_dossier_eml(&news_items(), &options()).unwrap()).unwrap();
+        assert!(eml.contains("From: Hemeroteca <hemeroteca@acme.es>"));
+        assert!(eml.contains("Subject: "));
+        assert!(eml.contains("Content-Type: multipart/alternative"));
+        let plain = eml.find("Content-Type: text/plain").unwrap();
+        let html = eml.find("Content-Type: text/html").unwrap();
+        assert!(plain < html);
+
+        // A message without recipients is not generated
+        let options = MailOptions::default();
+        assert!(generate_dossier_eml(&news_items(), &options).is_err());
+    }
+
+    #[test]
+    fn test_mail_delivery_from_str() {
+        let delivery = "localhost:2525".parse::<MailDelivery>().unwrap();
+        assert_eq!(delivery, MailDelivery::Smtp { host: "localhost".to_string(), port: 2525 });
+        let delivery = "smtp.acme.es".parse::<MailDelivery>().unwrap();
+        assert_eq!(delivery, MailDelivery::Smtp { host: "smtp.acme.es".to_string(), port: DEFAULT_SMTP_PORT });
+        assert!("localhost:smtp".parse::<MailDelivery>().is_err());
+        assert!(":25".parse::<MailDelivery>().is_err());
+    }
+
+    #[test]
+    fn test_send_dossier_smtp() {
+        let (port, handle) = smtp_stand_in();
+        let delivery = MailDelivery::Smtp { host: "127.0.0.1".to_string(), port };
+        send_dossier(&news_items(), &options(), &delivery).unwrap();
+
+        let (envelope, data) = handle.join().unwrap();
+        assert_eq!(envelope[0], "MAIL FROM:<hemeroteca@acme.es>");
+        assert_eq!(&envelope[1..], ["RCPT TO:<redaccion@acme.es>", "RCPT TO:<direccion@acme.es>"]);
+        assert!(data.contains("Content-Type: multipart/alternative"));
+        assert!(data.contains("Content-Type: text/html"));
+    }
+}
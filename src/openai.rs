@@ -1,6 +1,6 @@
 use serde_json::json;
 
-/// Module for OpenAI related functions
+// Module for OpenAI related functions
 
 /// Function that given a text returns a summary
 pub async fn summarize(text: &str, api_key: &str) -> Result<String, reqwest::Error> {
@@ -0,0 +1,347 @@
+/// Module for publishing the dossier items as a rolling RSS 2.0 or Atom feed
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::common::NewsItem;
+use crate::storage::parse_pub_date;
+
+/// Prefix of the hemeroteca extension elements, e.g. `<hemeroteca:relevance>`
+pub const HEMEROTECA_PREFIX: &str = "hemeroteca";
+
+/// Namespace of the hemeroteca extension elements
+pub const HEMEROTECA_NAMESPACE: &str = "urn:hemeroteca:1.0";
+
+/// Default number of items kept in a published feed
+pub const DEFAULT_FEED_MAX_ITEMS: usize = 100;
+
+/// Enum that represents the format of a published feed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedFormat {
+    Rss,
+    Atom,
+}
+
+/// Struct with the options of a published feed
+#[derive(Debug, Clone)]
+pub struct FeedOptions {
+    pub title: String,
+    pub link: String,
+    pub description: String,
+    /// Maximum number of items kept across runs, the oldest are dropped
+    pub max_items: usize,
+}
+
+impl Default for FeedOptions {
+    fn default() -> Self {
+        FeedOptions {
+            title: "Hemeroteca dossier".to_string(),
+            link: "http://localhost/".to_string(),
+            description: "Most relevant news items of the hemeroteca dossiers".to_string(),
+            max_items: DEFAULT_FEED_MAX_ITEMS,
+        }
+    }
+}
+
+// Function that puts the new entries first and then the previous ones not
+// published again, keeping at most max_items
+fn roll<T>(new: Vec<T>, previous: Vec<T>, key: impl Fn(&T) -> String, max_items: usize) -> Vec<T> {
+    let published = new.iter().map(&key).collect::<HashSet<String>>();
+    new.into_iter()
+        .chain(previous.into_iter().filter(|entry| !published.contains(&key(entry))))
+        .take(max_items)
+        .collect()
+}
+
+// Function that writes a file replacing it at once so readers never see it
+// half written
+fn replace_file(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let tmp = path.with_extension("tmp");
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(tmp, path)
+}
+
+// Function that returns the values of the hemeroteca extension elements of a
+// NewsItem by name
+fn extension_values(news_item: &NewsItem) -> Vec<(&str, String)> {
+    let mut values = vec![("channel", news_item.channel.clone())];
+    if let Some(relevance) = news_item.relevance {
+        values.push(("relevance", relevance.to_string()));
+    }
+    values
+}
+
+/// Function that returns the RSS item of a NewsItem with the clean content as
+/// `content:encoded` and the channel and relevance as `hemeroteca:channel` and
+/// `hemeroteca:relevance`
+pub fn rss_item(news_item: &NewsItem) -> rss::Item {
+    let elements = extension_values(news_item)
+        .into_iter()
+        .map(|(name, value)| {
+            let extension = rss::extension::ExtensionBuilder::default()
+                .name(format!("{}:{}", HEMEROTECA_PREFIX, name))
+                .value(Some(value))
+                .build();
+            (name.to_string(), vec![extension])
+        })
+        .collect();
+    let extensions = BTreeMap::from([(HEMEROTECA_PREFIX.to_string(), elements)]);
+    let dublin_core = Some(news_item.creators.clone())
+        .filter(|creators| !creators.is_empty())
+        .map(|creators| rss::extension::dublincore::DublinCoreExtension {
+            creators: creators.split(',').map(|creator| creator.trim().to_string()).collect(),
+            ..Default::default()
+        });
+
+    rss::ItemBuilder::default()
+        .title(Some(news_item.title.clone()))
+        .link(Some(news_item.link.clone()))
+        .guid(Some(rss::Guid {
+            value: news_item.link.clone(),
+            permalink: true,
+        }))
+        .description(Some(news_item.description.clone()))
+        .pub_date(news_item.pub_date.clone())
+        .categories(
+            news_item
+                .categories
+                .iter()
+                .flat_map(|categories| categories.split(','))
+                .map(|category| rss::Category {
+                    name: category.to_string(),
+                    domain: None,
+                })
+                .collect::<Vec<rss::Category>>(),
+        )
+        .content(news_item.clean_content.clone())
+        .dublin_core_ext(dublin_core)
+        .extensions(extensions)
+        .build()
+}
+
+/// Function that returns the Atom entry of a NewsItem with the clean content
+/// as content and the channel and relevance as `hemeroteca:channel` and
+/// `hemeroteca:relevance`
+pub fn atom_entry(news_item: &NewsItem, updated: atom_syndication::FixedDateTime) -> atom_syndication::Entry {
+    let elements = extension_values(news_item)
+        .into_iter()
+        .map(|(name, value)| {
+            let extension = atom_syndication::extension::Extension {
+                name: format!("{}:{}", HEMEROTECA_PREFIX, name),
+                value: Some(value),
+                ..Default::default()
+            };
+            (name.to_string(), vec![extension])
+        })
+        .collect();
+    let extensions = BTreeMap::from([(HEMEROTECA_PREFIX.to_string(), elements)]);
+    let published = parse_pub_date(news_item.pub_date.as_deref());
+
+    atom_syndication::Entry {
+        title: atom_syndication::Text::plain(news_item.title.clone()),
+        id: news_item.link.clone(),
+        updated: published.unwrap_or(updated),
+        published,
+        authors: news_item
+            .creators
+            .split(',')
+            .filter(|creator| !creator.trim().is_empty())
+            .map(|creator| atom_syndication::Person {
+                name: creator.trim().to_string(),
+                ..Default::default()
+            })
+            .collect(),
+        categories: news_item
+            .categories
+            .iter()
+            .flat_map(|categories| categories.split(','))
+            .map(|category| atom_syndication::Category {
+                term: category.to_string(),
+                ..Default::default()
+            })
+            .collect(),
+        links: vec![atom_syndication::Link {
+            href: news_item.link.clone(),
+            ..Default::default()
+        }],
+        summary: Some(atom_syndication::Text::plain(news_item.description.clone())),
+        content: news_item.clean_content.as_ref().map(|clean_content| atom_syndication::Content {
+            value: Some(clean_content.clone()),
+            content_type: Some("text".to_string()),
+            ..Default::default()
+        }),
+        extensions,
+        ..Default::default()
+    }
+}
+
+/// Function that publishes the NewsItems in a rolling RSS 2.0 feed. The
+/// items of the previous runs stored in the file are kept after the new ones
+/// and returns the number of items of the feed
+pub fn publish_rss(news_items: &[NewsItem], path: &Path, options: &FeedOptions) -> Result<usize, Box<dyn Error>> {
+    let previous = if path.exists() {
+        rss::Channel::read_from(BufReader::new(std::fs::File::open(path)?))?.items
+    } else {
+        Vec::new()
+    };
+    let key = |item: &rss::Item| item.guid.as_ref().map(|guid| guid.value.clone()).unwrap_or_default();
+    let items = roll(news_items.iter().map(rss_item).collect(), previous, key, options.max_items);
+
+    let channel = rss::ChannelBuilder::default()
+        .title(options.title.clone())
+        .link(options.link.clone())
+        .description(options.description.clone())
+        .generator(Some("hemeroteca".to_string()))
+        .last_build_date(Some(chrono::Utc::now().to_rfc2822()))
+        .namespaces(BTreeMap::from([(
+            HEMEROTECA_PREFIX.to_string(),
+            HEMEROTECA_NAMESPACE.to_string(),
+        )]))
+        .items(items)
+        .build();
+    replace_file(path, channel.to_string().as_bytes())?;
+    Ok(channel.items.len())
+}
+
+/// Function that publishes the NewsItems in a rolling Atom feed. The entries
+/// of the previous runs stored in the file are kept after the new ones and
+/// returns the number of entries of the feed
+pub fn publish_atom(news_items: &[NewsItem], path: &Path, options: &FeedOptions) -> Result<usize, Box<dyn Error>> {
+    let previous = if path.exists() {
+        atom_syndication::Feed::read_from(BufReader::new(std::fs::File::open(path)?))?.entries
+    } else {
+        Vec::new()
+    };
+    let updated = chrono::Utc::now().fixed_offset();
+    let entries = roll(
+        news_items.iter().map(|news_item| atom_entry(news_item, updated)).collect(),
+        previous,
+        |entry: &atom_syndication::Entry| entry.id.clone(),
+        options.max_items,
+    );
+
+    let feed = atom_syndication::Feed {
+        title: atom_syndication::Text::plain(options.title.clone()),
+        id: options.link.clone(),
+        updated,
+        subtitle: Some(atom_syndication::Text::plain(options.description.clone())),
+        links: vec![atom_syndication::Link {
+            href: options.link.clone(),
+            ..Default::default()
+        }],
+        generator: Some(atom_syndication::Generator {
+            value: "hemeroteca".to_string(),
+            ..Default::default()
+        }),
+        namespaces: BTreeMap::from([(HEMEROTECA_PREFIX.to_string(), HEMEROTECA_NAMESPACE.to_string())]),
+        entries,
+        ..Default::default()
+    };
+    replace_file(path, feed.to_string().as_bytes())?;
+    Ok(feed.entries.len())
+}
+
+/// Function that publishes the NewsItems in a rolling feed of the format
+/// passed and returns the number of items of the feed
+///
+/// Example:
+/// ```
+/// use std::path::Path;
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::publish::{publish_feed, FeedFormat, FeedOptions};
+///
+/// let news_item = NewsItem {
+///     title: "La inflación baja".to_string(),
+///     link: "https://www.acme.es/1.html".to_string(),
+///     relevance: Some(10),
+///     ..Default::default()
+/// };
+/// let path = Path::new(".doctest-feed.rss");
+/// let count = publish_feed(&[news_item], path, FeedFormat::Rss, &FeedOptions::default()).unwrap();
+/// assert_eq!(count, 1);
+/// std::fs::remove_file(path).unwrap();
+/// ```
+pub fn publish_feed(
+    news_items: &[NewsItem],
+    path: &Path,
+    format: FeedFormat,
+    options: &FeedOptions,
+) -> Result<usize, Box<dyn Error>> {
+    match format {
+        FeedFormat::Rss => publish_rss(news_items, path, options),
+        FeedFormat::Atom => publish_atom(news_items, path, options),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn news_item(link: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: "El País".to_string(),
+            title: title.to_string(),
+            link: link.to_string(),
+            description: "Descripción".to_string(),
+            creators: "Ana,Luis".to_string(),
+            pub_date: Some("Mon, 01 Jan 2024 10:00:00 +0000".to_string()),
+            categories: Some("economía,política".to_string()),
+            clean_content: Some("Contenido & más".to_string()),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_publish_rss_rolls_the_items() {
+        let path = Path::new(".test-feed.rss");
+        let _ = std::fs::remove_file(path);
+        let options = FeedOptions {
+            max_items: 3,
+            ..Default::default()
+        };
+
+        let first = vec![news_item("https://www.acme.es/a.html", "A", 10), news_item("https://www.acme.es/b.html", "B", 5)];
+        assert_eq!(publish_rss(&first, path, &options).unwrap(), 2);
+        let second = vec![news_item("https://www.acme.es/c.html", "C", 30), news_item("https://www.acme.es/a.html", "A (updated)", 20)];
+        assert_eq!(publish_rss(&second, path, &options).unwrap(), 3);
+        let third = vec![news_item("https://www.acme.es/d.html", "D", 1)];
+        assert_eq!(publish_rss(&third, path, &options).unwrap(), 3);
+
+        let xml = std::fs::read_to_string(path).unwrap();
+        let channel = rss::Channel::read_from(xml.as_bytes()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let titles = channel.items.iter().map(|item| item.title.clone().unwrap()).collect::<Vec<String>>();
+        assert_eq!(titles, vec!["D", "C", "A (updated)"]);
+        assert!(xml.contains("xmlns:hemeroteca=\"urn:hemeroteca:1.0\""));
+        assert!(xml.contains("<hemeroteca:relevance>20</hemeroteca:relevance>"));
+        assert!(xml.contains("<hemeroteca:channel>El País</hemeroteca:channel>"));
+        assert_eq!(channel.items[1].content(), Some("Contenido & más"));
+        assert_eq!(channel.items[1].categories.len(), 2);
+        assert_eq!(channel.items[1].dublin_core_ext().unwrap().creators, vec!["Ana", "Luis"]);
+    }
+
+    #[test]
+    fn test_publish_atom_rolls_the_entries() {
+        let path = Path::new(".test-feed.atom");
+        let _ = std::fs::remove_file(path);
+        let options = FeedOptions::default();
+
+        publish_atom(&[news_item("https://www.acme.es/a.html", "A", 10)], path, &options).unwrap();
+        let count = publish_atom(&[news_item("https://www.acme.es/b.html", "B", 20)], path, &options).unwrap();
+        assert_eq!(count, 2);
+
+        let xml = std::fs::read_to_string(path).unwrap();
+        let feed = atom_syndication::Feed::read_from(xml.as_bytes()).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(feed.entries[0].id, "https://www.acme.es/b.html");
+        assert_eq!(feed.entries[1].id, "https://www.acme.es/a.html");
+        assert!(xml.contains("<hemeroteca:relevance>10</hemeroteca:relevance>"));
+        assert_eq!(feed.entries[0].published.unwrap().to_rfc2822(), "Mon, 1 Jan 2024 10:00:00 +0000");
+        assert_eq!(feed.entries[0].content.as_ref().unwrap().value.as_deref(), Some("Contenido & más"));
+        assert_eq!(feed.entries[0].authors.len(), 2);
+    }
+}
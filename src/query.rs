@@ -0,0 +1,618 @@
+//! Module for the boolean query language used to filter NewsItems
+//!
+//! A query is a combination of terms with `AND`, `OR`, `NOT` and parentheses.
+//! The operators are written in uppercase, in lowercase they are plain words.
+//! Two terms without an operator between them are combined with `AND`. A term
+//! is a word or a quoted phrase, optionally prefixed by a field and suffixed by
+//! `*` to match word prefixes instead of whole words:
+//!
+//! `(economía OR inflación*) AND NOT category:deportes AND channel:"el país"`
+//!
+//! Terms without a field are matched against the categories, keywords and
+//! entities of the NewsItem.
+use std::error::Error;
+use std::fmt::Display;
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{NewsItem, Operator};
+
+/// Enum that represents the fields a term can be matched against
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Default,
+    Title,
+    Description,
+    Channel,
+    Category,
+    Keyword,
+    Entity,
+    Creator,
+    Content,
+}
+
+impl Field {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Field::Default => "",
+            Field::Title => "title",
+            Field::Description => "description",
+            Field::Channel => "channel",
+            Field::Category => "category",
+            Field::Keyword => "keyword",
+            Field::Entity => "entity",
+            Field::Creator => "creator",
+            Field::Content => "content",
+        }
+    }
+
+    /// Function that returns the values of the field in a NewsItem. Values are
+    /// kept apart so phrases don't match across categories or keywords
+    fn values(&self, news_item: &NewsItem) -> Vec<String> {
+        let split = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(|v| v.split(',').map(String::from).collect::<Vec<String>>())
+                .unwrap_or_default()
+        };
+        match self {
+            Field::Default => [
+                Field::Category.values(news_item),
+                Field::Keyword.values(news_item),
+                Field::Entity.values(news_item),
+            ]
+            .concat(),
+            Field::Title => vec![news_item.title.clone()],
+            Field::Description => vec![news_item.description.clone()],
+            Field::Channel => vec![news_item.channel.clone()],
+            Field::Category => split(&news_item.categories),
+            Field::Keyword => split(&news_item.keywords),
+            Field::Entity => news_item.entities.iter().map(|entity| entity.name.clone()).collect(),
+            Field::Creator => news_item.creators.split(',').map(String::from).collect(),
+            Field::Content => news_item.clean_content.iter().cloned().collect(),
+        }
+    }
+}
+
+impl FromStr for Field {
+    type Err = QueryError;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        match field.to_lowercase().as_str() {
+            "title" => Ok(Field::Title),
+            "description" => Ok(Field::Description),
+            "channel" => Ok(Field::Channel),
+            "category" => Ok(Field::Category),
+            "keyword" => Ok(Field::Keyword),
+            "entity" => Ok(Field::Entity),
+            "creator" => Ok(Field::Creator),
+            "content" => Ok(Field::Content),
+            _ => Err(QueryError::UnknownField(field.to_string())),
+        }
+    }
+}
+
+/// Enum that represents the errors found while parsing a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    EmptyQuery,
+    UnexpectedEnd,
+    UnexpectedToken(String),
+    UnknownField(String),
+    UnclosedQuote,
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::EmptyQuery => write!(f, "Empty query"),
+            QueryError::UnexpectedEnd => write!(f, "Unexpected end of query"),
+            QueryError::UnexpectedToken(token) => write!(f, "Unexpected token '{}'", token),
+            QueryError::UnknownField(field) => write!(f, "Unknown field '{}'", field),
+            QueryError::UnclosedQuote => write!(f, "Unclosed quote"),
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+/// Enum that represents the AST of a query. It is (de)serialized as a string
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Query {
+    Term {
+        field: Field,
+        words: Vec<String>,
+        prefix: bool,
+    },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    Not(Box<Query>),
+}
+
+/// Enum that represents the tokens of a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+    Term {
+        field: Option<String>,
+        text: String,
+        quoted: bool,
+        prefix: bool,
+    },
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::And => write!(f, "AND"),
+            Token::Or => write!(f, "OR"),
+            Token::Not => write!(f, "NOT"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Term { text, .. } => write!(f, "{}", text),
+        }
+    }
+}
+
+/// Function that splits a text in lowercase words
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect()
+}
+
+/// Function that splits a query in tokens
+fn tokenize(query: &str) -> Result<Vec<Token>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut chars = query.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            _ => {
+                // Read a bare word, which may be a field followed by a quoted phrase
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' || c == '"' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+
+                let (field, mut text, quoted) = if chars.peek() == Some(&'"') {
+                    chars.next();
+                    let mut phrase = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some(c) => phrase.push(c),
+                            None => return Err(QueryError::UnclosedQuote),
+                        }
+                    }
+                    let field = word.strip_suffix(':').map(String::from);
+                    if field.is_none() && !word.is_empty() {
+                        return Err(QueryError::UnexpectedToken(word));
+                    }
+                    (field, phrase, true)
+                } else {
+                    match word.as_str() {
+                        "AND" => {
+                            tokens.push(Token::And);
+                            continue;
+                        }
+                        "OR" => {
+                            tokens.push(Token::Or);
+                            continue;
+                        }
+                        "NOT" => {
+                            tokens.push(Token::Not);
+                            continue;
+                        }
+                        _ => match word.split_once(':') {
+                            Some((field, text)) if !field.is_empty() => (Some(field.to_string()), text.to_string(), false),
+                            _ => (None, word, false),
+                        },
+                    }
+                };
+
+                let prefix = if chars.peek() == Some(&'*') {
+                    chars.next();
+                    true
+                } else if !quoted && text.ends_with('*') {
+                    text.pop();
+                    true
+                } else {
+                    false
+                };
+
+                tokens.push(Token::Term {
+                    field,
+                    text,
+                    quoted,
+                    prefix,
+                });
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// Struct that implements a recursive descent parser over the tokens
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    // or := and ("OR" and)*
+    fn parse_or(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.parse_and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.next();
+            queries.push(self.parse_and()?);
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::Or(queries)
+        })
+    }
+
+    // and := unary (["AND"] unary)*
+    fn parse_and(&mut self) -> Result<Query, QueryError> {
+        let mut queries = vec![self.parse_unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.next();
+                    queries.push(self.parse_unary()?);
+                }
+                Some(Token::Not) | Some(Token::Open) | Some(Token::Term { .. }) => {
+                    queries.push(self.parse_unary()?);
+                }
+                _ => break,
+            }
+        }
+        Ok(if queries.len() == 1 {
+            queries.pop().unwrap()
+        } else {
+            Query::And(queries)
+        })
+    }
+
+    // unary := "NOT" unary | primary
+    fn parse_unary(&mut self) -> Result<Query, QueryError> {
+        if self.peek() == Some(&Token::Not) {
+            self.next();
+            Ok(Query::Not(Box::new(self.parse_unary()?)))
+        } else {
+            self.parse_primary()
+        }
+    }
+
+    // primary := "(" or ")" | term
+    fn parse_primary(&mut self) -> Result<Query, QueryError> {
+        match self.next() {
+            Some(Token::Open) => {
+                let query = self.parse_or()?;
+                match self.next() {
+                    Some(Token::Close) => Ok(query),
+                    Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+                    None => Err(QueryError::UnexpectedEnd),
+                }
+            }
+            Some(Token::Term {
+                field,
+                text,
+                quoted: _,
+                prefix,
+            }) => {
+                // Unknown fields are matched as plain text
+                let (field, text) = match field {
+                    Some(field) => match Field::from_str(&field) {
+                        Ok(field) => (field, text),
+                        Err(err) => {
+                            log::warn!("{}, matching it as text", err);
+                            (Field::Default, format!("{}:{}", field, text))
+                        }
+                    },
+                    None => (Field::Default, text),
+                };
+                let words = words(&text);
+                if words.is_empty() {
+                    return Err(QueryError::UnexpectedToken(text));
+                }
+                Ok(Query::Term { field, words, prefix })
+            }
+            Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+            None => Err(QueryError::UnexpectedEnd),
+        }
+    }
+}
+
+impl Query {
+    /// Function that parses a query
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::query::{Field, Query};
+    ///
+    /// let query = Query::parse("(economía OR inflación) AND NOT deportes").unwrap();
+    /// assert_eq!(query.to_string(), "((economía OR inflación) AND NOT deportes)");
+    /// let query = Query::parse("title:\"banco de españa\" ley*").unwrap();
+    /// assert_eq!(query.to_string(), "(title:\"banco de españa\" AND ley*)");
+    /// assert!(Query::parse("(economía OR").is_err());
+    /// let query = Query::parse("author:nobody").unwrap();
+    /// assert_eq!(query.to_string(), "\"author nobody\"");
+    /// ```
+    pub fn parse(query: &str) -> Result<Query, QueryError> {
+        let tokens = tokenize(query)?;
+        if tokens.is_empty() {
+            return Err(QueryError::EmptyQuery);
+        }
+        let mut parser = Parser { tokens, position: 0 };
+        let query = parser.parse_or()?;
+        match parser.next() {
+            None => Ok(query),
+            Some(token) => Err(QueryError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Function that builds a query from a list of opt in queries combined
+    /// with an operator. It returns None if the list is empty
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    ///
+    /// let opt_in = vec!["economía".to_string(), "category:política".to_string()];
+    /// let query = Query::from_opt_in(&opt_in, Operator::OR).unwrap().unwrap();
+    /// assert_eq!(query.to_string(), "(economía OR category:política)");
+    /// assert!(Query::from_opt_in(&[], Operator::OR).unwrap().is_none());
+    /// ```
+    pub fn from_opt_in(opt_in: &[String], operator: Operator) -> Result<Option<Query>, QueryError> {
+        let mut queries = opt_in
+            .iter()
+            .map(|query| Query::parse(query))
+            .collect::<Result<Vec<Query>, QueryError>>()?;
+        Ok(match queries.len() {
+            0 => None,
+            1 => queries.pop(),
+            _ => match operator {
+                Operator::AND => Some(Query::And(queries)),
+                Operator::OR => Some(Query::Or(queries)),
+            },
+        })
+    }
+
+    /// Function that returns true if the NewsItem matches the query
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    ///
+    /// let news_item = NewsItem {
+    ///     categories: Some("leyenda,música".to_string()),
+    ///     ..Default::default()
+    /// };
+    /// assert!(!Query::parse("ley").unwrap().matches(&news_item));
+    /// assert!(Query::parse("ley*").unwrap().matches(&news_item));
+    /// ```
+    pub fn matches(&self, news_item: &NewsItem) -> bool {
+        match self {
+            Query::Term { field, words, prefix } => field
+                .values(news_item)
+                .iter()
+                .any(|value| contains_words(&self::words(value), words, *prefix)),
+            Query::And(queries) => queries.iter().all(|query| query.matches(news_item)),
+            Query::Or(queries) => queries.iter().any(|query| query.matches(news_item)),
+            Query::Not(query) => !query.matches(news_item),
+        }
+    }
+}
+
+/// Function that returns true if the words of a value contain the words of a
+/// term as a sequence. If prefix is true, the last word of the term only needs
+/// to be a prefix
+fn contains_words(value: &[String], term: &[String], prefix: bool) -> bool {
+    if term.len() > value.len() {
+        return false;
+    }
+    value.windows(term.len()).any(|window| {
+        window.iter().zip(term.iter()).enumerate().all(|(i, (word, term_word))| {
+            if prefix && i == term.len() - 1 {
+                word.starts_with(term_word.as_str())
+            } else {
+                word == term_word
+            }
+        })
+    })
+}
+
+impl Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Query::Term { field, words, prefix } => {
+                if *field != Field::Default {
+                    write!(f, "{}:", field.as_str())?;
+                }
+                if words.len() > 1 {
+                    write!(f, "\"{}\"", words.join(" "))?;
+                } else {
+                    write!(f, "{}", words.join(" "))?;
+                }
+                if *prefix {
+                    write!(f, "*")?;
+                }
+                Ok(())
+            }
+            Query::And(queries) => write!(
+                f,
+                "({})",
+                queries.iter().map(|q| q.to_string()).collect::<Vec<String>>().join(" AND ")
+            ),
+            Query::Or(queries) => write!(
+                f,
+                "({})",
+                queries.iter().map(|q| q.to_string()).collect::<Vec<String>>().join(" OR ")
+            ),
+            Query::Not(query) => write!(f, "NOT {}", query),
+        }
+    }
+}
+
+impl FromStr for Query {
+    type Err = QueryError;
+
+    fn from_str(query: &str) -> Result<Self, Self::Err> {
+        Query::parse(query)
+    }
+}
+
+impl TryFrom<String> for Query {
+    type Error = QueryError;
+
+    fn try_from(query: String) -> Result<Self, Self::Error> {
+        Query::parse(&query)
+    }
+}
+
+impl From<Query> for String {
+    fn from(query: Query) -> Self {
+        query.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityKind};
+
+    fn news_item() -> NewsItem {
+        NewsItem {
+            channel: "EL PAÍS: el periódico global".to_string(),
+            title: "El Banco de España alerta sobre la inflación".to_string(),
+            categories: Some("economía,banca central".to_string()),
+            keywords: Some("tipos de interés".to_string()),
+            entities: vec![Entity {
+                name: "Banco de España".to_string(),
+                kind: EntityKind::Organization,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens = tokenize(r#"(a OR title:"b c") AND NOT d*"#).unwrap();
+        assert_eq!(tokens.len(), 8);
+        assert_eq!(
+            tokens[3],
+            Token::Term {
+                field: Some("title".to_string()),
+                text: "b c".to_string(),
+                quoted: true,
+                prefix: false
+            }
+        );
+        assert_eq!(
+            tokens[7],
+            Token::Term {
+                field: None,
+                text: "d".to_string(),
+                quoted: false,
+                prefix: true
+            }
+        );
+        assert_eq!(tokenize(r#"title:"open"#), Err(QueryError::UnclosedQuote));
+    }
+
+    #[test]
+    fn test_parse_precedence() {
+        let query = Query::parse("a b OR c AND NOT d").unwrap();
+        assert_eq!(query.to_string(), "((a AND b) OR (c AND NOT d))");
+        let query = Query::parse("a AND (b OR c)").unwrap();
+        assert_eq!(query.to_string(), "(a AND (b OR c))");
+    }
+
+    #[test]
+    fn test_parse_operators_and_words() {
+        let query = Query::parse("Rock and roll OR NOT Jazz").unwrap();
+        assert_eq!(query.to_string(), "((rock AND and AND roll) OR NOT jazz)");
+        let query = Query::parse("title:\"Pan Y Vino\" Category:Economía").unwrap();
+        assert_eq!(query.to_string(), "(title:\"pan y vino\" AND category:economía)");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(Query::parse(""), Err(QueryError::EmptyQuery));
+        assert_eq!(Query::parse("a AND"), Err(QueryError::UnexpectedEnd));
+        assert_eq!(Query::parse("(a OR b"), Err(QueryError::UnexpectedEnd));
+        assert_eq!(Query::parse("a)"), Err(QueryError::UnexpectedToken(")".to_string())));
+        // Unknown fields are not errors, they are matched as text
+        assert_eq!(Query::parse("foo:bar").unwrap().to_string(), "\"foo bar\"");
+    }
+
+    #[test]
+    fn test_matches_exact_word_and_prefix() {
+        let news_item = news_item();
+        assert!(Query::parse("economía").unwrap().matches(&news_item));
+        assert!(!Query::parse("econ").unwrap().matches(&news_item));
+        assert!(Query::parse("econ*").unwrap().matches(&news_item));
+        assert!(Query::parse("central").unwrap().matches(&news_item));
+        assert!(Query::parse("\"tipos de interés\"").unwrap().matches(&news_item));
+        // Phrases do not match across categories
+        assert!(!Query::parse("\"economía banca\"").unwrap().matches(&news_item));
+    }
+
+    #[test]
+    fn test_matches_fields() {
+        let news_item = news_item();
+        assert!(Query::parse("title:inflación").unwrap().matches(&news_item));
+        assert!(!Query::parse("inflación").unwrap().matches(&news_item));
+        assert!(Query::parse("channel:\"el país\"").unwrap().matches(&news_item));
+        assert!(Query::parse("entity:\"banco de españa\"").unwrap().matches(&news_item));
+        assert!(!Query::parse("category:tipos").unwrap().matches(&news_item));
+        assert!(Query::parse("keyword:tipos").unwrap().matches(&news_item));
+    }
+
+    #[test]
+    fn test_matches_boolean() {
+        let news_item = news_item();
+        let query = Query::parse("(economía OR inflación) AND NOT deportes").unwrap();
+        assert!(query.matches(&news_item));
+        let query = Query::parse("(economía OR inflación) AND NOT banca*").unwrap();
+        assert!(!query.matches(&news_item));
+        let query = Query::parse("deportes OR (title:banco AND channel:país)").unwrap();
+        assert!(query.matches(&news_item));
+    }
+}
@@ -4,6 +4,7 @@ use once_cell::sync::Lazy;
 use std::collections::HashSet;
 use std::fmt::Display;
 
+use serde::{Deserialize, Serialize};
 use strsim::sorensen_dice;
 
 use crate::common::{NewsItem, DEFAULT_CONFIG_FOLDER_NAME, DEFAULT_ROOT_WORDS_FILE};
@@ -27,14 +28,27 @@ static ROOT_WORDS: Lazy<HashSet<&'static str>> = Lazy::new(|| {
     .collect()
 });
 
+/// Struct to represent the contribution of each field to the relevance_core
+/// of a NewsItem
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
+pub struct RelevanceBreakdown {
+    pub creator: u64,
+    pub categories: u64,
+    pub keywords: u64,
+    pub title: u64,
+    pub content: u64,
+}
+
 /// Struct to represent the relevance of a NewsItem
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Relevance {
     pub error: bool,
     pub relevance_core: u64,
     pub relevance_content: u64,
     pub explanation: String,
     pub elapsed_time: f64,
+    #[serde(default)]
+    pub breakdown: RelevanceBreakdown,
 }
 
 impl Relevance {
@@ -46,6 +60,13 @@ impl Relevance {
             relevance_content,
             explanation: Relevance::build_explanation(relevance_core.0, relevance_core.1, relevance_core.2, relevance_core.3, relevance_core.4, relevance_core.5),
             elapsed_time,
+            breakdown: RelevanceBreakdown {
+                creator: relevance_core.1,
+                categories: relevance_core.2,
+                keywords: relevance_core.3,
+                title: relevance_core.4,
+                content: relevance_core.5,
+            },
         }
     }
 
@@ -198,6 +219,26 @@ fn calculate_relevance_core(news_item: &NewsItem) -> (bool, u64, u64, u64, u64,
 
 /// Function that calculates the relevance_full of a NewsItem
 pub async fn calculate_relevance(news_item: &NewsItem) -> Relevance {
+    score_relevance(news_item)
+}
+
+/// Function that calculates synchronously the relevance of a NewsItem with
+/// its breakdown, e.g. to explain it in a report
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::relevance::score_relevance;
+///
+/// let news_item = NewsItem {
+///     title: "El Gobierno aprueba la reforma".to_string(),
+///     ..Default::default()
+/// };
+/// let relevance = score_relevance(&news_item);
+/// assert!(relevance.explanation.starts_with("breakdown"));
+/// assert_eq!(relevance.relevance_core, relevance.breakdown.title + relevance.breakdown.content);
+/// ```
+pub fn score_relevance(news_item: &NewsItem) -> Relevance {
     // Start time
     let start = std::time::Instant::now();
 
@@ -263,6 +304,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_error), (true, 0, 0, 0, 0, 0));
         
@@ -278,6 +321,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_creators), (false, 10, 0, 0, 0, 0));
         
@@ -293,6 +338,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_categories), (false, 0, 10, 0, 0, 0));
         
@@ -308,6 +355,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_keywords), (false, 0, 0, 15, 0, 0));
         
@@ -323,6 +372,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_title), (false, 0, 0, 0, 20, 0));
         
@@ -338,6 +389,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_description), (false, 0, 0, 0, 0, 2));
 
@@ -353,6 +406,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         assert_eq!(calculate_relevance_core(&news_item_with_description), (false, 10, 0, 0, 0, 2));
     }
@@ -371,6 +426,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
         
         let relevance_core = calculate_relevance_core(&news_item_with_clean_content);
@@ -391,6 +448,8 @@ mod tests {
             link: "".to_string(),
             pub_date: None,
             relevance: None,
+            entities: vec![],
+            raw_html: None,
         };
 
         let relevance_core = calculate_relevance_core(&news_item_without_clean_content);
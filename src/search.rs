@@ -0,0 +1,249 @@
+/// Module for the full-text search over the stored NewsItems
+use chrono::{NaiveDate, NaiveTime};
+use serde::Serialize;
+use sqlite::{Connection, State};
+
+use crate::common::NewsItem;
+use crate::storage::{NewsItemQuery, NEWS_ITEM_COLUMNS};
+
+/// Markers used to highlight the matched terms in the snippets
+pub const HIGHLIGHT_START: &str = "**";
+pub const HIGHLIGHT_END: &str = "**";
+
+/// Weights of the bm25 ranking for title, description, categories, keywords
+/// and clean_content
+const BM25_WEIGHTS: &str = "10.0, 5.0, 2.0, 2.0, 1.0";
+
+/// Struct that represents the filters of a search
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    /// Only items published on or after this date
+    pub since: Option<NaiveDate>,
+    /// Only items published on or before this date
+    pub until: Option<NaiveDate>,
+    /// Only items whose channel contains this text (case insensitive)
+    pub channel: Option<String>,
+    /// Maximum number of results, 0 for no limit
+    pub limit: usize,
+}
+
+/// Struct that represents a search result
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchResult {
+    pub channel: String,
+    pub title: String,
+    pub link: String,
+    pub pub_date: Option<String>,
+    pub score: f64,
+    pub snippet: String,
+    #[serde(skip)]
+    pub news_item: NewsItem,
+}
+
+impl SearchOptions {
+    /// Function that returns the query selecting the items published within
+    /// the date range of the options, the dates are whole days in UTC
+    fn date_range(&self) -> NewsItemQuery {
+        let start_of_day = |date: NaiveDate| date.and_time(NaiveTime::MIN).and_utc();
+        let mut query = NewsItemQuery::new();
+        if let Some(since) = self.since {
+            query = query.since(start_of_day(since));
+        }
+        if let Some(until) = self.until.and_then(|until| until.succ_opt()) {
+            query = query.until(start_of_day(until));
+        }
+        query
+    }
+}
+
+/// Function that searches the stored NewsItems with a FTS5 query, e.g.
+/// `inflación AND "banco central"` or `econom*`, and returns the results
+/// ranked by bm25 with the matched terms highlighted in the snippets
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::search::{search_news_items, SearchOptions};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// NewsItem::create_table(&conn).unwrap();
+/// let news_item = NewsItem {
+///     title: "La inflación baja en octubre".to_string(),
+///     link: "https://www.acme.es/inflacion.html".to_string(),
+///     ..Default::default()
+/// };
+/// news_item.insert(&conn).unwrap();
+/// let results = search_news_items(&conn, "inflacion", &SearchOptions::default()).unwrap();
+/// assert_eq!(results.len(), 1);
+/// assert_eq!(results[0].snippet, "La **inflación** baja en octubre");
+/// ```
+pub fn search_news_items(conn: &Connection, query: &str, options: &SearchOptions) -> sqlite::Result<Vec<SearchResult>> {
+    let columns = NEWS_ITEM_COLUMNS
+        .split(", ")
+        .map(|column| format!("news_item.{}", column))
+        .collect::<Vec<String>>()
+        .join(", ");
+    // The date range is applied with the indexed pub_timestamp column
+    let date_range = options.date_range();
+    let sql = format!(
+        "SELECT {columns}, bm25(news_item_fts, {BM25_WEIGHTS}) AS score,
+                snippet(news_item_fts, -1, '{HIGHLIGHT_START}', '{HIGHLIGHT_END}', '…', 24)
+         FROM news_item_fts JOIN (SELECT * FROM news_item{}) AS news_item ON news_item.id = news_item_fts.rowid
+         WHERE news_item_fts MATCH :query
+           AND (:channel IS NULL OR instr(lower(news_item.channel), lower(:channel)) > 0)
+         ORDER BY score",
+        date_range.where_clause()
+    );
+    let mut statement = conn.prepare(sql)?;
+    date_range.bind(&mut statement)?;
+    statement.bind((":query", query))?;
+    statement.bind((":channel", options.channel.as_deref()))?;
+
+    let mut results = Vec::new();
+    while let State::Row = statement.next()? {
+        let news_item = NewsItem::read_row(&statement)?;
+        // bm25 returns lower values for better matches
+        let score = -statement.read::<f64, _>(12)?;
+        let snippet = statement.read::<String, _>(13)?;
+        results.push(SearchResult {
+            channel: news_item.channel.clone(),
+            title: news_item.title.clone(),
+            link: news_item.link.clone(),
+            pub_date: news_item.pub_date.clone(),
+            score,
+            snippet,
+            news_item,
+        });
+        if options.limit > 0 && results.len() >= options.limit {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+/// Function that formats the search results as Markdown
+pub fn search_results_to_markdown(query: &str, results: &[SearchResult]) -> String {
+    let mut report = format!("# Search: {}\n\n{} results\n\n", query, results.len());
+    for (i, result) in results.iter().enumerate() {
+        report.push_str(&format!("## {}. [{}]({})\n", i + 1, result.title, result.link));
+        report.push_str(&format!(
+            "{} | {} | score {:.2}\n\n",
+            result.channel,
+            result.pub_date.as_deref().unwrap_or("No date"),
+            result.score
+        ));
+        report.push_str(&format!("> {}\n\n", result.snippet.replace('\n', " ")));
+    }
+    report
+}
+
+/// Function that formats the search results as JSON
+pub fn search_results_to_json(results: &[SearchResult]) -> serde_json::Result<String> {
+    serde_json::to_string_pretty(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn insert(conn: &Connection, channel: &str, title: &str, content: &str, pub_date: &str) {
+        NewsItem {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            link: format!("https://www.acme.es/{}.html", title.replace(' ', "-")),
+            description: "Description".to_string(),
+            pub_date: Some(pub_date.to_string()),
+            clean_content: Some(content.to_string()),
+            ..Default::default()
+        }
+        .insert(conn)
+        .unwrap();
+    }
+
+    fn database() -> Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+        insert(
+            &conn,
+            "El Diario",
+            "Sube el paro",
+            "La inflación y el paro suben",
+            "Mon, 01 Sep 2025 10:00:00 +0000",
+        );
+        insert(
+            &conn,
+            "La Gaceta",
+            "La inflación baja",
+            "Los precios bajan",
+            "Wed, 01 Oct 2025 10:00:00 +0000",
+        );
+        insert(&conn, "La Gaceta", "Deportes", "El partido", "Wed, 01 Oct 2025 12:00:00 +0000");
+        conn
+    }
+
+    #[test]
+    fn test_search_ranks_title_matches_first() {
+        let conn = database();
+        let results = search_news_items(&conn, "inflacion", &SearchOptions::default()).unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].title, "La inflación baja");
+        assert!(results[0].score > results[1].score);
+        assert_eq!(results[1].snippet, "La **inflación** y el paro suben");
+    }
+
+    #[test]
+    fn test_search_with_filters() {
+        let conn = database();
+        let options = SearchOptions {
+            channel: Some("gaceta".to_string()),
+            ..Default::default()
+        };
+        let results = search_news_items(&conn, "inflacion OR paro", &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].channel, "La Gaceta");
+
+        let options = SearchOptions {
+            until: NaiveDate::from_ymd_opt(2025, 9, 30),
+            ..Default::default()
+        };
+        let results = search_news_items(&conn, "inflacion", &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Sube el paro");
+
+        let options = SearchOptions {
+            since: NaiveDate::from_ymd_opt(2025, 10, 1),
+            limit: 1,
+            ..Default::default()
+        };
+        let results = search_news_items(&conn, "inflacion OR partido", &options).unwrap();
+        assert_eq!(results.len(), 1);
+
+        // The RFC 3339 dates are also within the date range
+        insert(&conn, "El Diario", "Baja la inflación", "Los precios", "2025-10-15T08:00:00+02:00");
+        let options = SearchOptions {
+            since: NaiveDate::from_ymd_opt(2025, 10, 15),
+            until: NaiveDate::from_ymd_opt(2025, 10, 15),
+            ..Default::default()
+        };
+        let results = search_news_items(&conn, "inflacion", &options).unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "Baja la inflación");
+    }
+
+    #[test]
+    fn test_search_with_invalid_query() {
+        let conn = database();
+        assert!(search_news_items(&conn, "\"unclosed", &SearchOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_search_results_output() {
+        let conn = database();
+        let results = search_news_items(&conn, "precios", &SearchOptions::default()).unwrap();
+        let markdown = search_results_to_markdown("precios", &results);
+        assert!(markdown.contains("## 1. [La inflación baja](https://www.acme.es/La-inflación-baja.html)"));
+        assert!(markdown.contains("> Los **precios** bajan"));
+        let json = search_results_to_json(&results).unwrap();
+        assert!(json.contains("\"snippet\": \"Los **precios** bajan\""));
+    }
+}
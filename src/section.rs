@@ -0,0 +1,370 @@
+/// Module for grouping the news items of a dossier in sections, like the
+/// sections of a newspaper: by topic, using the watchlists as interest
+/// profiles, by channel, by category or by story. Each section keeps its most
+/// relevant items and a summary of all of them
+use std::collections::HashMap;
+
+use strsim::sorensen_dice;
+
+use crate::common::NewsItem;
+use crate::watchlist::Watchlist;
+
+/// Similarity of the titles of two items of the same story
+const STORY_TITLE_SIMILARITY: f64 = 0.5;
+
+/// Number of entities two items of the same story share at least
+const STORY_SHARED_ENTITIES: usize = 2;
+
+/// Enum that represents how the news items are grouped in sections
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Grouping {
+    /// A section per topic, the items match the rule of its watchlist
+    Topic,
+    Channel,
+    /// A section per first category of the items
+    Category,
+    /// A section per cluster of items with similar titles or entities
+    Story,
+}
+
+impl Grouping {
+    // Function that returns the name of the section of the items that do not
+    // fit in any other
+    fn fallback(&self) -> &str {
+        match self {
+            Grouping::Topic => "Other topics",
+            Grouping::Channel => "Other channels",
+            Grouping::Category => "Uncategorized",
+            Grouping::Story => "Other stories",
+        }
+    }
+}
+
+/// Struct that represents the options of the sections
+#[derive(Debug, Clone)]
+pub struct SectionOptions {
+    pub grouping: Grouping,
+    /// Maximum number of items of each section
+    pub top_k: usize,
+    /// Topics of the Topic grouping in the order of their sections
+    pub topics: Vec<Watchlist>,
+}
+
+/// Struct that represents a section of a dossier
+#[derive(Debug, Clone)]
+pub struct NewsSection {
+    pub name: String,
+    /// Summary of all the items of the section, including the ones left out
+    pub summary: String,
+    /// Number of items of the section before keeping the top-k
+    pub total_items: usize,
+    /// Most relevant items of the section
+    pub news_items: Vec<NewsItem>,
+}
+
+impl NewsSection {
+    // Function that creates a section with the top k items by relevance and
+    // the summary of all of them
+    fn new(name: &str, mut news_items: Vec<NewsItem>, top_k: usize) -> NewsSection {
+        news_items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
+        let summary = summarize_section(&news_items);
+        let total_items = news_items.len();
+        news_items.truncate(top_k);
+        NewsSection {
+            name: name.to_string(),
+            summary,
+            total_items,
+            news_items,
+        }
+    }
+
+    // Function that returns the total relevance of the items kept
+    fn relevance(&self) -> u64 {
+        self.news_items.iter().map(|item| item.relevance.unwrap_or_default()).sum()
+    }
+}
+
+// Function that summarizes the items of a section ordered by relevance: the
+// channels, the average relevance, the lead item and the most mentioned
+// entities
+fn summarize_section(news_items: &[NewsItem]) -> String {
+    let Some(lead) = news_items.first() else {
+        return "No items".to_string();
+    };
+
+    let mut channels = Vec::new();
+    for item in news_items.iter() {
+        if !channels.contains(&item.channel.as_str()) {
+            channels.push(item.channel.as_str());
+        }
+    }
+    let total = news_items.iter().map(|item| item.relevance.unwrap_or_default()).sum::<u64>();
+
+    let mut summary = format!(
+        "{} {} from {} with an average relevance of {:.2}. Lead: {}.",
+        news_items.len(),
+        if news_items.len() == 1 { "item" } else { "items" },
+        channels.join(", "),
+        total as f64 / news_items.len() as f64,
+        lead.title
+    );
+
+    // The entities mentioned by more items first
+    let mut mentions: HashMap<&str, usize> = HashMap::new();
+    for item in news_items.iter() {
+        for entity in item.entities.iter() {
+            *mentions.entry(entity.name.as_str()).or_default() += 1;
+        }
+    }
+    let mut mentions = mentions.into_iter().collect::<Vec<(&str, usize)>>();
+    mentions.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    if !mentions.is_empty() {
+        let names = mentions.iter().take(3).map(|(name, _)| *name).collect::<Vec<&str>>();
+        summary.push_str(&format!(" Mentions: {}.", names.join(", ")));
+    }
+    summary
+}
+
+// Function that returns whether two items are about the same story
+fn same_story(a: &NewsItem, b: &NewsItem) -> bool {
+    if sorensen_dice(&a.title.to_lowercase(), &b.title.to_lowercase()) >= STORY_TITLE_SIMILARITY {
+        return true;
+    }
+    let shared = a.entities.iter().filter(|entity| b.entities.contains(entity)).count();
+    shared >= STORY_SHARED_ENTITIES
+}
+
+// Function that clusters the items by story and returns the clusters in the
+// order of their first item
+fn story_clusters(news_items: &[NewsItem]) -> Vec<Vec<usize>> {
+    // Union-find of the items about the same story
+    let mut parents = (0..news_items.len()).collect::<Vec<usize>>();
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        parents[i] = root;
+        root
+    }
+    for i in 0..news_items.len() {
+        for j in (i + 1)..news_items.len() {
+            if same_story(&news_items[i], &news_items[j]) {
+                let (root_i, root_j) = (find(&mut parents, i), find(&mut parents, j));
+                parents[root_j.max(root_i)] = root_i.min(root_j);
+            }
+        }
+    }
+
+    let mut clusters: Vec<Vec<usize>> = Vec::new();
+    let mut cluster_of_root = HashMap::new();
+    for i in 0..news_items.len() {
+        let root = find(&mut parents, i);
+        let cluster = *cluster_of_root.entry(root).or_insert_with(|| {
+            clusters.push(Vec::new());
+            clusters.len() - 1
+        });
+        clusters[cluster].push(i);
+    }
+    clusters
+}
+
+/// Function that groups the NewsItems in sections. The topic sections follow
+/// the order of the topics and the rest are ordered by the relevance of their
+/// items, the items that do not fit in any section go to a last one
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::section::{section_news_items, Grouping, SectionOptions};
+///
+/// let item = |channel: &str, relevance: u64| NewsItem {
+///     channel: channel.to_string(),
+///     title: format!("{} {}", channel, relevance),
+///     relevance: Some(relevance),
+///     ..Default::default()
+/// };
+/// let news_items = vec![item("El País", 5), item("20 Minutos", 20), item("El País", 10), item("El País", 1)];
+/// let options = SectionOptions { grouping: Grouping::Channel, top_k: 2, topics: vec![] };
+/// let sections = section_news_items(&news_items, &options);
+/// assert_eq!(sections[0].name, "20 Minutos");
+/// assert_eq!(sections[1].name, "El País");
+/// assert_eq!(sections[1].total_items, 3);
+/// assert_eq!(sections[1].news_items.len(), 2);
+/// ```
+pub fn section_news_items(news_items: &[NewsItem], options: &SectionOptions) -> Vec<NewsSection> {
+    let mut groups: Vec<(String, Vec<NewsItem>)> = Vec::new();
+    let mut fallback = Vec::new();
+    let push = |groups: &mut Vec<(String, Vec<NewsItem>)>, name: &str, item: &NewsItem| {
+        match groups.iter_mut().find(|(group, _)| group == name) {
+            Some((_, items)) => items.push(item.clone()),
+            None => groups.push((name.to_string(), vec![item.clone()])),
+        }
+    };
+
+    match options.grouping {
+        Grouping::Topic => {
+            // Every topic has its section in order, the items go to the first
+            // topic they match
+            for topic in options.topics.iter() {
+                groups.push((topic.name.clone(), Vec::new()));
+            }
+            for item in news_items.iter() {
+                match options.topics.iter().find(|topic| topic.rule.matches(item)) {
+                    Some(topic) => push(&mut groups, &topic.name, item),
+                    None => fallback.push(item.clone()),
+                }
+            }
+            groups.retain(|(_, items)| !items.is_empty());
+        }
+        Grouping::Channel => {
+            for item in news_items.iter() {
+                push(&mut groups, &item.channel, item);
+            }
+        }
+        Grouping::Category => {
+            for item in news_items.iter() {
+                let category = item.categories.as_deref().and_then(|categories| {
+                    categories.split(',').map(str::trim).find(|category| !category.is_empty())
+                });
+                match category {
+                    Some(category) => push(&mut groups, category, item),
+                    None => fallback.push(item.clone()),
+                }
+            }
+        }
+        Grouping::Story => {
+            // The stories are named after their most relevant item and the
+            // items without any related one go to the last section
+            for cluster in story_clusters(news_items) {
+                let mut items = cluster.into_iter().map(|i| news_items[i].clone()).collect::<Vec<NewsItem>>();
+                if items.len() == 1 {
+                    fallback.append(&mut items);
+                } else {
+                    items.sort_by_key(|item| std::cmp::Reverse(item.relevance));
+                    groups.push((items[0].title.clone(), items));
+                }
+            }
+        }
+    }
+
+    let mut sections = groups
+        .into_iter()
+        .map(|(name, items)| NewsSection::new(&name, items, options.top_k))
+        .collect::<Vec<NewsSection>>();
+    if options.grouping != Grouping::Topic {
+        sections.sort_by(|a, b| b.relevance().cmp(&a.relevance()).then(a.name.cmp(&b.name)));
+    }
+    if !fallback.is_empty() {
+        sections.push(NewsSection::new(options.grouping.fallback(), fallback, options.top_k));
+    }
+    sections
+}
+
+/// Function that returns the items of the sections in the order of the
+/// sections
+pub fn flatten_sections(sections: &[NewsSection]) -> Vec<NewsItem> {
+    sections.iter().flat_map(|section| section.news_items.iter().cloned()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityKind};
+
+    fn news_item(channel: &str, title: &str, categories: Option<&str>, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            link: format!("https://www.acme.es/{}.html", title.replace(' ', "-")),
+            categories: categories.map(str::to_string),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    fn organization(name: &str) -> Entity {
+        Entity {
+            name: name.to_string(),
+            kind: EntityKind::Organization,
+        }
+    }
+
+    fn options(grouping: Grouping) -> SectionOptions {
+        SectionOptions {
+            grouping,
+            top_k: 10,
+            topics: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_section_by_topic() {
+        let topics: Vec<Watchlist> = serde_json::from_str(
+            r#"[
+                {"name": "Economía", "rule": {"any": [{"phrase": "inflación"}, {"phrase": "IPC"}]}},
+                {"name": "Deportes", "rule": {"phrase": "fútbol"}},
+                {"name": "Cultura", "rule": {"phrase": "museo"}}
+            ]"#,
+        )
+        .unwrap();
+        let news_items = vec![
+            news_item("El País", "La liga de fútbol", None, 30),
+            news_item("El País", "La inflación baja", None, 10),
+            news_item("El País", "El IPC sube", None, 20),
+            news_item("El País", "Llueve en Madrid", None, 5),
+        ];
+        let options = SectionOptions { topics, ..options(Grouping::Topic) };
+        let sections = section_news_items(&news_items, &options);
+
+        // The topics keep their order, the empty ones are left out
+        let names = sections.iter().map(|section| section.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["Economía", "Deportes", "Other topics"]);
+        assert_eq!(sections[0].news_items[0].title, "El IPC sube");
+        assert_eq!(sections[2].news_items[0].title, "Llueve en Madrid");
+    }
+
+    #[test]
+    fn test_section_by_category() {
+        let news_items = vec![
+            news_item("El País", "A", Some("economía, política"), 5),
+            news_item("El País", "B", Some(" deportes"), 30),
+            news_item("El País", "C", None, 50),
+            news_item("El País", "D", Some("economía"), 10),
+        ];
+        let sections = section_news_items(&news_items, &SectionOptions { top_k: 1, ..options(Grouping::Category) });
+
+        let names = sections.iter().map(|section| section.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["deportes", "economía", "Uncategorized"]);
+        assert_eq!(sections[1].total_items, 2);
+        assert_eq!(sections[1].news_items.len(), 1);
+        assert_eq!(sections[1].news_items[0].title, "D");
+        assert!(sections[1].summary.starts_with("2 items from El País with an average relevance of 7.50. Lead: D."));
+        assert_eq!(flatten_sections(&sections).len(), 3);
+    }
+
+    #[test]
+    fn test_section_by_story() {
+        let news_items = vec![
+            news_item("El País", "El Gobierno aprueba la reforma laboral", None, 20),
+            news_item("20 Minutos", "El Gobierno aprueba la reforma laboral hoy", None, 30),
+            NewsItem {
+                entities: vec![organization("Banco de España"), organization("BCE")],
+                ..news_item("El País", "Los tipos de interés", None, 10)
+            },
+            NewsItem {
+                entities: vec![organization("BCE"), organization("Banco de España")],
+                ..news_item("El Mundo", "Las hipotecas se encarecen", None, 5)
+            },
+            news_item("El Mundo", "Llueve en Madrid", None, 1),
+        ];
+        let sections = section_news_items(&news_items, &options(Grouping::Story));
+
+        let names = sections.iter().map(|section| section.name.as_str()).collect::<Vec<&str>>();
+        assert_eq!(names, vec!["El Gobierno aprueba la reforma laboral hoy", "Los tipos de interés", "Other stories"]);
+        assert_eq!(sections[0].news_items.len(), 2);
+        assert!(sections[0].summary.starts_with("2 items from 20 Minutos, El País"));
+        assert!(sections[1].summary.ends_with("Mentions: BCE, Banco de España."));
+        assert_eq!(sections[2].news_items[0].title, "Llueve en Madrid");
+    }
+}
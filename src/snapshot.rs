@@ -0,0 +1,74 @@
+/// Module for the compressed, content-addressed snapshots of the raw HTML
+use std::io::{Read, Write};
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+
+/// Function that returns the hex encoded SHA-256 hash that addresses a
+/// snapshot
+///
+/// Example:
+/// ```
+/// use hemeroteca::snapshot::snapshot_hash;
+///
+/// let hash = snapshot_hash("<html></html>");
+/// assert_eq!(hash.len(), 64);
+/// assert_eq!(hash, snapshot_hash("<html></html>"));
+/// ```
+pub fn snapshot_hash(html: &str) -> String {
+    Sha256::digest(html.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Function that compresses the raw HTML of a snapshot with gzip
+pub fn compress_snapshot(html: &str) -> std::io::Result<Vec<u8>> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(html.as_bytes())?;
+    encoder.finish()
+}
+
+/// Function that decompresses a snapshot back to its raw HTML
+///
+/// Example:
+/// ```
+/// use hemeroteca::snapshot::{compress_snapshot, decompress_snapshot};
+///
+/// let compressed = compress_snapshot("<p>Hola</p>").unwrap();
+/// assert_eq!(decompress_snapshot(&compressed).unwrap(), "<p>Hola</p>");
+/// ```
+pub fn decompress_snapshot(compressed: &[u8]) -> std::io::Result<String> {
+    let mut html = String::new();
+    GzDecoder::new(compressed).read_to_string(&mut html)?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_round_trip() {
+        let html = "<html><body>".to_string() + &"<p>Párrafo con acentos.</p>".repeat(100) + "</body></html>";
+        let compressed = compress_snapshot(&html).unwrap();
+        assert!(compressed.len() < html.len());
+        assert_eq!(decompress_snapshot(&compressed).unwrap(), html);
+    }
+
+    #[test]
+    fn test_snapshot_hash() {
+        assert_eq!(
+            snapshot_hash(""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_ne!(snapshot_hash("a"), snapshot_hash("b"));
+    }
+
+    #[test]
+    fn test_decompress_invalid_snapshot() {
+        assert!(decompress_snapshot(b"not gzip").is_err());
+    }
+}
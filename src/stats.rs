@@ -0,0 +1,342 @@
+/// Module for the relevance statistics of the relevance reports: the
+/// distribution of the relevance per channel, the contribution of each scoring
+/// component and the error rates, with ASCII and SVG charts to embed them in
+/// the Markdown and HTML reports
+use serde::Serialize;
+
+use crate::common::NewsItem;
+use crate::epub::escape_xml;
+use crate::html::channel_color;
+use crate::relevance::score_relevance;
+use crate::relevance_per_channel;
+
+/// Number of bins of the relevance histograms
+pub const HISTOGRAM_BINS: usize = 5;
+
+/// Width in characters of the longest ASCII bar
+const ASCII_BAR_WIDTH: usize = 20;
+
+/// Color of the SVG bars of the scoring components
+const COMPONENTS_COLOR: &str = "#0969da";
+
+/// Struct that represents a bin of a relevance histogram
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBin {
+    /// Range of the bin, e.g. `0-9`
+    pub label: String,
+    pub start: u64,
+    pub end: u64,
+    pub count: usize,
+    /// ASCII bar of the count
+    pub bar: String,
+}
+
+/// Struct that represents the contribution of a scoring component to the
+/// relevance
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ComponentStatistic {
+    pub name: String,
+    pub total: u64,
+    /// Average per item with two decimals
+    pub average: String,
+    /// Share of the relevance of all the components with one decimal
+    pub share: String,
+    /// ASCII bar of the share
+    pub bar: String,
+}
+
+/// Struct that represents the relevance statistics of a channel
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ChannelStatistics {
+    pub channel: String,
+    pub channel_color: String,
+    pub items: usize,
+    /// Items with an error in the pipeline
+    pub errors: usize,
+    /// Percentage of items with an error with one decimal
+    pub error_rate: String,
+    pub min: u64,
+    pub p25: u64,
+    pub median: u64,
+    pub p75: u64,
+    pub p90: u64,
+    pub max: u64,
+    /// Mean relevance with two decimals
+    pub mean: String,
+    pub histogram: Vec<HistogramBin>,
+    pub components: Vec<ComponentStatistic>,
+    /// SVG bar chart of the histogram
+    pub histogram_svg: String,
+    /// SVG bar chart of the shares of the components
+    pub components_svg: String,
+}
+
+/// Struct that represents the relevance statistics of a report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RelevanceStatistics {
+    /// Statistics of all the items together
+    pub overall: ChannelStatistics,
+    /// Statistics per channel ordered by average relevance
+    pub channels: Vec<ChannelStatistics>,
+}
+
+/// Function that returns the percentile of the sorted values with the
+/// nearest-rank method, 0 if there are no values
+///
+/// Example:
+/// ```
+/// use hemeroteca::stats::percentile;
+///
+/// let values = [15, 20, 35, 40, 50];
+/// assert_eq!(percentile(&values, 30.0), 20);
+/// assert_eq!(percentile(&values, 50.0), 35);
+/// assert_eq!(percentile(&values, 100.0), 50);
+/// assert_eq!(percentile(&[], 50.0), 0);
+/// ```
+pub fn percentile(sorted_values: &[u64], percentile: f64) -> u64 {
+    if sorted_values.is_empty() {
+        return 0;
+    }
+    let rank = (percentile / 100.0 * sorted_values.len() as f64).ceil() as usize;
+    sorted_values[rank.clamp(1, sorted_values.len()) - 1]
+}
+
+// Function that returns an ASCII bar of the value relative to the maximum
+fn ascii_bar(value: f64, max: f64) -> String {
+    if max <= 0.0 {
+        return String::new();
+    }
+    "█".repeat((value / max * ASCII_BAR_WIDTH as f64).round() as usize)
+}
+
+/// Function that returns a horizontal SVG bar chart of the labelled values
+///
+/// Example:
+/// ```
+/// use hemeroteca::stats::svg_bar_chart;
+///
+/// let svg = svg_bar_chart(&[("0-9".to_string(), 3.0), ("10-19".to_string(), 1.0)], "#0969da");
+/// assert!(svg.starts_with("<svg"));
+/// assert_eq!(svg.matches("<rect").count(), 2);
+/// ```
+pub fn svg_bar_chart(bars: &[(String, f64)], color: &str) -> String {
+    const LABEL_WIDTH: usize = 90;
+    const BAR_WIDTH: usize = 200;
+    const VALUE_WIDTH: usize = 50;
+    const ROW_HEIGHT: usize = 18;
+
+    let max = bars.iter().map(|(_, value)| *value).fold(0.0, f64::max);
+    let height = bars.len() * ROW_HEIGHT;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" class=\"chart\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\" font-size=\"11\">",
+        LABEL_WIDTH + BAR_WIDTH + VALUE_WIDTH,
+        height,
+        LABEL_WIDTH + BAR_WIDTH + VALUE_WIDTH,
+        height
+    );
+    for (i, (label, value)) in bars.iter().enumerate() {
+        let y = i * ROW_HEIGHT;
+        let width = if max > 0.0 { (value / max * BAR_WIDTH as f64).round() as usize } else { 0 };
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\">{}</text>\
+             <rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\
+             <text x=\"{}\" y=\"{}\">{}</text>",
+            LABEL_WIDTH - 4,
+            y + 13,
+            escape_xml(label),
+            LABEL_WIDTH,
+            y + 3,
+            width,
+            ROW_HEIGHT - 6,
+            color,
+            LABEL_WIDTH + width + 4,
+            y + 13,
+            value
+        ));
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// Function that returns the statistics of the items of a channel with the
+// histogram bins of the given width
+fn channel_statistics(channel: &str, news_items: &[&NewsItem], bin_width: u64) -> ChannelStatistics {
+    let mut values = news_items.iter().map(|item| item.relevance.unwrap_or_default()).collect::<Vec<u64>>();
+    values.sort_unstable();
+    let errors = news_items.iter().filter(|item| item.error.is_some()).count();
+    let count = news_items.len().max(1) as f64;
+
+    // Histogram with the same bins for every channel
+    let counts = (0..HISTOGRAM_BINS as u64)
+        .map(|bin| {
+            let last = bin == HISTOGRAM_BINS as u64 - 1;
+            let start = bin * bin_width;
+            let end = start + bin_width - 1;
+            let count = values.iter().filter(|value| **value >= start && (**value <= end || last)).count();
+            (start, end, count)
+        })
+        .collect::<Vec<(u64, u64, usize)>>();
+    let max_count = counts.iter().map(|(_, _, count)| *count).max().unwrap_or_default() as f64;
+    let histogram = counts
+        .into_iter()
+        .enumerate()
+        .map(|(bin, (start, end, count))| HistogramBin {
+            label: if bin == HISTOGRAM_BINS - 1 { format!("{}+", start) } else { format!("{}-{}", start, end) },
+            start,
+            end,
+            count,
+            bar: ascii_bar(count as f64, max_count),
+        })
+        .collect::<Vec<HistogramBin>>();
+
+    // Totals of the scoring components of the relevance
+    let mut totals = [("creator", 0), ("categories", 0), ("keywords", 0), ("title", 0), ("content", 0)];
+    for item in news_items.iter() {
+        let breakdown = score_relevance(item).breakdown;
+        let scores = [breakdown.creator, breakdown.categories, breakdown.keywords, breakdown.title, breakdown.content];
+        for (total, score) in totals.iter_mut().zip(scores) {
+            total.1 += score;
+        }
+    }
+    let sum = totals.iter().map(|(_, total)| *total).sum::<u64>() as f64;
+    let max_total = totals.iter().map(|(_, total)| *total).max().unwrap_or_default() as f64;
+    let components = totals
+        .iter()
+        .map(|(name, total)| ComponentStatistic {
+            name: name.to_string(),
+            total: *total,
+            average: format!("{:.2}", *total as f64 / count),
+            share: format!("{:.1}", if sum > 0.0 { *total as f64 / sum * 100.0 } else { 0.0 }),
+            bar: ascii_bar(*total as f64, max_total),
+        })
+        .collect::<Vec<ComponentStatistic>>();
+
+    let color = channel_color(channel);
+    let histogram_svg = svg_bar_chart(
+        &histogram.iter().map(|bin| (bin.label.clone(), bin.count as f64)).collect::<Vec<(String, f64)>>(),
+        &color,
+    );
+    let components_svg = svg_bar_chart(
+        &components.iter().map(|component| (component.name.clone(), component.total as f64)).collect::<Vec<(String, f64)>>(),
+        COMPONENTS_COLOR,
+    );
+
+    ChannelStatistics {
+        channel: channel.to_string(),
+        channel_color: color,
+        items: news_items.len(),
+        errors,
+        error_rate: format!("{:.1}", errors as f64 / count * 100.0),
+        min: values.first().copied().unwrap_or_default(),
+        p25: percentile(&values, 25.0),
+        median: percentile(&values, 50.0),
+        p75: percentile(&values, 75.0),
+        p90: percentile(&values, 90.0),
+        max: values.last().copied().unwrap_or_default(),
+        mean: format!("{:.2}", values.iter().sum::<u64>() as f64 / count),
+        histogram,
+        components,
+        histogram_svg,
+        components_svg,
+    }
+}
+
+/// Function that returns the relevance statistics of the NewsItems overall
+/// and per channel
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::stats::relevance_statistics;
+///
+/// let item = |channel: &str, relevance: u64| NewsItem {
+///     channel: channel.to_string(),
+///     relevance: Some(relevance),
+///     ..Default::default()
+/// };
+/// let statistics = relevance_statistics(&[item("El País", 10), item("El País", 30), item("20 Minutos", 5)]);
+/// assert_eq!(statistics.overall.items, 3);
+/// assert_eq!(statistics.overall.median, 10);
+/// assert_eq!(statistics.channels[0].channel, "El País");
+/// assert_eq!(statistics.channels[0].max, 30);
+/// ```
+pub fn relevance_statistics(news_items: &[NewsItem]) -> RelevanceStatistics {
+    // The bins cover up to the maximum relevance of all the items
+    let max = news_items.iter().map(|item| item.relevance.unwrap_or_default()).max().unwrap_or_default();
+    let bin_width = (max / HISTOGRAM_BINS as u64 + 1).max(1);
+
+    let channels = relevance_per_channel(news_items)
+        .into_iter()
+        .map(|(channel, _)| {
+            let items = news_items.iter().filter(|item| item.channel == channel).collect::<Vec<&NewsItem>>();
+            channel_statistics(&channel, &items, bin_width)
+        })
+        .collect();
+
+    RelevanceStatistics {
+        overall: channel_statistics("All channels", &news_items.iter().collect::<Vec<&NewsItem>>(), bin_width),
+        channels,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::PipelineError;
+
+    fn news_item(channel: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: channel.to_string(),
+            title: title.to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_relevance_statistics() {
+        let news_items = vec![
+            news_item("El País", "El Gobierno aprueba la reforma", 40),
+            news_item("El País", "B", 10),
+            news_item("El País", "C", 0),
+            NewsItem {
+                error: Some(PipelineError::NoContent),
+                ..news_item("20 Minutos", "D", 0)
+            },
+            news_item("20 Minutos", "E", 5),
+        ];
+        let statistics = relevance_statistics(&news_items);
+
+        // All the items, the bins of 9 cover up to the maximum
+        let overall = &statistics.overall;
+        assert_eq!((overall.min, overall.p25, overall.median, overall.p75, overall.p90, overall.max), (0, 0, 5, 10, 40, 40));
+        assert_eq!(overall.mean, "11.00");
+        assert_eq!(overall.errors, 1);
+        assert_eq!(overall.error_rate, "20.0");
+        let bins = overall.histogram.iter().map(|bin| (bin.label.as_str(), bin.count)).collect::<Vec<(&str, usize)>>();
+        assert_eq!(bins, vec![("0-8", 3), ("9-17", 1), ("18-26", 0), ("27-35", 0), ("36+", 1)]);
+        assert_eq!(overall.histogram[0].bar, "█".repeat(20));
+
+        // The channels are ordered by average relevance
+        let channels = statistics.channels.iter().map(|channel| channel.channel.as_str()).collect::<Vec<&str>>();
+        assert_eq!(channels, vec!["El País", "20 Minutos"]);
+        assert_eq!(statistics.channels[1].error_rate, "50.0");
+        assert_eq!(statistics.channels[1].histogram.len(), HISTOGRAM_BINS);
+
+        // The title component explains the relevance of the first item
+        let title = overall.components.iter().find(|component| component.name == "title").unwrap();
+        assert!(title.total > 0);
+        assert_eq!(overall.components.iter().map(|component| component.total).sum::<u64>(),
+            news_items.iter().map(|item| score_relevance(item).relevance_core).sum::<u64>());
+        assert!(overall.components_svg.contains(">title</text>"));
+    }
+
+    #[test]
+    fn test_relevance_statistics_without_items() {
+        let statistics = relevance_statistics(&[]);
+        assert_eq!(statistics.overall.items, 0);
+        assert_eq!(statistics.overall.mean, "0.00");
+        assert!(statistics.channels.is_empty());
+        assert!(statistics.overall.components.iter().all(|component| component.share == "0.0"));
+    }
+}
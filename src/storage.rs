@@ -2,9 +2,576 @@ use std::str::FromStr;
 
 /// Module for storage related functions
 use crate::common::{NewsItem, PipelineError};
+use crate::entities::Entity;
 
+use chrono::{DateTime, FixedOffset, Utc};
 use sqlite::{Connection, State};
 
+/// Struct that represents an ordered step of a schema migration
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: i64,
+    pub description: &'static str,
+    pub sql: &'static str,
+}
+
+/// Name of the news_item schema in the schema_version table
+pub const NEWS_ITEM_SCHEMA: &str = "news_item";
+
+/// Migrations of the news_item table, the first one is the original table
+pub const NEWS_ITEM_MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "Create the news_item table",
+        sql: "CREATE TABLE IF NOT EXISTS news_item (
+                id              INTEGER PRIMARY KEY,
+                channel         TEXT NOT NULL,
+                title           TEXT NOT NULL,
+                link            TEXT NOT NULL UNIQUE,
+                description     TEXT NOT NULL,
+                creators        TEXT,
+                pub_date        TEXT,
+                categories      TEXT,
+                keywords        TEXT,
+                clean_content   TEXT,
+                error           TEXT
+            )",
+    },
+    Migration {
+        version: 2,
+        description: "Add the relevance column",
+        sql: "ALTER TABLE news_item ADD COLUMN relevance INTEGER",
+    },
+    Migration {
+        version: 3,
+        description: "Add the entities column",
+        sql: "ALTER TABLE news_item ADD COLUMN entities TEXT",
+    },
+    Migration {
+        version: 4,
+        description: "Create the news_item_revision table",
+        sql: "CREATE TABLE IF NOT EXISTS news_item_revision (
+                id              INTEGER PRIMARY KEY,
+                link            TEXT NOT NULL,
+                field           TEXT NOT NULL,
+                old_value       TEXT NOT NULL,
+                new_value       TEXT NOT NULL,
+                changed_at      TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS news_item_revision_link ON news_item_revision (link)",
+    },
+    Migration {
+        version: 5,
+        description: "Create the news_item_fts full-text search table",
+        sql: "CREATE VIRTUAL TABLE IF NOT EXISTS news_item_fts USING fts5(
+                title, description, categories, keywords, clean_content,
+                content = 'news_item', content_rowid = 'id', tokenize = 'unicode61 remove_diacritics 2'
+            );
+            CREATE TRIGGER IF NOT EXISTS news_item_fts_insert AFTER INSERT ON news_item BEGIN
+                INSERT INTO news_item_fts (rowid, title, description, categories, keywords, clean_content)
+                VALUES (new.id, new.title, new.description, new.categories, new.keywords, new.clean_content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS news_item_fts_delete AFTER DELETE ON news_item BEGIN
+                INSERT INTO news_item_fts (news_item_fts, rowid, title, description, categories, keywords, clean_content)
+                VALUES ('delete', old.id, old.title, old.description, old.categories, old.keywords, old.clean_content);
+            END;
+            CREATE TRIGGER IF NOT EXISTS news_item_fts_update AFTER UPDATE ON news_item BEGIN
+                INSERT INTO news_item_fts (news_item_fts, rowid, title, description, categories, keywords, clean_content)
+                VALUES ('delete', old.id, old.title, old.description, old.categories, old.keywords, old.clean_content);
+                INSERT INTO news_item_fts (rowid, title, description, categories, keywords, clean_content)
+                VALUES (new.id, new.title, new.description, new.categories, new.keywords, new.clean_content);
+            END;
+            INSERT INTO news_item_fts (news_item_fts) VALUES ('rebuild')",
+    },
+    Migration {
+        version: 6,
+        description: "Add the pub_timestamp column",
+        sql: "ALTER TABLE news_item ADD COLUMN pub_timestamp INTEGER;
+            CREATE INDEX IF NOT EXISTS news_item_pub_timestamp ON news_item (pub_timestamp)",
+    },
+];
+
+/// Columns read by the queries of NewsItems, in the order expected by
+/// `NewsItem::read_row`
+pub(crate) const NEWS_ITEM_COLUMNS: &str =
+    "channel, title, link, description, creators, pub_date, categories, keywords, clean_content, error, relevance, entities";
+
+/// Enum that represents the outcome of upserting a NewsItem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    Updated,
+    Unchanged,
+}
+
+/// Struct that counts the outcomes of upserting several NewsItems
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpsertSummary {
+    pub inserted: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+impl UpsertSummary {
+    /// Function that adds an outcome to the summary
+    pub fn add(&mut self, outcome: UpsertOutcome) {
+        match outcome {
+            UpsertOutcome::Inserted => self.inserted += 1,
+            UpsertOutcome::Updated => self.updated += 1,
+            UpsertOutcome::Unchanged => self.unchanged += 1,
+        }
+    }
+}
+
+/// Struct that represents a change of a field of a stored NewsItem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Revision {
+    pub link: String,
+    pub field: String,
+    pub old_value: String,
+    pub new_value: String,
+    pub changed_at: String,
+}
+
+/// Enum that represents the error state filter of a NewsItemQuery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ErrorFilter {
+    /// Items processed without errors
+    NoError,
+    /// Items with any error
+    AnyError,
+    /// Items with the given error kind, e.g. `NetworkError`
+    Kind(String),
+}
+
+/// Enum that represents the order of the results of a NewsItemQuery
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NewsItemOrder {
+    /// Order in which the items were stored
+    #[default]
+    Stored,
+    PubDate,
+    Relevance,
+    Title,
+}
+
+impl NewsItemOrder {
+    fn as_column(&self) -> &str {
+        match self {
+            NewsItemOrder::Stored => "id",
+            NewsItemOrder::PubDate => "pub_timestamp",
+            NewsItemOrder::Relevance => "relevance",
+            NewsItemOrder::Title => "title",
+        }
+    }
+}
+
+/// Struct that builds a query over the stored NewsItems
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::storage::{ErrorFilter, NewsItemOrder, NewsItemQuery};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// NewsItem::create_table(&conn).unwrap();
+/// for (i, relevance) in [10, 30, 20].iter().enumerate() {
+///     let news_item = NewsItem {
+///         channel: "El Diario".to_string(),
+///         link: format!("https://www.acme.es/{}.html", i),
+///         relevance: Some(*relevance),
+///         ..Default::default()
+///     };
+///     news_item.insert(&conn).unwrap();
+/// }
+///
+/// let news_items = NewsItemQuery::new()
+///     .channel("el diario")
+///     .min_relevance(15)
+///     .error(ErrorFilter::NoError)
+///     .order_by(NewsItemOrder::Relevance, true)
+///     .limit(1)
+///     .fetch(&conn)
+///     .unwrap();
+/// assert_eq!(news_items.len(), 1);
+/// assert_eq!(news_items[0].relevance, Some(30));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct NewsItemQuery {
+    channel: Option<String>,
+    since: Option<i64>,
+    until: Option<i64>,
+    category: Option<String>,
+    error: Option<ErrorFilter>,
+    min_relevance: Option<u64>,
+    has_content: Option<bool>,
+    order: NewsItemOrder,
+    descending: bool,
+    limit: Option<usize>,
+    offset: usize,
+}
+
+impl NewsItemQuery {
+    /// Function that returns a query matching all the stored NewsItems
+    pub fn new() -> NewsItemQuery {
+        NewsItemQuery::default()
+    }
+
+    /// Only items whose channel is the given one (case insensitive)
+    pub fn channel(mut self, channel: &str) -> NewsItemQuery {
+        self.channel = Some(channel.to_string());
+        self
+    }
+
+    /// Only items published at or after the given time
+    pub fn since(mut self, since: DateTime<Utc>) -> NewsItemQuery {
+        self.since = Some(since.timestamp());
+        self
+    }
+
+    /// Only items published before the given time
+    pub fn until(mut self, until: DateTime<Utc>) -> NewsItemQuery {
+        self.until = Some(until.timestamp());
+        self
+    }
+
+    /// Only items with the given category (case insensitive)
+    pub fn category(mut self, category: &str) -> NewsItemQuery {
+        self.category = Some(category.to_string());
+        self
+    }
+
+    /// Only items with the given error state
+    pub fn error(mut self, error: ErrorFilter) -> NewsItemQuery {
+        self.error = Some(error);
+        self
+    }
+
+    /// Only items with at least the given relevance
+    pub fn min_relevance(mut self, min_relevance: u64) -> NewsItemQuery {
+        self.min_relevance = Some(min_relevance);
+        self
+    }
+
+    /// Only items with (or without) clean content
+    pub fn has_content(mut self, has_content: bool) -> NewsItemQuery {
+        self.has_content = Some(has_content);
+        self
+    }
+
+    /// Order of the results
+    pub fn order_by(mut self, order: NewsItemOrder, descending: bool) -> NewsItemQuery {
+        self.order = order;
+        self.descending = descending;
+        self
+    }
+
+    /// Maximum number of results
+    pub fn limit(mut self, limit: usize) -> NewsItemQuery {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Number of results to skip
+    pub fn offset(mut self, offset: usize) -> NewsItemQuery {
+        self.offset = offset;
+        self
+    }
+
+    /// Function that returns the WHERE clause of the query
+    pub(crate) fn where_clause(&self) -> String {
+        let mut conditions = Vec::new();
+        if self.channel.is_some() {
+            conditions.push("lower(channel) = lower(:channel)");
+        }
+        if self.since.is_some() {
+            conditions.push("pub_timestamp >= :since");
+        }
+        if self.until.is_some() {
+            conditions.push("pub_timestamp < :until");
+        }
+        if self.category.is_some() {
+            conditions.push("instr(',' || lower(categories) || ',', ',' || lower(:category) || ',') > 0");
+        }
+        match self.error {
+            Some(ErrorFilter::NoError) => conditions.push("(error IS NULL OR error = 'None')"),
+            Some(ErrorFilter::AnyError) => conditions.push("(error IS NOT NULL AND error != 'None')"),
+            Some(ErrorFilter::Kind(_)) => conditions.push("(error = :error OR error LIKE :error || '(%')"),
+            None => {}
+        }
+        if self.min_relevance.is_some() {
+            conditions.push("relevance >= :min_relevance");
+        }
+        match self.has_content {
+            Some(true) => conditions.push("(clean_content IS NOT NULL AND clean_content != '')"),
+            Some(false) => conditions.push("(clean_content IS NULL OR clean_content = '')"),
+            None => {}
+        }
+
+        if conditions.is_empty() {
+            String::new()
+        } else {
+            format!(" WHERE {}", conditions.join(" AND "))
+        }
+    }
+
+    /// Function that binds the values of the filters to a statement
+    pub(crate) fn bind(&self, statement: &mut sqlite::Statement) -> sqlite::Result<()> {
+        if let Some(channel) = &self.channel {
+            statement.bind((":channel", channel.as_str()))?;
+        }
+        if let Some(since) = self.since {
+            statement.bind((":since", since))?;
+        }
+        if let Some(until) = self.until {
+            statement.bind((":until", until))?;
+        }
+        if let Some(category) = &self.category {
+            statement.bind((":category", category.trim()))?;
+        }
+        if let Some(ErrorFilter::Kind(kind)) = &self.error {
+            statement.bind((":error", kind.as_str()))?;
+        }
+        if let Some(min_relevance) = self.min_relevance {
+            statement.bind((":min_relevance", min_relevance as i64))?;
+        }
+        Ok(())
+    }
+
+    /// Function that runs the query and returns the matching NewsItems
+    pub fn fetch(&self, conn: &Connection) -> sqlite::Result<Vec<NewsItem>> {
+        let mut sql = format!("SELECT {} FROM news_item{}", NEWS_ITEM_COLUMNS, self.where_clause());
+        sql.push_str(&format!(
+            " ORDER BY {} {}, id",
+            self.order.as_column(),
+            if self.descending { "DESC" } else { "ASC" }
+        ));
+        // SQLite needs a LIMIT to use an OFFSET, -1 means no limit
+        sql.push_str(&format!(
+            " LIMIT {} OFFSET {}",
+            self.limit.map_or(-1, |limit| limit as i64),
+            self.offset
+        ));
+
+        let mut statement = conn.prepare(sql)?;
+        self.bind(&mut statement)?;
+        let mut news_items = Vec::new();
+        while let State::Row = statement.next()? {
+            news_items.push(NewsItem::read_row(&statement)?);
+        }
+        Ok(news_items)
+    }
+
+    /// Function that returns the number of NewsItems matching the filters,
+    /// ignoring the limit and offset
+    pub fn count(&self, conn: &Connection) -> sqlite::Result<usize> {
+        let mut statement = conn.prepare(format!("SELECT COUNT(*) FROM news_item{}", self.where_clause()))?;
+        self.bind(&mut statement)?;
+        statement.next()?;
+        Ok(statement.read::<i64, _>(0)? as usize)
+    }
+
+    /// Function that returns true if a NewsItem matches the filters of the
+    /// query, the same way the database does
+    pub fn matches(&self, news_item: &NewsItem) -> bool {
+        let timestamp = pub_timestamp(news_item.pub_date.as_deref());
+        let has_content = news_item.clean_content.as_deref().is_some_and(|content| !content.is_empty());
+        let error = news_item.error.as_ref().map(|error| error.as_str());
+
+        self.channel
+            .as_ref()
+            .is_none_or(|channel| news_item.channel.to_lowercase() == channel.to_lowercase())
+            && self.since.is_none_or(|since| timestamp.is_some_and(|t| t >= since))
+            && self.until.is_none_or(|until| timestamp.is_some_and(|t| t < until))
+            && self.category.as_ref().is_none_or(|category| {
+                news_item
+                    .categories
+                    .as_deref()
+                    .unwrap_or("")
+                    .split(',')
+                    .any(|c| c.to_lowercase() == category.trim().to_lowercase())
+            })
+            && match &self.error {
+                Some(ErrorFilter::NoError) => error.is_none(),
+                Some(ErrorFilter::AnyError) => error.is_some(),
+                Some(ErrorFilter::Kind(kind)) => error == Some(kind.as_str()),
+                None => true,
+            }
+            && self
+                .min_relevance
+                .is_none_or(|min_relevance| news_item.relevance.is_some_and(|r| r >= min_relevance))
+            && self.has_content.is_none_or(|has| has == has_content)
+    }
+
+    /// Function that runs the query over NewsItems kept in memory in the
+    /// order they were stored
+    pub fn apply(&self, news_items: &[NewsItem]) -> Vec<NewsItem> {
+        let mut matching = news_items
+            .iter()
+            .filter(|news_item| self.matches(news_item))
+            .collect::<Vec<&NewsItem>>();
+
+        // The sort is stable so ties keep the stored order as in the database
+        match self.order {
+            NewsItemOrder::Stored => {}
+            NewsItemOrder::PubDate => matching.sort_by_key(|news_item| pub_timestamp(news_item.pub_date.as_deref())),
+            NewsItemOrder::Relevance => matching.sort_by_key(|news_item| news_item.relevance),
+            NewsItemOrder::Title => matching.sort_by(|a, b| a.title.cmp(&b.title)),
+        }
+        if self.descending {
+            // Reverse keeping the stored order of the ties
+            let mut groups: Vec<Vec<&NewsItem>> = Vec::new();
+            for news_item in matching {
+                let same = groups.last().is_some_and(|group| self.same_key(group[0], news_item));
+                match groups.last_mut() {
+                    Some(group) if same => group.push(news_item),
+                    _ => groups.push(vec![news_item]),
+                }
+            }
+            matching = groups.into_iter().rev().flatten().collect();
+        }
+
+        matching
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Function that returns true if two NewsItems have the same order key
+    fn same_key(&self, a: &NewsItem, b: &NewsItem) -> bool {
+        match self.order {
+            NewsItemOrder::Stored => false,
+            NewsItemOrder::PubDate => pub_timestamp(a.pub_date.as_deref()) == pub_timestamp(b.pub_date.as_deref()),
+            NewsItemOrder::Relevance => a.relevance == b.relevance,
+            NewsItemOrder::Title => a.title == b.title,
+        }
+    }
+}
+
+/// Function that parses a publication date in RFC 2822 or RFC 3339 format
+pub(crate) fn parse_pub_date(pub_date: Option<&str>) -> Option<DateTime<FixedOffset>> {
+    let pub_date = pub_date?.trim();
+    DateTime::parse_from_rfc2822(pub_date)
+        .or_else(|_| DateTime::parse_from_rfc3339(pub_date))
+        .ok()
+}
+
+/// Function that returns the timestamp of a publication date in RFC 2822 or
+/// RFC 3339 format
+fn pub_timestamp(pub_date: Option<&str>) -> Option<i64> {
+    parse_pub_date(pub_date).map(|date| date.timestamp())
+}
+
+/// Function that fills the pub_timestamp of the rows stored before the
+/// column existed
+fn backfill_pub_timestamps(conn: &Connection) -> sqlite::Result<()> {
+    let mut statement =
+        conn.prepare("SELECT id, pub_date FROM news_item WHERE pub_timestamp IS NULL AND pub_date != ''")?;
+    let mut rows = Vec::new();
+    while let State::Row = statement.next()? {
+        let id = statement.read::<i64, _>(0)?;
+        if let Some(timestamp) = pub_timestamp(statement.read::<Option<String>, _>(1)?.as_deref()) {
+            rows.push((id, timestamp));
+        }
+    }
+
+    for (id, timestamp) in rows {
+        let mut statement = conn.prepare("UPDATE news_item SET pub_timestamp = :pub_timestamp WHERE id = :id")?;
+        statement.bind((":pub_timestamp", timestamp))?;
+        statement.bind((":id", id))?;
+        statement.next()?;
+    }
+    Ok(())
+}
+
+/// Function that returns the current version of a schema, 0 if no migration
+/// has been applied
+pub fn schema_version(conn: &Connection, schema: &str) -> sqlite::Result<i64> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS schema_version (
+            schema          TEXT NOT NULL,
+            version         INTEGER NOT NULL,
+            description     TEXT NOT NULL,
+            applied_at      TEXT NOT NULL,
+            PRIMARY KEY (schema, version)
+        )",
+    )?;
+    let mut statement = conn.prepare("SELECT COALESCE(MAX(version), 0) FROM schema_version WHERE schema = :schema")?;
+    statement.bind((":schema", schema))?;
+    statement.next()?;
+    statement.read::<i64, _>(0)
+}
+
+/// Function that applies in order the migrations of a schema newer than its
+/// current version and returns the resulting version. Each migration runs in
+/// its own transaction
+///
+/// Example:
+/// ```
+/// use hemeroteca::storage::{migrate, schema_version, NEWS_ITEM_MIGRATIONS, NEWS_ITEM_SCHEMA};
+///
+/// let conn = sqlite::open(":memory:").unwrap();
+/// let version = migrate(&conn, NEWS_ITEM_SCHEMA, NEWS_ITEM_MIGRATIONS).unwrap();
+/// assert_eq!(version, NEWS_ITEM_MIGRATIONS.len() as i64);
+/// assert_eq!(schema_version(&conn, NEWS_ITEM_SCHEMA).unwrap(), version);
+/// ```
+pub fn migrate(conn: &Connection, schema: &str, migrations: &[Migration]) -> sqlite::Result<i64> {
+    let current_version = schema_version(conn, schema)?;
+    let mut version = current_version;
+
+    for migration in migrations.iter().filter(|migration| migration.version > current_version) {
+        log::debug!(
+            "Migrating {} to version {}: {}",
+            schema,
+            migration.version,
+            migration.description
+        );
+        conn.execute("BEGIN")?;
+        let result = conn.execute(migration.sql).and_then(|_| {
+            let mut statement = conn.prepare(
+                "INSERT INTO schema_version (schema, version, description, applied_at)
+                 VALUES (:schema, :version, :description, :applied_at)",
+            )?;
+            statement.bind((":schema", schema))?;
+            statement.bind((":version", migration.version))?;
+            statement.bind((":description", migration.description))?;
+            statement.bind((":applied_at", chrono::Utc::now().to_rfc3339().as_str()))?;
+            statement.next()?;
+            Ok(())
+        });
+        match result {
+            Ok(_) => conn.execute("COMMIT")?,
+            Err(err) => {
+                conn.execute("ROLLBACK")?;
+                return Err(err);
+            }
+        }
+        version = migration.version;
+    }
+
+    Ok(version)
+}
+
+/// Function that serializes entities to be stored in a column as a JSON array
+fn entities_to_column(entities: &[Entity]) -> Option<String> {
+    if entities.is_empty() {
+        None
+    } else {
+        serde_json::to_string(entities).ok()
+    }
+}
+
+/// Function that deserializes the entities stored in a column as a JSON array
+fn entities_from_column(entities: Option<String>) -> Result<Vec<Entity>, serde_json::Error> {
+    match entities {
+        Some(entities) => serde_json::from_str(&entities),
+        None => Ok(vec![]),
+    }
+}
+
 impl NewsItem {
     /// Function that returns a Bindable slice of tuples with the values of the
     /// NewsItem
@@ -26,6 +593,8 @@ impl NewsItem {
     ///    creators: "John Doe".to_string(),
     ///    error: None,
     ///    relevance: None,
+    ///    entities: vec![],
+    ///    raw_html: None,
     /// };
     ///
     /// let binds = news_item.binds();
@@ -68,7 +637,8 @@ impl NewsItem {
         ]
     }
 
-    /// Function that create a table in the database to store the news items
+    /// Function that creates the table in the database to store the news
+    /// items or migrates it to the latest schema
     ///
     /// Example:
     /// ```
@@ -79,79 +649,577 @@ impl NewsItem {
     /// assert_eq!(result.is_ok(), true);
     /// ```
     pub fn create_table(conn: &Connection) -> sqlite::Result<()> {
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS news_item (
-                id              INTEGER PRIMARY KEY,
-                channel         TEXT NOT NULL,
-                title           TEXT NOT NULL,
-                link            TEXT NOT NULL UNIQUE,
-                description     TEXT NOT NULL,
-                creators        TEXT,
-                pub_date        TEXT,
-                categories      TEXT,
-                keywords        TEXT,
-                clean_content   TEXT,
-                error           TEXT
-            )",
-        )?;
+        migrate(conn, NEWS_ITEM_SCHEMA, NEWS_ITEM_MIGRATIONS)?;
+        backfill_pub_timestamps(conn)?;
         Ok(())
     }
 
     pub fn insert(&self, conn: &Connection) -> sqlite::Result<()> {
         let mut statement = conn.prepare(
-            "INSERT INTO news_item (channel, title, link, description, creators, pub_date, categories, keywords, clean_content, error) 
-             VALUES (:channel, :title, :link, :description, :creators, :pub_date, :categories, :keywords, :clean_content, :error)",
+            "INSERT INTO news_item (channel, title, link, description, creators, pub_date, categories, keywords, clean_content, error, relevance, entities, pub_timestamp) 
+             VALUES (:channel, :title, :link, :description, :creators, :pub_date, :categories, :keywords, :clean_content, :error, :relevance, :entities, :pub_timestamp)",
         )?;
         // Bind the values
         statement.bind(&self.binds()[..])?;
+        statement.bind((":pub_timestamp", pub_timestamp(self.pub_date.as_deref())))?;
+        statement.bind((":relevance", self.relevance.map(|r| r as i64)))?;
+        statement.bind((":entities", entities_to_column(&self.entities).as_deref()))?;
 
         statement.next()?; // Execute the statement
         Ok(())
     }
 
+    /// Function that reads a NewsItem from a row with the columns in
+    /// NEWS_ITEM_COLUMNS order
+    pub(crate) fn read_row(statement: &sqlite::Statement) -> sqlite::Result<NewsItem> {
+        let channel: String = statement.read(0)?;
+        let title: String = statement.read(1)?;
+        let link: String = statement.read(2)?;
+        let description: String = statement.read(3)?;
+        let creators: String = statement.read(4)?;
+        let pub_date: Option<String> = statement.read::<Option<String>, _>(5)?;
+        let categories: Option<String> = statement.read::<Option<String>, _>(6)?;
+        let keywords: Option<String> = statement.read::<Option<String>, _>(7)?;
+        let clean_content: Option<String> = statement.read::<Option<String>, _>(8)?;
+        let error: Option<String> = statement.read::<Option<String>, _>(9)?;
+        let relevance: Option<i64> = statement.read::<Option<i64>, _>(10)?;
+        let entities: Option<String> = statement.read::<Option<String>, _>(11)?;
+
+        // Empty strings are stored for the missing optional values
+        let non_empty = |value: Option<String>| value.filter(|v| !v.is_empty());
+
+        let entities = entities_from_column(non_empty(entities)).unwrap_or_else(|err| {
+            log::warn!("Could not parse the entities of {}: {}", link, err);
+            vec![]
+        });
+
+        let error = match error {
+            Some(e) => match e.as_str() {
+                "None" => None,
+                _ => match PipelineError::from_str(&e) {
+                    Ok(e) => Some(e),
+                    Err(_) => Some(PipelineError::UnknownError),
+                },
+            },
+            None => None,
+        };
+
+        Ok(NewsItem {
+            channel,
+            title,
+            link,
+            description,
+            creators,
+            pub_date: non_empty(pub_date),
+            categories: non_empty(categories),
+            keywords: non_empty(keywords),
+            clean_content: non_empty(clean_content),
+            error,
+            relevance: relevance.map(|r| r as u64),
+            entities,
+            raw_html: None,
+        })
+    }
+
+    /// Function that returns all the stored NewsItems, use NewsItemQuery to
+    /// filter them
     pub fn query_all(conn: &Connection) -> sqlite::Result<Vec<NewsItem>> {
+        NewsItemQuery::new().fetch(conn)
+    }
+
+    /// Function that returns the stored NewsItem with the given link
+    pub fn query_by_link(conn: &Connection, link: &str) -> sqlite::Result<Option<NewsItem>> {
+        let mut statement = conn.prepare(format!("SELECT {} FROM news_item WHERE link = :link", NEWS_ITEM_COLUMNS))?;
+        statement.bind((":link", link))?;
+        match statement.next()? {
+            State::Row => Ok(Some(NewsItem::read_row(&statement)?)),
+            State::Done => Ok(None),
+        }
+    }
+
+    /// Function that merges a newer version of the NewsItem into this one.
+    /// Values missing in the newer version are kept, so a failed refetch does
+    /// not erase the content fetched before
+    pub fn merge(&self, newer: &NewsItem) -> NewsItem {
+        let pick = |old: &String, new: &String| if new.is_empty() { old.clone() } else { new.clone() };
+        let pick_option = |old: &Option<String>, new: &Option<String>| match new {
+            Some(value) if !value.is_empty() => Some(value.clone()),
+            _ => old.clone(),
+        };
+
+        // The error belongs to the content, keep the old one if the old content is kept
+        let error = if newer.clean_content.is_some() || self.clean_content.is_none() {
+            newer.error.clone()
+        } else {
+            self.error.clone()
+        };
+
+        NewsItem {
+            channel: pick(&self.channel, &newer.channel),
+            title: pick(&self.title, &newer.title),
+            link: self.link.clone(),
+            description: pick(&self.description, &newer.description),
+            creators: pick(&self.creators, &newer.creators),
+            pub_date: pick_option(&self.pub_date, &newer.pub_date),
+            categories: pick_option(&self.categories, &newer.categories),
+            keywords: pick_option(&self.keywords, &newer.keywords),
+            clean_content: pick_option(&self.clean_content, &newer.clean_content),
+            error,
+            relevance: newer.relevance.or(self.relevance),
+            entities: if newer.entities.is_empty() {
+                self.entities.clone()
+            } else {
+                newer.entities.clone()
+            },
+            raw_html: newer.raw_html.clone().or_else(|| self.raw_html.clone()),
+        }
+    }
+
+    /// Function that returns true if two NewsItems store the same values
+    pub(crate) fn same_values(&self, other: &NewsItem) -> bool {
+        self.binds() == other.binds() && self.relevance == other.relevance && self.entities == other.entities
+    }
+
+    /// Function that inserts the NewsItem or, if its link is already stored,
+    /// merges it into the stored one recording the changes of the title,
+    /// description and clean content as revisions
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::storage::UpsertOutcome;
+    ///
+    /// let conn = sqlite::open(":memory:").unwrap();
+    /// NewsItem::create_table(&conn).unwrap();
+    ///
+    /// let mut news_item = NewsItem {
+    ///     title: "Title".to_string(),
+    ///     link: "https://www.acme.es/1.html".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(news_item.upsert(&conn).unwrap(), UpsertOutcome::Inserted);
+    /// assert_eq!(news_item.upsert(&conn).unwrap(), UpsertOutcome::Unchanged);
+    /// news_item.title = "New title".to_string();
+    /// assert_eq!(news_item.upsert(&conn).unwrap(), UpsertOutcome::Updated);
+    /// assert_eq!(NewsItem::revisions(&conn, &news_item.link).unwrap().len(), 1);
+    /// ```
+    pub fn upsert(&self, conn: &Connection) -> sqlite::Result<UpsertOutcome> {
+        // A savepoint instead of BEGIN so the upsert can run inside the
+        // transaction of the archive runs
+        conn.execute("SAVEPOINT upsert")?;
+        match self.upsert_in_transaction(conn) {
+            Ok(outcome) => {
+                conn.execute("RELEASE upsert")?;
+                Ok(outcome)
+            }
+            Err(err) => {
+                conn.execute("ROLLBACK TO upsert")?;
+                conn.execute("RELEASE upsert")?;
+                Err(err)
+            }
+        }
+    }
+
+    fn upsert_in_transaction(&self, conn: &Connection) -> sqlite::Result<UpsertOutcome> {
+        let stored = match NewsItem::query_by_link(conn, &self.link)? {
+            Some(stored) => stored,
+            None => {
+                self.insert(conn)?;
+                return Ok(UpsertOutcome::Inserted);
+            }
+        };
+
+        let merged = stored.merge(self);
+        if merged.same_values(&stored) {
+            return Ok(UpsertOutcome::Unchanged);
+        }
+
+        // Record the revisions of the fields readers care about
+        let changed_at = chrono::Utc::now().to_rfc3339();
+        for (field, old_value, new_value) in [
+            ("title", stored.title.as_str(), merged.title.as_str()),
+            ("description", stored.description.as_str(), merged.description.as_str()),
+            (
+                "clean_content",
+                stored.clean_content.as_deref().unwrap_or(""),
+                merged.clean_content.as_deref().unwrap_or(""),
+            ),
+        ] {
+            if !old_value.is_empty() && old_value != new_value {
+                let mut statement = conn.prepare(
+                    "INSERT INTO news_item_revision (link, field, old_value, new_value, changed_at)
+                     VALUES (:link, :field, :old_value, :new_value, :changed_at)",
+                )?;
+                statement.bind(
+                    &[
+                        (":link", self.link.as_str()),
+                        (":field", field),
+                        (":old_value", old_value),
+                        (":new_value", new_value),
+                        (":changed_at", changed_at.as_str()),
+                    ][..],
+                )?;
+                statement.next()?;
+            }
+        }
+
         let mut statement = conn.prepare(
-            "SELECT channel, title, link, description, creators, pub_date, categories, keywords, clean_content, error FROM news_item",
+            "UPDATE news_item SET channel = :channel, title = :title, description = :description,
+                creators = :creators, pub_date = :pub_date, categories = :categories, keywords = :keywords,
+                clean_content = :clean_content, error = :error, relevance = :relevance, entities = :entities,
+                pub_timestamp = :pub_timestamp
+             WHERE link = :link",
         )?;
+        statement.bind(&merged.binds()[..])?;
+        statement.bind((":pub_timestamp", pub_timestamp(merged.pub_date.as_deref())))?;
+        statement.bind((":relevance", merged.relevance.map(|r| r as i64)))?;
+        statement.bind((":entities", entities_to_column(&merged.entities).as_deref()))?;
+        statement.next()?;
 
-        let mut news_items = Vec::new();
+        Ok(UpsertOutcome::Updated)
+    }
+
+    /// Function that returns the revisions of the NewsItem with the given link
+    /// from the oldest to the newest
+    pub fn revisions(conn: &Connection, link: &str) -> sqlite::Result<Vec<Revision>> {
+        let mut statement = conn.prepare(
+            "SELECT link, field, old_value, new_value, changed_at FROM news_item_revision
+             WHERE link = :link ORDER BY id",
+        )?;
+        statement.bind((":link", link))?;
+
+        let mut revisions = Vec::new();
         while let State::Row = statement.next()? {
-            let channel: String = statement.read(0)?;
-            let title: String = statement.read(1)?;
-            let link: String = statement.read(2)?;
-            let description: String = statement.read(3)?;
-            let creators: String = statement.read(4)?;
-            let pub_date: Option<String> = statement.read::<Option<String>, _>(5)?;
-            let categories: Option<String> = statement.read::<Option<String>, _>(6)?;
-            let keywords: Option<String> = statement.read::<Option<String>, _>(7)?;
-            let clean_content: Option<String> = statement.read::<Option<String>, _>(8)?;
-            let error: Option<String> = statement.read::<Option<String>, _>(9)?;
-
-            let error = match error {
-                Some(e) => match e.as_str() {
-                    "None" => None,
-                    _ => match PipelineError::from_str(&e) {
-                        Ok(e) => Some(e),
-                        Err(_) => Some(PipelineError::UnknownError),
-                    },
-                },
-                None => None,
-            };
-
-            news_items.push(NewsItem {
-                channel,
-                title,
-                link,
-                description,
-                creators,
-                pub_date,
-                categories,
-                keywords,
-                clean_content,
-                error,
-                relevance: None,
+            revisions.push(Revision {
+                link: statement.read(0)?,
+                field: statement.read(1)?,
+                old_value: statement.read(2)?,
+                new_value: statement.read(3)?,
+                changed_at: statement.read(4)?,
             });
         }
-        Ok(news_items)
+        Ok(revisions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+
+    // Creates a database with the schema used before the migrations existed
+    fn create_v1_database(conn: &Connection) {
+        conn.execute(NEWS_ITEM_MIGRATIONS[0].sql).unwrap();
+        conn.execute(
+            "INSERT INTO news_item (channel, title, link, description, creators, pub_date, categories, keywords, clean_content, error)
+             VALUES ('Other', 'Title 1', 'https://www.acme.es/1.html', 'Description', '', '', '', '', 'Content', 'None')",
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_migrate_v1_database() {
+        let conn = sqlite::open(":memory:").unwrap();
+        create_v1_database(&conn);
+        assert_eq!(schema_version(&conn, NEWS_ITEM_SCHEMA).unwrap(), 0);
+
+        NewsItem::create_table(&conn).unwrap();
+        assert_eq!(
+            schema_version(&conn, NEWS_ITEM_SCHEMA).unwrap(),
+            NEWS_ITEM_MIGRATIONS.last().unwrap().version
+        );
+
+        // The old rows are still readable and the new columns are empty
+        let news_items = NewsItem::query_all(&conn).unwrap();
+        assert_eq!(news_items.len(), 1);
+        assert_eq!(news_items[0].title, "Title 1");
+        assert_eq!(news_items[0].clean_content, Some("Content".to_string()));
+        assert_eq!(news_items[0].relevance, None);
+        assert!(news_items[0].entities.is_empty());
+    }
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+        NewsItem::create_table(&conn).unwrap();
+
+        let mut statement = conn.prepare("SELECT COUNT(*) FROM schema_version").unwrap();
+        statement.next().unwrap();
+        assert_eq!(statement.read::<i64, _>(0).unwrap(), NEWS_ITEM_MIGRATIONS.len() as i64);
+    }
+
+    #[test]
+    fn test_failed_migration_is_rolled_back() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let migrations = [
+            NEWS_ITEM_MIGRATIONS[0],
+            Migration {
+                version: 2,
+                description: "Broken migration",
+                sql: "ALTER TABLE news_item ADD COLUMN relevance INTEGER; ALTER TABLE missing ADD COLUMN x TEXT",
+            },
+        ];
+        assert!(migrate(&conn, NEWS_ITEM_SCHEMA, &migrations).is_err());
+        assert_eq!(schema_version(&conn, NEWS_ITEM_SCHEMA).unwrap(), 1);
+
+        // The latest migrations can still be applied after the failure
+        assert!(NewsItem::create_table(&conn).is_ok());
+    }
+
+    #[test]
+    fn test_insert_and_query_new_columns() {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+
+        let news_item = NewsItem {
+            channel: "Other".to_string(),
+            title: "Title".to_string(),
+            link: "https://www.acme.es/1.html".to_string(),
+            description: "Description".to_string(),
+            relevance: Some(42),
+            entities: vec![Entity {
+                name: "Banco de España".to_string(),
+                kind: EntityKind::Organization,
+            }],
+            ..Default::default()
+        };
+        news_item.insert(&conn).unwrap();
+
+        let news_items = NewsItem::query_all(&conn).unwrap();
+        assert_eq!(news_items[0].relevance, Some(42));
+        assert_eq!(news_items[0].entities, news_item.entities);
+    }
+
+    #[test]
+    fn test_entities_column() {
+        let entities = vec![
+            Entity {
+                name: "Sánchez; Feijóo".to_string(),
+                kind: EntityKind::Person,
+            },
+            Entity {
+                name: "Banco de España".to_string(),
+                kind: EntityKind::Organization,
+            },
+        ];
+        assert_eq!(entities_from_column(entities_to_column(&entities)).unwrap(), entities);
+        assert_eq!(entities_to_column(&[]), None);
+        assert_eq!(entities_from_column(None).unwrap(), vec![]);
+
+        // Malformed columns are reported instead of being guessed
+        let malformed = Some("Person:Pedro Sánchez;Organization:Banco de España".to_string());
+        assert!(entities_from_column(malformed).is_err());
+    }
+
+    #[test]
+    fn test_full_text_index_is_kept_in_sync() {
+        let conn = sqlite::open(":memory:").unwrap();
+        create_v1_database(&conn);
+        NewsItem::create_table(&conn).unwrap();
+
+        let count = |query: &str| {
+            let mut statement = conn
+                .prepare("SELECT COUNT(*) FROM news_item_fts WHERE news_item_fts MATCH :query")
+                .unwrap();
+            statement.bind((":query", query)).unwrap();
+            statement.next().unwrap();
+            statement.read::<i64, _>(0).unwrap()
+        };
+
+        // The rows of the migrated database are indexed
+        assert_eq!(count("content"), 1);
+
+        let mut news_item = NewsItem {
+            title: "La economía crece".to_string(),
+            link: "https://www.acme.es/2.html".to_string(),
+            ..Default::default()
+        };
+        news_item.insert(&conn).unwrap();
+        assert_eq!(count("economia"), 1);
+
+        news_item.title = "La inflación baja".to_string();
+        news_item.upsert(&conn).unwrap();
+        assert_eq!(count("economia"), 0);
+        assert_eq!(count("inflacion"), 1);
+
+        conn.execute("DELETE FROM news_item").unwrap();
+        assert_eq!(count("inflacion OR content"), 0);
+    }
+
+    #[test]
+    fn test_upsert_merges_and_tracks_revisions() {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+
+        let news_item = NewsItem {
+            channel: "Other".to_string(),
+            title: "Sánchez dimite".to_string(),
+            link: "https://www.acme.es/1.html".to_string(),
+            description: "Description".to_string(),
+            error: Some(PipelineError::NoContent),
+            ..Default::default()
+        };
+        assert_eq!(news_item.upsert(&conn).unwrap(), UpsertOutcome::Inserted);
+
+        // The refetched item has a corrected title and the content now
+        let refetched = NewsItem {
+            title: "Sánchez no dimite".to_string(),
+            clean_content: Some("Content".to_string()),
+            error: None,
+            relevance: Some(7),
+            ..news_item.clone()
+        };
+        assert_eq!(refetched.upsert(&conn).unwrap(), UpsertOutcome::Updated);
+
+        // A failed refetch does not erase the content
+        let failed = NewsItem {
+            error: Some(PipelineError::NetworkError("timeout".to_string())),
+            ..news_item.clone()
+        };
+        assert_eq!(failed.upsert(&conn).unwrap(), UpsertOutcome::Updated);
+
+        let stored = NewsItem::query_by_link(&conn, &news_item.link).unwrap().unwrap();
+        assert_eq!(stored.title, "Sánchez dimite");
+        assert_eq!(stored.clean_content, Some("Content".to_string()));
+        assert!(stored.error.is_none());
+        assert_eq!(stored.relevance, Some(7));
+
+        let revisions = NewsItem::revisions(&conn, &news_item.link).unwrap();
+        let changes = revisions
+            .iter()
+            .map(|r| (r.field.as_str(), r.old_value.as_str(), r.new_value.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            changes,
+            vec![
+                ("title", "Sánchez dimite", "Sánchez no dimite"),
+                ("title", "Sánchez no dimite", "Sánchez dimite"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failed_upsert_keeps_no_revisions() {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+
+        let news_item = NewsItem {
+            title: "Sánchez dimite".to_string(),
+            link: "https://www.acme.es/1.html".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(news_item.upsert(&conn).unwrap(), UpsertOutcome::Inserted);
+
+        // The update fails after the revision was recorded
+        conn.execute(
+            "CREATE TRIGGER fail_update BEFORE UPDATE ON news_item BEGIN SELECT RAISE(ABORT, 'failed'); END",
+        )
+        .unwrap();
+        let refetched = NewsItem {
+            title: "Sánchez no dimite".to_string(),
+            ..news_item.clone()
+        };
+        assert!(refetched.upsert(&conn).is_err());
+        assert!(NewsItem::revisions(&conn, &news_item.link).unwrap().is_empty());
+
+        // The upsert also rolls back inside an outer transaction
+        conn.execute("BEGIN").unwrap();
+        assert!(refetched.upsert(&conn).is_err());
+        conn.execute("COMMIT").unwrap();
+        assert!(NewsItem::revisions(&conn, &news_item.link).unwrap().is_empty());
+    }
+
+    fn stored_news_items() -> Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        NewsItem::create_table(&conn).unwrap();
+        let items = [
+            ("El Diario", "Mon, 01 Sep 2025 10:00:00 +0000", "Economía,Banca", None, Some(10), true),
+            ("El Diario", "Wed, 01 Oct 2025 10:00:00 +0000", "Deportes", Some(PipelineError::NoContent), Some(50), false),
+            ("La Gaceta", "Thu, 02 Oct 2025 10:00:00 +0000", "economía", None, Some(30), true),
+            ("La Gaceta", "", "", Some(PipelineError::NetworkError("timeout".to_string())), None, false),
+        ];
+        for (i, (channel, pub_date, categories, error, relevance, has_content)) in items.into_iter().enumerate() {
+            NewsItem {
+                channel: channel.to_string(),
+                title: format!("Title {}", i),
+                link: format!("https://www.acme.es/{}.html", i),
+                pub_date: Some(pub_date.to_string()),
+                categories: Some(categories.to_string()),
+                clean_content: has_content.then(|| "Content".to_string()),
+                error,
+                relevance,
+                ..Default::default()
+            }
+            .insert(&conn)
+            .unwrap();
+        }
+        conn
+    }
+
+    fn titles(news_items: Vec<NewsItem>) -> Vec<String> {
+        news_items.into_iter().map(|news_item| news_item.title).collect()
+    }
+
+    #[test]
+    fn test_news_item_query_filters() {
+        let conn = stored_news_items();
+
+        let query = NewsItemQuery::new().channel("EL DIARIO");
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 0", "Title 1"]);
+
+        let since = DateTime::parse_from_rfc3339("2025-10-01T00:00:00Z").unwrap().to_utc();
+        let until = DateTime::parse_from_rfc3339("2025-10-02T00:00:00Z").unwrap().to_utc();
+        let query = NewsItemQuery::new().since(since).until(until);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 1"]);
+
+        let query = NewsItemQuery::new().category("economía");
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 0", "Title 2"]);
+
+        let query = NewsItemQuery::new().error(ErrorFilter::AnyError);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 1", "Title 3"]);
+        let query = NewsItemQuery::new().error(ErrorFilter::NoError);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 0", "Title 2"]);
+        let query = NewsItemQuery::new().error(ErrorFilter::Kind("NetworkError".to_string()));
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 3"]);
+
+        let query = NewsItemQuery::new().min_relevance(30);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 1", "Title 2"]);
+
+        let query = NewsItemQuery::new().has_content(false);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 1", "Title 3"]);
+    }
+
+    #[test]
+    fn test_news_item_query_order_and_pagination() {
+        let conn = stored_news_items();
+
+        let query = NewsItemQuery::new().order_by(NewsItemOrder::PubDate, true);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 2", "Title 1", "Title 0", "Title 3"]);
+
+        let query = NewsItemQuery::new()
+            .order_by(NewsItemOrder::Relevance, true)
+            .limit(2)
+            .offset(1);
+        let news_items = query.fetch(&conn).unwrap();
+        assert_eq!(titles(news_items.clone()), vec!["Title 2", "Title 0"]);
+        assert_eq!(news_items[0].relevance, Some(30));
+        assert_eq!(news_items[0].clean_content, Some("Content".to_string()));
+        assert_eq!(query.count(&conn).unwrap(), 4);
+
+        let query = NewsItemQuery::new().offset(3);
+        assert_eq!(titles(query.fetch(&conn).unwrap()), vec!["Title 3"]);
+    }
+
+    #[test]
+    fn test_pub_timestamps_are_backfilled() {
+        let conn = sqlite::open(":memory:").unwrap();
+        create_v1_database(&conn);
+        conn.execute("UPDATE news_item SET pub_date = 'Mon, 01 Sep 2025 10:00:00 +0000'")
+            .unwrap();
+        NewsItem::create_table(&conn).unwrap();
+
+        let since = DateTime::parse_from_rfc3339("2025-09-01T00:00:00Z").unwrap().to_utc();
+        assert_eq!(NewsItemQuery::new().since(since).count(&conn).unwrap(), 1);
     }
 }
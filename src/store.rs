@@ -0,0 +1,383 @@
+/// Module for the storage backends of the NewsItems
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::sync::Mutex;
+
+use crate::archive::{canonical_link, Archive, ArchiveSummary};
+use crate::common::{FeedStatus, NewsItem};
+use crate::health::FeedRun;
+use crate::storage::{NewsItemQuery, UpsertOutcome};
+
+/// Trait that represents a store of NewsItems and the runs that saw them.
+/// The archive database is the SQLite implementation and MemoryStore keeps
+/// everything in memory
+pub trait NewsStore {
+    /// Function that inserts a NewsItem
+    fn insert(&self, news_item: &NewsItem) -> Result<(), Box<dyn Error>>;
+
+    /// Function that inserts a NewsItem or merges it into the stored one
+    fn upsert(&self, news_item: &NewsItem) -> Result<UpsertOutcome, Box<dyn Error>>;
+
+    /// Function that returns the stored NewsItems matching a query
+    fn query(&self, query: &NewsItemQuery) -> Result<Vec<NewsItem>, Box<dyn Error>>;
+
+    /// Function that records the rating given to a link and returns false if
+    /// the link is not stored
+    fn rate(&self, link: &str, rating: u64) -> Result<bool, Box<dyn Error>>;
+
+    /// Function that returns true if the link was recorded in a run
+    fn contains(&self, link: &str) -> Result<bool, Box<dyn Error>>;
+
+    /// Function that records the start of a run and returns its id
+    fn start_run(&self, command: &str) -> Result<i64, Box<dyn Error>>;
+
+    /// Function that upserts the NewsItems seen in a run keyed by their
+    /// canonical link
+    fn record_run(&self, run_id: i64, news_items: &[NewsItem]) -> Result<ArchiveSummary, Box<dyn Error>>;
+
+    /// Function that records the status of the feeds fetched in a run
+    fn record_feeds(&self, run_id: i64, feeds: &[FeedStatus]) -> Result<(), Box<dyn Error>>;
+
+    /// Function that returns the status of the feeds in the latest runs that
+    /// fetched feeds, ordered by run
+    fn feed_history(&self, runs: usize) -> Result<Vec<FeedRun>, Box<dyn Error>>;
+}
+
+impl NewsStore for Archive {
+    fn insert(&self, news_item: &NewsItem) -> Result<(), Box<dyn Error>> {
+        Ok(news_item.insert(self.connection())?)
+    }
+
+    fn upsert(&self, news_item: &NewsItem) -> Result<UpsertOutcome, Box<dyn Error>> {
+        Ok(news_item.upsert(self.connection())?)
+    }
+
+    fn query(&self, query: &NewsItemQuery) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+        Ok(query.fetch(self.connection())?)
+    }
+
+    fn rate(&self, link: &str, rating: u64) -> Result<bool, Box<dyn Error>> {
+        Ok(Archive::rate(self, link, rating)?)
+    }
+
+    fn contains(&self, link: &str) -> Result<bool, Box<dyn Error>> {
+        Ok(Archive::contains(self, link)?)
+    }
+
+    fn start_run(&self, command: &str) -> Result<i64, Box<dyn Error>> {
+        Ok(Archive::start_run(self, command)?)
+    }
+
+    fn record_run(&self, run_id: i64, news_items: &[NewsItem]) -> Result<ArchiveSummary, Box<dyn Error>> {
+        Ok(self.record_news_items(run_id, news_items)?)
+    }
+
+    fn record_feeds(&self, run_id: i64, feeds: &[FeedStatus]) -> Result<(), Box<dyn Error>> {
+        Ok(self.record_feed_statuses(run_id, feeds)?)
+    }
+
+    fn feed_history(&self, runs: usize) -> Result<Vec<FeedRun>, Box<dyn Error>> {
+        Ok(Archive::feed_history(self, runs)?)
+    }
+}
+
+/// Struct with the contents of a MemoryStore
+#[derive(Debug, Default)]
+struct MemoryContents {
+    news_items: Vec<NewsItem>,
+    ratings: BTreeMap<String, u64>,
+    runs: Vec<(String, String)>,
+    run_items: BTreeSet<(i64, String)>,
+    feeds: BTreeMap<(i64, String), FeedStatus>,
+}
+
+/// Struct that stores the NewsItems in memory, for tests and library users
+/// that do not want a database
+///
+/// Example:
+/// ```
+/// use hemeroteca::prelude::*;
+/// use hemeroteca::store::{MemoryStore, NewsStore};
+///
+/// let store = MemoryStore::new();
+/// let news_item = NewsItem {
+///     link: "https://www.acme.es/1.html".to_string(),
+///     relevance: Some(10),
+///     ..Default::default()
+/// };
+/// let run_id = store.start_run("dossier").unwrap();
+/// let summary = store.record_run(run_id, &[news_item]).unwrap();
+/// assert_eq!(summary.new_items, 1);
+/// let news_items = store.query(&NewsItemQuery::new().min_relevance(5)).unwrap();
+/// assert_eq!(news_items.len(), 1);
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    contents: Mutex<MemoryContents>,
+}
+
+impl MemoryStore {
+    /// Function that returns an empty MemoryStore
+    pub fn new() -> MemoryStore {
+        MemoryStore::default()
+    }
+
+    /// Function that returns the rating of a link if it was rated
+    pub fn rating(&self, link: &str) -> Option<u64> {
+        self.contents().ratings.get(&canonical_link(link)).copied()
+    }
+
+    fn contents(&self) -> std::sync::MutexGuard<'_, MemoryContents> {
+        self.contents.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl NewsStore for MemoryStore {
+    fn insert(&self, news_item: &NewsItem) -> Result<(), Box<dyn Error>> {
+        let mut contents = self.contents();
+        if contents.news_items.iter().any(|stored| stored.link == news_item.link) {
+            return Err(format!("The link {} is already stored", news_item.link).into());
+        }
+        contents.news_items.push(NewsItem {
+            raw_html: None,
+            ..news_item.clone()
+        });
+        Ok(())
+    }
+
+    fn upsert(&self, news_item: &NewsItem) -> Result<UpsertOutcome, Box<dyn Error>> {
+        let mut contents = self.contents();
+        match contents.news_items.iter_mut().find(|stored| stored.link == news_item.link) {
+            Some(stored) => {
+                let merged = NewsItem {
+                    raw_html: None,
+                    ..stored.merge(news_item)
+                };
+                if merged.same_values(stored) {
+                    Ok(UpsertOutcome::Unchanged)
+                } else {
+                    *stored = merged;
+                    Ok(UpsertOutcome::Updated)
+                }
+            }
+            None => {
+                contents.news_items.push(NewsItem {
+                    raw_html: None,
+                    ..news_item.clone()
+                });
+                Ok(UpsertOutcome::Inserted)
+            }
+        }
+    }
+
+    fn query(&self, query: &NewsItemQuery) -> Result<Vec<NewsItem>, Box<dyn Error>> {
+        Ok(query.apply(&self.contents().news_items))
+    }
+
+    fn rate(&self, link: &str, rating: u64) -> Result<bool, Box<dyn Error>> {
+        let link = canonical_link(link);
+        let mut contents = self.contents();
+        // As in the archive only the items recorded in a run can be rated
+        if !contents.run_items.iter().any(|(_, recorded)| *recorded == link) {
+            return Ok(false);
+        }
+        contents.ratings.insert(link, rating);
+        Ok(true)
+    }
+
+    fn contains(&self, link: &str) -> Result<bool, Box<dyn Error>> {
+        let link = canonical_link(link);
+        Ok(self.contents().run_items.iter().any(|(_, recorded)| *recorded == link))
+    }
+
+    fn start_run(&self, command: &str) -> Result<i64, Box<dyn Error>> {
+        let mut contents = self.contents();
+        contents.runs.push((command.to_string(), chrono::Utc::now().to_rfc3339()));
+        Ok(contents.runs.len() as i64)
+    }
+
+    fn record_run(&self, run_id: i64, news_items: &[NewsItem]) -> Result<ArchiveSummary, Box<dyn Error>> {
+        if run_id < 1 || run_id as usize > self.contents().runs.len() {
+            return Err(format!("The run {} was not started", run_id).into());
+        }
+
+        let mut summary = ArchiveSummary::default();
+        for news_item in news_items {
+            let news_item = NewsItem {
+                link: canonical_link(&news_item.link),
+                ..news_item.clone()
+            };
+            match self.upsert(&news_item)? {
+                UpsertOutcome::Inserted => summary.new_items += 1,
+                UpsertOutcome::Updated => {
+                    summary.seen_items += 1;
+                    summary.updated_items += 1;
+                }
+                UpsertOutcome::Unchanged => summary.seen_items += 1,
+            }
+            self.contents().run_items.insert((run_id, news_item.link));
+        }
+        Ok(summary)
+    }
+
+    fn record_feeds(&self, run_id: i64, feeds: &[FeedStatus]) -> Result<(), Box<dyn Error>> {
+        let mut contents = self.contents();
+        if run_id < 1 || run_id as usize > contents.runs.len() {
+            return Err(format!("The run {} was not started", run_id).into());
+        }
+        for feed in feeds {
+            contents.feeds.insert((run_id, feed.url.clone()), feed.clone());
+        }
+        Ok(())
+    }
+
+    fn feed_history(&self, runs: usize) -> Result<Vec<FeedRun>, Box<dyn Error>> {
+        let contents = self.contents();
+        let run_ids = contents.feeds.keys().map(|(run_id, _)| *run_id).collect::<BTreeSet<i64>>();
+        let latest = run_ids.into_iter().rev().take(runs).collect::<BTreeSet<i64>>();
+        Ok(contents
+            .feeds
+            .iter()
+            .filter(|((run_id, _), _)| latest.contains(run_id))
+            .map(|((run_id, _), feed)| FeedRun {
+                run_id: *run_id,
+                started_at: contents.runs[*run_id as usize - 1].1.clone(),
+                status: feed.clone(),
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::NewsItemOrder;
+
+    fn news_item(link: &str, title: &str, relevance: u64) -> NewsItem {
+        NewsItem {
+            channel: "Other".to_string(),
+            title: title.to_string(),
+            link: link.to_string(),
+            description: "Description".to_string(),
+            relevance: Some(relevance),
+            ..Default::default()
+        }
+    }
+
+    // Runs the same operations on a store and returns what they observed
+    fn exercise(store: &dyn NewsStore) -> (ArchiveSummary, Vec<String>, bool, bool) {
+        let run_id = store.start_run("dossier").unwrap();
+        store
+            .record_run(
+                run_id,
+                &[
+                    news_item("https://www.acme.es/a.html", "A", 10),
+                    news_item("https://www.acme.es/b.html", "B", 30),
+                ],
+            )
+            .unwrap();
+        let run_id = store.start_run("dossier").unwrap();
+        let summary = store
+            .record_run(
+                run_id,
+                &[
+                    news_item("https://www.acme.es/a.html?utm_source=rss", "A (updated)", 20),
+                    news_item("https://www.acme.es/c.html", "C", 20),
+                ],
+            )
+            .unwrap();
+        assert!(store.insert(&news_item("https://www.acme.es/c.html", "C", 1)).is_err());
+
+        let titles = store
+            .query(&NewsItemQuery::new().order_by(NewsItemOrder::Relevance, true).limit(3))
+            .unwrap()
+            .into_iter()
+            .map(|news_item| news_item.title)
+            .collect();
+        let rated = store.rate("https://www.acme.es/b.html#comments", 5).unwrap();
+        let unknown_rated = store.rate("https://www.acme.es/unknown.html", 5).unwrap();
+        (summary, titles, rated, unknown_rated)
+    }
+
+    #[test]
+    fn test_memory_store_behaves_as_the_archive() {
+        let archive = Archive::open(":memory:").unwrap();
+        let memory = MemoryStore::new();
+
+        let expected = (
+            ArchiveSummary {
+                new_items: 1,
+                seen_items: 1,
+                updated_items: 1,
+            },
+            vec!["B".to_string(), "A (updated)".to_string(), "C".to_string()],
+            true,
+            false,
+        );
+        assert_eq!(exercise(&archive), expected);
+        assert_eq!(exercise(&memory), expected);
+        assert_eq!(memory.rating("https://www.acme.es/b.html"), Some(5));
+    }
+
+    #[test]
+    fn test_only_recorded_items_are_rated() {
+        let archive = Archive::open(":memory:").unwrap();
+        let memory = MemoryStore::new();
+        for store in [&archive as &dyn NewsStore, &memory] {
+            store.insert(&news_item("https://www.acme.es/a.html", "A", 10)).unwrap();
+            assert!(!store.rate("https://www.acme.es/a.html", 5).unwrap());
+            assert!(!store.contains("https://www.acme.es/a.html").unwrap());
+
+            let run_id = store.start_run("dossier").unwrap();
+            store.record_run(run_id, &[news_item("https://www.acme.es/a.html", "A", 10)]).unwrap();
+            assert!(store.rate("https://www.acme.es/a.html", 5).unwrap());
+            assert!(store.contains("https://www.acme.es/a.html#comments").unwrap());
+        }
+    }
+
+    // Records the feeds of three runs and returns the run and url of the
+    // history of the two latest ones
+    fn exercise_feeds(store: &dyn NewsStore) -> Vec<(i64, String, Option<u16>)> {
+        let feed = |url: &str, http_status: u16| FeedStatus {
+            url: url.to_string(),
+            http_status: Some(http_status),
+            ..Default::default()
+        };
+        for http_status in [200, 404, 500] {
+            let run_id = store.start_run("dossier").unwrap();
+            store
+                .record_feeds(run_id, &[feed("https://www.acme.es/b.xml", http_status), feed("https://www.acme.es/a.xml", 200)])
+                .unwrap();
+        }
+        // Runs without feeds, e.g. offline runs, are not in the history
+        store.start_run("relevance").unwrap();
+
+        store
+            .feed_history(2)
+            .unwrap()
+            .into_iter()
+            .map(|run| {
+                assert!(!run.started_at.is_empty());
+                (run.run_id, run.status.url, run.status.http_status)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_memory_store_feed_history_behaves_as_the_archive() {
+        let expected = vec![
+            (2, "https://www.acme.es/a.xml".to_string(), Some(200)),
+            (2, "https://www.acme.es/b.xml".to_string(), Some(404)),
+            (3, "https://www.acme.es/a.xml".to_string(), Some(200)),
+            (3, "https://www.acme.es/b.xml".to_string(), Some(500)),
+        ];
+        assert_eq!(exercise_feeds(&Archive::open(":memory:").unwrap()), expected);
+        assert_eq!(exercise_feeds(&MemoryStore::new()), expected);
+    }
+
+    #[test]
+    fn test_memory_store_record_run_requires_a_started_run() {
+        let store = MemoryStore::new();
+        assert!(store.record_run(1, &[news_item("https://www.acme.es/a.html", "A", 1)]).is_err());
+    }
+}
@@ -0,0 +1,514 @@
+/// Module for rendering the reports with Handlebars templates. The default
+/// templates are shipped with the library and can be overridden with files
+/// named `<template>.hbs` in ~/.hemeroteca/templates, e.g. `dossier.md.hbs`
+use std::error::Error;
+use std::path::Path;
+
+use handlebars::Handlebars;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+use crate::common::{NewsItem, DEFAULT_CONFIG_FOLDER_NAME, DEFAULT_TEMPLATES_FOLDER_NAME};
+use crate::entities::{entity_index, Entity};
+use crate::html::channel_color;
+use crate::relevance::score_relevance;
+use crate::section::{flatten_sections, NewsSection};
+use crate::stats::{relevance_statistics, RelevanceStatistics};
+use crate::{generate_anchor, relevance_per_channel};
+
+/// Default templates by name, the ones ending with `.html` escape the values
+const DEFAULT_TEMPLATES: [(&str, &str); 6] = [
+    ("dossier.md", include_str!("../templates/dossier.md.hbs")),
+    ("relevance.md", include_str!("../templates/relevance.md.hbs")),
+    ("news_items.md", include_str!("../templates/news_items.md.hbs")),
+    ("dossier.html", include_str!("../templates/dossier.html.hbs")),
+    ("relevance.html", include_str!("../templates/relevance.html.hbs")),
+    ("style.css", include_str!("../templates/style.css.hbs")),
+];
+
+/// Templates of ~/.hemeroteca/templates loaded on first use
+static TEMPLATES: Lazy<Templates> = Lazy::new(Templates::load);
+
+/// Struct that represents a news item in a report
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportItem {
+    /// Position of the item in the report starting at 1
+    pub position: usize,
+    /// Markdown anchor of the title
+    pub anchor: String,
+    pub channel: String,
+    pub channel_color: String,
+    pub title: String,
+    pub link: String,
+    pub description: String,
+    /// First 50 characters of the description
+    pub short_description: String,
+    pub creators: String,
+    pub pub_date: Option<String>,
+    pub categories: Option<String>,
+    pub keywords: Option<String>,
+    pub clean_content: Option<String>,
+    pub error: Option<String>,
+    pub relevance: u64,
+    /// Explanation of the relevance, only filled with_relevance_breakdown
+    pub relevance_breakdown: String,
+    pub entities: Vec<Entity>,
+}
+
+/// Struct that represents a reference from the entities index to an item
+#[derive(Debug, Clone, Serialize)]
+pub struct ItemReference {
+    pub position: usize,
+    pub anchor: String,
+}
+
+/// Struct that represents an entity of the entities index
+#[derive(Debug, Clone, Serialize)]
+pub struct EntityIndexEntry {
+    pub name: String,
+    pub kind: String,
+    pub items: Vec<ItemReference>,
+}
+
+/// Struct that represents the relevance of the items of a channel
+#[derive(Debug, Clone, Serialize)]
+pub struct ChannelRelevance {
+    pub channel: String,
+    pub channel_color: String,
+    pub items: usize,
+    pub total: u64,
+    /// Average relevance as the relevance report has always printed it
+    pub average: String,
+    /// Average relevance with two decimals
+    pub average_rounded: String,
+}
+
+/// Struct that represents a section of a sectioned dossier
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportSection {
+    /// Position of the section in the report starting at 1
+    pub position: usize,
+    pub name: String,
+    /// Markdown anchor of the name
+    pub anchor: String,
+    pub summary: String,
+    pub item_count: usize,
+    /// Number of items of the section before keeping its top-k
+    pub total_items: usize,
+    /// Items of the section, they are also in the items of the report
+    pub items: Vec<ReportItem>,
+}
+
+/// Struct with the data passed to the report templates
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportModel {
+    pub title: String,
+    pub date: String,
+    pub item_count: usize,
+    pub items: Vec<ReportItem>,
+    pub entities: Vec<EntityIndexEntry>,
+    pub channels: Vec<ChannelRelevance>,
+    /// Sections of the items, empty when the report is not sectioned
+    pub sections: Vec<ReportSection>,
+    /// Relevance statistics, only computed for the relevance reports
+    pub statistics: Option<RelevanceStatistics>,
+}
+
+impl ReportModel {
+    /// Function that builds the model of a report with the news items in
+    /// the order passed
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::template::ReportModel;
+    ///
+    /// let news_item = NewsItem {
+    ///     channel: "El País".to_string(),
+    ///     title: "La inflación baja".to_string(),
+    ///     relevance: Some(10),
+    ///     ..Default::default()
+    /// };
+    /// let model = ReportModel::new("Dossier", &[news_item]);
+    /// assert_eq!(model.items[0].position, 1);
+    /// assert_eq!(model.items[0].anchor, "#la-inflaci-n-baja");
+    /// assert_eq!(model.channels[0].average, "10");
+    /// assert_eq!(model.channels[0].average_rounded, "10.00");
+    /// ```
+    pub fn new(title: &str, news_items: &[NewsItem]) -> ReportModel {
+        let items = news_items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| ReportItem {
+                position: i + 1,
+                anchor: generate_anchor(&item.title),
+                channel: item.channel.clone(),
+                channel_color: channel_color(&item.channel),
+                title: item.title.clone(),
+                link: item.link.clone(),
+                description: item.description.clone(),
+                short_description: item.description.chars().take(50).collect(),
+                creators: item.creators.clone(),
+                pub_date: item.pub_date.clone(),
+                categories: item.categories.clone(),
+                keywords: item.keywords.clone(),
+                clean_content: item.clean_content.clone(),
+                error: item.error.as_ref().map(|error| error.as_string()),
+                relevance: item.relevance.unwrap_or_default(),
+                relevance_breakdown: String::new(),
+                entities: item.entities.clone(),
+            })
+            .collect::<Vec<ReportItem>>();
+
+        let entities = entity_index(news_items)
+            .into_iter()
+            .map(|(entity, positions)| EntityIndexEntry {
+                name: entity.name,
+                kind: entity.kind.as_str().to_string(),
+                items: positions
+                    .into_iter()
+                    .map(|i| ItemReference {
+                        position: i + 1,
+                        anchor: items[i].anchor.clone(),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        let channels = relevance_per_channel(news_items)
+            .into_iter()
+            .map(|(channel, (total, count))| ChannelRelevance {
+                channel_color: channel_color(&channel),
+                channel,
+                items: count,
+                total: total as u64,
+                average: (total / count as f64).to_string(),
+                average_rounded: format!("{:.2}", total / count as f64),
+            })
+            .collect();
+
+        ReportModel {
+            title: title.to_string(),
+            date: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            item_count: news_items.len(),
+            items,
+            entities,
+            channels,
+            sections: Vec::new(),
+            statistics: None,
+        }
+    }
+
+    /// Function that builds the model of a report with the news items of the
+    /// sections, numbered in the order of the sections
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::section::{section_news_items, Grouping, SectionOptions};
+    /// use hemeroteca::template::ReportModel;
+    ///
+    /// let item = |channel: &str| NewsItem {
+    ///     channel: channel.to_string(),
+    ///     title: channel.to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let options = SectionOptions { grouping: Grouping::Channel, top_k: 5, topics: vec![] };
+    /// let sections = section_news_items(&[item("El País"), item("20 Minutos")], &options);
+    /// let model = ReportModel::sectioned("Dossier", &sections);
+    /// assert_eq!(model.item_count, 2);
+    /// assert_eq!(model.sections[1].items[0].position, 2);
+    /// ```
+    pub fn sectioned(title: &str, sections: &[NewsSection]) -> ReportModel {
+        let mut model = ReportModel::new(title, &flatten_sections(sections));
+        let mut items = model.items.iter();
+        model.sections = sections
+            .iter()
+            .enumerate()
+            .map(|(i, section)| ReportSection {
+                position: i + 1,
+                name: section.name.clone(),
+                anchor: generate_anchor(&section.name),
+                summary: section.summary.clone(),
+                item_count: section.news_items.len(),
+                total_items: section.total_items,
+                items: items.by_ref().take(section.news_items.len()).cloned().collect(),
+            })
+            .collect();
+        model
+    }
+
+    /// Function that fills the relevance breakdown of the items, it scores
+    /// them again so it is only computed for the templates that show it
+    pub fn with_relevance_breakdown(mut self, news_items: &[NewsItem]) -> ReportModel {
+        for (item, news_item) in self.items.iter_mut().zip(news_items.iter()) {
+            let relevance = score_relevance(news_item);
+            item.relevance_breakdown = if relevance.error {
+                "Error in the news item".to_string()
+            } else {
+                format!(
+                    "core {} {} + clean content {}",
+                    relevance.relevance_core, relevance.explanation, relevance.relevance_content
+                )
+            };
+        }
+        // The items of the sections are copies of the items of the report
+        let mut items = self.items.iter();
+        for section in self.sections.iter_mut() {
+            section.items = items.by_ref().take(section.items.len()).cloned().collect();
+        }
+        self
+    }
+
+    /// Function that fills the relevance statistics of the news items: the
+    /// percentiles, histograms, scoring components and error rates per channel
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::template::ReportModel;
+    ///
+    /// let news_item = NewsItem {
+    ///     channel: "El País".to_string(),
+    ///     relevance: Some(10),
+    ///     ..Default::default()
+    /// };
+    /// let news_items = vec![news_item];
+    /// let model = ReportModel::new("Relevance Report", &news_items).with_statistics(&news_items);
+    /// assert_eq!(model.statistics.unwrap().channels[0].median, 10);
+    /// ```
+    pub fn with_statistics(mut self, news_items: &[NewsItem]) -> ReportModel {
+        self.statistics = Some(relevance_statistics(news_items));
+        self
+    }
+}
+
+/// Struct that holds the report templates. The Markdown templates are
+/// rendered as is and the HTML ones escape the values
+pub struct Templates {
+    markdown: Handlebars<'static>,
+    html: Handlebars<'static>,
+}
+
+impl Templates {
+    /// Function that returns the default templates
+    pub fn defaults() -> Templates {
+        let mut markdown = Handlebars::new();
+        markdown.register_escape_fn(handlebars::no_escape);
+        let mut templates = Templates {
+            markdown,
+            html: Handlebars::new(),
+        };
+        for (name, template) in DEFAULT_TEMPLATES.iter() {
+            templates
+                .register(name, template)
+                .expect("The default templates should be valid");
+        }
+        templates
+    }
+
+    /// Function that returns the default templates overridden by the files
+    /// `<template>.hbs` of a folder. The invalid files are logged and skipped
+    pub fn from_folder(folder: &Path) -> Templates {
+        let mut templates = Templates::defaults();
+        for (name, _) in DEFAULT_TEMPLATES.iter() {
+            let file = folder.join(format!("{}.hbs", name));
+            if !file.exists() {
+                continue;
+            }
+            let registered = std::fs::read_to_string(&file)
+                .map_err(|err| err.into())
+                .and_then(|template| templates.register(name, &template));
+            match registered {
+                Ok(()) => log::info!("Using the template {}", file.display()),
+                Err(err) => log::error!("Could not read the template {}. ERROR: {}", file.display(), err),
+            }
+        }
+        templates
+    }
+
+    /// Function that returns the default templates overridden by the ones of
+    /// ~/.hemeroteca/templates
+    pub fn load() -> Templates {
+        match dirs::home_dir() {
+            Some(home_dir) => {
+                Templates::from_folder(&home_dir.join(DEFAULT_CONFIG_FOLDER_NAME).join(DEFAULT_TEMPLATES_FOLDER_NAME))
+            }
+            None => Templates::defaults(),
+        }
+    }
+
+    /// Function that renders a template with a report model
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::template::{ReportModel, Templates};
+    ///
+    /// let news_item = NewsItem {
+    ///     title: "La inflación baja".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// let model = ReportModel::new("Dossier", &[news_item]);
+    /// let report = Templates::defaults().render("dossier.md", &model).unwrap();
+    /// assert!(report.starts_with("# Dossier"));
+    /// ```
+    pub fn render(&self, name: &str, model: &ReportModel) -> Result<String, Box<dyn Error>> {
+        Ok(self.registry(name).render(name, model)?)
+    }
+
+    // Function that registers a template in the registry of its format, the
+    // stylesheet is a partial of the HTML templates
+    fn register(&mut self, name: &str, template: &str) -> Result<(), Box<dyn Error>> {
+        if name == "style.css" {
+            self.html.register_partial(name, template)?;
+        } else if name.ends_with(".html") {
+            self.html.register_template_string(name, template)?;
+        } else {
+            self.markdown.register_template_string(name, template)?;
+        }
+        Ok(())
+    }
+
+    // Function that returns the registry of a template
+    fn registry(&self, name: &str) -> &Handlebars<'static> {
+        if name.ends_with(".html") {
+            &self.html
+        } else {
+            &self.markdown
+        }
+    }
+}
+
+/// Function that renders a report with the templates of ~/.hemeroteca/templates
+/// falling back to the default template if the custom one fails
+pub fn render_report(name: &str, model: &ReportModel) -> String {
+    TEMPLATES.render(name, model).unwrap_or_else(|err| {
+        log::error!("Could not render the template {}, using the default one. ERROR: {}", name, err);
+        Templates::defaults()
+            .render(name, model)
+            .expect("The default templates should render")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::EntityKind;
+
+    fn news_items() -> Vec<NewsItem> {
+        vec![
+            NewsItem {
+                channel: "El País".to_string(),
+                title: "El Gobierno aprueba la reforma".to_string(),
+                link: "https://www.acme.es/1.html".to_string(),
+                pub_date: Some("Mon, 01 Jan 2024 10:00:00 +0000".to_string()),
+                clean_content: Some("Contenido <b>".to_string()),
+                relevance: Some(10),
+                entities: vec![Entity {
+                    name: "Banco de España".to_string(),
+                    kind: EntityKind::Organization,
+                }],
+                ..Default::default()
+            },
+            NewsItem {
+                channel: "20 Minutos".to_string(),
+                title: "Fútbol".to_string(),
+                link: "https://www.acme.es/2.html".to_string(),
+                relevance: Some(1),
+                ..Default::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_default_templates() {
+        let news_items = news_items();
+        let model = ReportModel::new("Dossier", &news_items);
+        let templates = Templates::defaults();
+
+        let dossier = templates.render("dossier.md", &model).unwrap();
+        assert!(dossier.contains("1. [El Gobierno aprueba la reforma](#el-gobierno-aprueba-la-reforma)\n2. [Fútbol](#f-tbol)\n"));
+        assert!(dossier.contains("- **Banco de España** (Organization): [1](#el-gobierno-aprueba-la-reforma)\n"));
+        assert!(dossier.contains("- **Publish Date:** Mon, 01 Jan 2024 10:00:00 +0000\n"));
+        assert!(dossier.contains("- **Keywords:** N/A\n"));
+        assert!(dossier.contains("#### Clean Content\nContenido <b>\n"));
+
+        let relevance = templates.render("relevance.md", &model).unwrap();
+        assert!(relevance.contains("- **El País:** Items: 1 Total: 10 Average: 10\n"));
+        assert!(relevance.contains("1. (1) [20 Minutos] Fútbol\n"));
+        assert!(!relevance.contains("## Relevance Statistics"));
+        let statistics = templates.render("relevance.md", &model.clone().with_statistics(&news_items)).unwrap();
+        assert!(statistics.contains("| El País | 1 | 0 | 0.0% | 10 | 10 | 10 | 10 | 10 | 10 | 10.00 |\n"));
+        assert!(statistics.contains("| Channel | 0-2 | 3-5 | 6-8 | 9-11 | 12+ |\n"));
+        assert!(statistics.contains("| 20 Minutos | 1 | 0 | 0 | 0 | 0 |\n"));
+
+        let log = templates.render("news_items.md", &model).unwrap();
+        assert!(log.contains("- **Entities:** Banco de España (Organization)\n"));
+        assert!(log.contains("- **Entities:** N/A\n"));
+
+        // The HTML templates escape the values
+        let html = templates.render("dossier.html", &model).unwrap();
+        assert!(html.contains("Contenido &lt;b&gt;"));
+        assert!(html.contains("box-sizing: border-box"));
+    }
+
+    #[test]
+    fn test_templates_from_folder() {
+        let folder = Path::new(".templates");
+        std::fs::create_dir_all(folder).unwrap();
+        std::fs::write(folder.join("dossier.md.hbs"), "{{#each items}}{{position}}:{{channel}};{{/each}}").unwrap();
+        std::fs::write(folder.join("relevance.md.hbs"), "{{#each items}").unwrap();
+        let templates = Templates::from_folder(folder);
+        std::fs::remove_dir_all(folder).unwrap();
+
+        let model = ReportModel::new("Dossier", &news_items());
+        assert_eq!(templates.render("dossier.md", &model).unwrap(), "1:El País;2:20 Minutos;");
+
+        // The invalid template is skipped and the default one is kept
+        assert!(templates.render("relevance.md", &model).unwrap().starts_with("# Dossier"));
+    }
+
+    #[test]
+    fn test_render_sectioned_templates() {
+        let sections = vec![
+            NewsSection {
+                name: "Economía".to_string(),
+                summary: "1 item from El País".to_string(),
+                total_items: 3,
+                news_items: vec![news_items()[0].clone()],
+            },
+            NewsSection {
+                name: "Deportes".to_string(),
+                summary: "1 item from 20 Minutos".to_string(),
+                total_items: 1,
+                news_items: vec![news_items()[1].clone()],
+            },
+        ];
+        let model = ReportModel::sectioned("Dossier", &sections).with_relevance_breakdown(&flatten_sections(&sections));
+        assert!(model.sections[1].items[0].relevance_breakdown.starts_with("core "));
+        let templates = Templates::defaults();
+
+        let dossier = templates.render("dossier.md", &model).unwrap();
+        assert!(dossier.contains("### [Economía](#econom-a)\n1. [El Gobierno aprueba la reforma](#el-gobierno-aprueba-la-reforma)\n"));
+        assert!(dossier.contains("## Economía\n\n> 1 item from El País (1 of 3 items)\n\n---\n### El Gobierno aprueba la reforma\n"));
+        let economy = dossier.find("## Economía\n").unwrap();
+        let sports = dossier.find("## Deportes\n").unwrap();
+        let football = dossier.find("### Fútbol\n").unwrap();
+        assert!(economy < sports && sports < football);
+
+        let html = templates.render("dossier.html", &model).unwrap();
+        assert!(html.contains("<section id=\"section-1\">\n<h2>Economía</h2>\n<p class=\"summary\">1 item from El País (1 of 3 items)</p>"));
+        assert!(html.contains("<article id=\"item-2\">"));
+    }
+
+    #[test]
+    fn test_relevance_breakdown() {
+        let news_items = news_items();
+        let model = ReportModel::new("Dossier", &news_items);
+        assert!(model.items[0].relevance_breakdown.is_empty());
+
+        let model = model.with_relevance_breakdown(&news_items);
+        assert!(model.items[0].relevance_breakdown.starts_with("core "));
+    }
+}
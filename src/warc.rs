@@ -0,0 +1,427 @@
+/// Module for recording the fetched responses in ISO 28500 WARC files
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use once_cell::sync::OnceCell;
+use reqwest::Response;
+
+/// Version written in the records
+const WARC_VERSION: &str = "WARC/1.1";
+
+/// Response headers that do not describe the recorded body because reqwest
+/// already decoded it
+const SKIPPED_HEADERS: [&str; 2] = ["transfer-encoding", "content-encoding"];
+
+/// Recorder of the run, every fetched response is written to it once set
+static RECORDER: OnceCell<Mutex<WarcWriter>> = OnceCell::new();
+
+/// Struct that writes WARC records, each one compressed as a gzip member so
+/// the file can be read by the standard web-archive tools
+pub struct WarcWriter {
+    path: PathBuf,
+    file: BufWriter<File>,
+}
+
+/// Struct that represents a WARC record read from a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WarcRecord {
+    pub headers: Vec<(String, String)>,
+    pub block: Vec<u8>,
+}
+
+/// Function that returns a new record id
+fn record_id() -> String {
+    let bytes: [u8; 16] = rand::random();
+    let hex = bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+    // Random (version 4) UUID
+    format!(
+        "<urn:uuid:{}-{}-4{}-{:x}{}-{}>",
+        &hex[0..8],
+        &hex[8..12],
+        &hex[13..16],
+        8 | (bytes[8] & 0x3),
+        &hex[17..20],
+        &hex[20..32]
+    )
+}
+
+impl WarcWriter {
+    /// Function that creates a WARC file and writes its warcinfo record
+    pub fn create(path: &Path) -> std::io::Result<WarcWriter> {
+        let mut writer = WarcWriter {
+            path: path.to_path_buf(),
+            file: BufWriter::new(File::create(path)?),
+        };
+        let info = format!(
+            "software: hemeroteca/{}\r\nformat: WARC File Format 1.1\r\nconformsTo: http://iipc.github.io/warc-specifications/specifications/warc-format/warc-1.1/\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        let filename = path.file_name().map(|name| name.to_string_lossy().to_string()).unwrap_or_default();
+        writer.write_record(
+            "warcinfo",
+            &[("WARC-Filename", filename.as_str())],
+            "application/warc-fields",
+            info.as_bytes(),
+        )?;
+        Ok(writer)
+    }
+
+    /// Function that returns the path of the WARC file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Function that writes a record and returns its id
+    pub fn write_record(
+        &mut self,
+        record_type: &str,
+        headers: &[(&str, &str)],
+        content_type: &str,
+        block: &[u8],
+    ) -> std::io::Result<String> {
+        let id = record_id();
+        let date = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+
+        let mut head = format!(
+            "{}\r\nWARC-Type: {}\r\nWARC-Record-ID: {}\r\nWARC-Date: {}\r\n",
+            WARC_VERSION, record_type, id, date
+        );
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Type: {}\r\nContent-Length: {}\r\n\r\n", content_type, block.len()));
+
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(head.as_bytes())?;
+        encoder.write_all(block)?;
+        encoder.write_all(b"\r\n\r\n")?;
+        self.file.write_all(&encoder.finish()?)?;
+        // The recorder lives until the end of the process, flush every record
+        self.file.flush()?;
+        Ok(id)
+    }
+
+    /// Function that writes the request and response records of a fetched url
+    pub fn write_exchange(&mut self, url: &str, response_head: &str, body: &[u8]) -> std::io::Result<()> {
+        let mut response = response_head.as_bytes().to_vec();
+        response.extend_from_slice(body);
+        let response_id = self.write_record(
+            "response",
+            &[("WARC-Target-URI", url)],
+            "application/http; msgtype=response",
+            &response,
+        )?;
+
+        let request = http_request_head(url);
+        self.write_record(
+            "request",
+            &[("WARC-Target-URI", url), ("WARC-Concurrent-To", response_id.as_str())],
+            "application/http; msgtype=request",
+            request.as_bytes(),
+        )?;
+        Ok(())
+    }
+}
+
+/// Function that returns the head of the GET request sent for a url
+fn http_request_head(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(url) => {
+            let target = match url.query() {
+                Some(query) => format!("{}?{}", url.path(), query),
+                None => url.path().to_string(),
+            };
+            format!(
+                "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: */*\r\n\r\n",
+                target,
+                url.host_str().unwrap_or_default()
+            )
+        }
+        Err(_) => format!("GET {} HTTP/1.1\r\nAccept: */*\r\n\r\n", url),
+    }
+}
+
+/// Function that returns the status line and headers of a response
+fn http_response_head(response: &Response) -> String {
+    let mut head = format!("{:?} {}\r\n", response.version(), response.status());
+    for (name, value) in response.headers() {
+        if SKIPPED_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        head.push_str(&format!("{}: {}\r\n", name, String::from_utf8_lossy(value.as_bytes())));
+    }
+    head.push_str("\r\n");
+    head
+}
+
+/// Function that starts recording the fetched responses of the run in a WARC
+/// file and returns an error if the run is already recording
+pub fn start_recording(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let writer = WarcWriter::create(path)?;
+    RECORDER
+        .set(Mutex::new(writer))
+        .map_err(|_| "The run is already recording a WARC file")?;
+    log::info!("Recording the fetched responses in {}", path.display());
+    Ok(())
+}
+
+/// Function that returns true if the run is recording a WARC file
+pub fn is_recording() -> bool {
+    RECORDER.get().is_some()
+}
+
+/// Function that records a response if the run is recording
+fn record_response(url: &str, head: &str, body: &[u8]) {
+    if let Some(recorder) = RECORDER.get() {
+        let mut recorder = recorder.lock().unwrap_or_else(|err| err.into_inner());
+        if let Err(err) = recorder.write_exchange(url, head, body) {
+            log::error!("Could not record {} in {}. ERROR: {}", url, recorder.path().display(), err);
+        }
+    }
+}
+
+/// Function that reads the body of a response as bytes recording it if the
+/// run is recording. The response is recorded under the requested url, not
+/// the one after the redirects, as the items are replayed by their links
+pub async fn read_response_bytes(url: &str, response: Response) -> reqwest::Result<Vec<u8>> {
+    if !is_recording() {
+        return Ok(response.bytes().await?.to_vec());
+    }
+    let head = http_response_head(&response);
+    let body = response.bytes().await?.to_vec();
+    record_response(url, &head, &body);
+    Ok(body)
+}
+
+/// Function that reads the body of a response as text recording it if the
+/// run is recording. The body is decoded with the charset of the response as
+/// `Response::text` does
+pub async fn read_response_text(url: &str, response: Response) -> reqwest::Result<String> {
+    if !is_recording() {
+        return response.text().await;
+    }
+    let charset = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(';').find_map(|param| param.trim().strip_prefix("charset=")))
+        .map(|charset| charset.trim_matches('"').to_string());
+    let body = read_response_bytes(url, response).await?;
+    Ok(decode(&body, charset.as_deref()))
+}
+
+/// Function that decodes a body with a charset, UTF-8 by default
+fn decode(body: &[u8], charset: Option<&str>) -> String {
+    let encoding = charset
+        .and_then(|charset| encoding_rs::Encoding::for_label(charset.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    encoding.decode(body).0.into_owned()
+}
+
+impl WarcRecord {
+    /// Function that returns the value of a header (case insensitive)
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Function that returns the body of the HTTP message of a request or
+    /// response record
+    pub fn http_body(&self) -> &[u8] {
+        match self.block.windows(4).position(|window| window == b"\r\n\r\n") {
+            Some(position) => &self.block[position + 4..],
+            None => &[],
+        }
+    }
+
+    /// Function that returns the text of the HTTP message body decoded with
+    /// the charset of its Content-Type header
+    pub fn http_text(&self) -> String {
+        let head_end = self.block.len() - self.http_body().len();
+        let head = String::from_utf8_lossy(&self.block[..head_end]);
+        let charset = head
+            .lines()
+            .find(|line| line.to_lowercase().starts_with("content-type:"))
+            .and_then(|line| line.split(';').find_map(|param| param.trim().strip_prefix("charset=")))
+            .map(|charset| charset.trim_matches('"').to_string());
+        decode(self.http_body(), charset.as_deref())
+    }
+}
+
+/// Function that reads a record from a WARC stream or None at the end
+fn read_record(reader: &mut impl BufRead) -> std::io::Result<Option<WarcRecord>> {
+    let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string());
+
+    // Skip the blank lines between records
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        if !line.trim().is_empty() {
+            break;
+        }
+    }
+    if !line.starts_with("WARC/") {
+        return Err(invalid("Missing WARC version line"));
+    }
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid("Unexpected end of the record headers"));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let (name, value) = header.split_once(':').ok_or_else(|| invalid("Invalid record header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let length = headers
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("Content-Length"))
+        .and_then(|(_, value)| value.parse::<usize>().ok())
+        .ok_or_else(|| invalid("Missing Content-Length"))?;
+    let mut block = vec![0; length];
+    reader.read_exact(&mut block)?;
+
+    Ok(Some(WarcRecord { headers, block }))
+}
+
+/// Function that reads the records of a WARC file, compressed (.warc.gz) or
+/// not
+pub fn read_warc(path: &Path) -> std::io::Result<Vec<WarcRecord>> {
+    let file = File::open(path)?;
+    let mut reader: Box<dyn BufRead> = if path.extension().is_some_and(|extension| extension == "gz") {
+        Box::new(BufReader::new(MultiGzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    let mut records = Vec::new();
+    while let Some(record) = read_record(&mut reader)? {
+        records.push(record);
+    }
+    Ok(records)
+}
+
+/// Function that returns the url and body text of the response records of a
+/// WARC file, in the order they were fetched
+pub fn read_warc_responses(path: &Path) -> std::io::Result<Vec<(String, String)>> {
+    Ok(read_warc(path)?
+        .into_iter()
+        .filter(|record| record.header("WARC-Type") == Some("response"))
+        .filter_map(|record| {
+            let url = record.header("WARC-Target-URI")?.to_string();
+            Some((url, record.http_text()))
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_id_is_an_uuid() {
+        let id = record_id();
+        assert_eq!(id.len(), "<urn:uuid:>".len() + 36);
+        let uuid = &id[10..46];
+        assert_eq!(&uuid[14..15], "4");
+        assert!(["8", "9", "a", "b"].contains(&&uuid[19..20]));
+        assert_ne!(id, record_id());
+    }
+
+    #[test]
+    fn test_write_and_read_warc() {
+        let path = Path::new(".test_write_and_read.warc.gz");
+        let mut writer = WarcWriter::create(path).unwrap();
+        let head = "HTTP/1.1 200 OK\r\ncontent-type: text/html; charset=iso-8859-1\r\n\r\n";
+        writer
+            .write_exchange("https://www.acme.es/a.html?id=1", head, b"<p>Espa\xf1a</p>")
+            .unwrap();
+        drop(writer);
+
+        let records = read_warc(path).unwrap();
+        let responses = read_warc_responses(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].header("WARC-Type"), Some("warcinfo"));
+        assert_eq!(records[1].header("warc-type"), Some("response"));
+        assert_eq!(records[2].header("WARC-Type"), Some("request"));
+        assert_eq!(records[2].header("WARC-Concurrent-To"), records[1].header("WARC-Record-ID"));
+        assert!(records[2].block.starts_with(b"GET /a.html?id=1 HTTP/1.1\r\nHost: www.acme.es\r\n"));
+        assert_eq!(records[1].http_body(), b"<p>Espa\xf1a</p>");
+
+        assert_eq!(
+            responses,
+            vec![("https://www.acme.es/a.html?id=1".to_string(), "<p>España</p>".to_string())]
+        );
+    }
+
+    // Function that starts a local server answering two requests, the first
+    // one with a redirect to /final.html, and returns its port
+    fn redirecting_server() -> u16 {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                let mut writer = stream.try_clone().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let response = if request_line.starts_with("GET /final.html") {
+                    "HTTP/1.1 200 OK\r\ncontent-type: text/html\r\ncontent-length: 15\r\nconnection: close\r\n\r\n<p>Content</p>\n".to_string()
+                } else {
+                    "HTTP/1.1 301 Moved Permanently\r\nlocation: /final.html\r\ncontent-length: 0\r\nconnection: close\r\n\r\n".to_string()
+                };
+                writer.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_record_redirected_response() {
+        let path = Path::new(".test_record_redirected.warc.gz");
+        start_recording(path).unwrap();
+        assert!(is_recording());
+
+        let url = format!("http://127.0.0.1:{}/article.html?utm_source=rss", redirecting_server());
+        let response = reqwest::get(&url).await.unwrap();
+        assert!(response.url().path().ends_with("/final.html"));
+        let content = read_response_text(&url, response).await.unwrap();
+        assert_eq!(content, "<p>Content</p>\n");
+
+        // The response is recorded under the requested url
+        let responses = read_warc_responses(path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert!(responses.contains(&(url, "<p>Content</p>\n".to_string())));
+    }
+
+    #[test]
+    fn test_read_invalid_warc() {
+        let path = Path::new(".test_read_invalid.warc");
+        std::fs::write(path, "HTTP/1.1 200 OK\r\n\r\n").unwrap();
+        let result = read_warc(path);
+        std::fs::remove_file(path).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,271 @@
+/// Module for watchlists and the alerts raised by them
+use std::error::Error;
+use std::io::Write;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::common::{NewsItem, DEFAULT_CONFIG_FOLDER_NAME, DEFAULT_WATCHLISTS_FILE};
+use crate::query::Query;
+
+/// Exit code used by the report binary when any watchlist raised an alert
+pub const ALERTS_EXIT_CODE: i32 = 3;
+
+/// Struct that wraps a Regex so it can be read from a watchlists file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Pattern(Regex);
+
+impl TryFrom<String> for Pattern {
+    type Error = regex::Error;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        Ok(Pattern(Regex::new(&pattern)?))
+    }
+}
+
+impl From<Pattern> for String {
+    fn from(pattern: Pattern) -> Self {
+        pattern.0.as_str().to_string()
+    }
+}
+
+/// Enum that represents a watchlist rule. Rules are read from JSON, e.g.
+/// `{"all": [{"entity": "Banco de España"}, {"not": {"phrase": "fútbol"}}]}`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Rule {
+    /// The item mentions the entity (case insensitive)
+    Entity(String),
+    /// The item contains the phrase as whole words (case insensitive)
+    Phrase(String),
+    /// The item matches the regular expression
+    Regex(Pattern),
+    /// The item matches the query, e.g. `title:ley AND NOT deportes`
+    Query(Query),
+    /// All the rules match
+    All(Vec<Rule>),
+    /// Any of the rules match
+    Any(Vec<Rule>),
+    /// The rule does not match
+    Not(Box<Rule>),
+}
+
+impl Rule {
+    /// Function that returns the descriptions of the rules matching the
+    /// NewsItem or None if the rule does not match
+    fn evaluate(&self, news_item: &NewsItem, text: &str) -> Option<Vec<String>> {
+        match self {
+            Rule::Entity(name) => news_item.has_entity(name).then(|| vec![format!("entity: {}", name)]),
+            Rule::Phrase(phrase) => contains_phrase(text, phrase).then(|| vec![format!("phrase: {}", phrase)]),
+            Rule::Regex(pattern) => pattern.0.is_match(text).then(|| vec![format!("regex: {}", pattern.0.as_str())]),
+            Rule::Query(query) => query.matches(news_item).then(|| vec![format!("query: {}", query)]),
+            Rule::All(rules) => rules
+                .iter()
+                .map(|rule| rule.evaluate(news_item, text))
+                .collect::<Option<Vec<Vec<String>>>>()
+                .map(|matches| matches.concat()),
+            Rule::Any(rules) => {
+                let matches = rules
+                    .iter()
+                    .filter_map(|rule| rule.evaluate(news_item, text))
+                    .collect::<Vec<Vec<String>>>();
+                (!matches.is_empty()).then(|| matches.concat())
+            }
+            Rule::Not(rule) => match rule.evaluate(news_item, text) {
+                Some(_) => None,
+                None => Some(vec![]),
+            },
+        }
+    }
+
+    /// Function that returns true if the rule matches the NewsItem
+    ///
+    /// Example:
+    /// ```
+    /// use hemeroteca::prelude::*;
+    /// use hemeroteca::watchlist::Rule;
+    ///
+    /// let rule: Rule = serde_json::from_str(r#"{"any": [{"phrase": "ley"}, {"regex": "^IPC"}]}"#).unwrap();
+    /// let news_item = NewsItem {
+    ///     title: "Nueva ley de vivienda".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// assert!(rule.matches(&news_item));
+    /// let news_item = NewsItem {
+    ///     title: "Una leyenda del rock".to_string(),
+    ///     ..Default::default()
+    /// };
+    /// assert!(!rule.matches(&news_item));
+    /// ```
+    pub fn matches(&self, news_item: &NewsItem) -> bool {
+        self.evaluate(news_item, &searchable_text(news_item)).is_some()
+    }
+}
+
+/// Struct that represents a named watchlist
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Watchlist {
+    pub name: String,
+    pub rule: Rule,
+}
+
+/// Struct that represents an alert raised by a watchlist on a NewsItem
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Alert {
+    pub watchlist: String,
+    pub channel: String,
+    pub title: String,
+    pub link: String,
+    pub pub_date: Option<String>,
+    pub matches: Vec<String>,
+}
+
+/// Function that returns the text of a NewsItem that rules are evaluated against
+fn searchable_text(news_item: &NewsItem) -> String {
+    [
+        news_item.title.as_str(),
+        news_item.description.as_str(),
+        news_item.categories.as_deref().unwrap_or(""),
+        news_item.keywords.as_deref().unwrap_or(""),
+        news_item.clean_content.as_deref().unwrap_or(""),
+    ]
+    .join("\n")
+}
+
+/// Function that returns true if a text contains a phrase as whole words
+fn contains_phrase(text: &str, phrase: &str) -> bool {
+    let pattern = format!(r"(?i)\b{}\b", regex::escape(phrase.trim()));
+    Regex::new(&pattern).map(|re| re.is_match(text)).unwrap_or(false)
+}
+
+/// Function that reads the watchlists from a JSON file
+pub fn read_watchlists(file: &str) -> Result<Vec<Watchlist>, Box<dyn Error>> {
+    let content = std::fs::read_to_string(file)?;
+    let watchlists: Vec<Watchlist> = serde_json::from_str(&content)?;
+    Ok(watchlists)
+}
+
+/// Function that reads the watchlists from ~/.hemeroteca/watchlists.json and
+/// returns an empty vector if the file does not exist
+pub fn read_default_watchlists() -> Vec<Watchlist> {
+    let Some(home_dir) = dirs::home_dir() else {
+        return Vec::new();
+    };
+    let file = home_dir.join(DEFAULT_CONFIG_FOLDER_NAME).join(DEFAULT_WATCHLISTS_FILE);
+    if !file.exists() {
+        return Vec::new();
+    }
+    match read_watchlists(file.to_str().unwrap()) {
+        Ok(watchlists) => watchlists,
+        Err(err) => {
+            log::error!("Could not read the watchlists from {}. ERROR: {}", file.display(), err);
+            Vec::new()
+        }
+    }
+}
+
+/// Function that evaluates the watchlists against a slice of NewsItems and
+/// returns the alerts raised
+pub fn evaluate_watchlists(watchlists: &[Watchlist], news_items: &[NewsItem]) -> Vec<Alert> {
+    let mut alerts = Vec::new();
+    for news_item in news_items {
+        let text = searchable_text(news_item);
+        for watchlist in watchlists {
+            if let Some(matches) = watchlist.rule.evaluate(news_item, &text) {
+                log::warn!("Watchlist '{}' matched: {}", watchlist.name, news_item.title);
+                alerts.push(Alert {
+                    watchlist: watchlist.name.clone(),
+                    channel: news_item.channel.clone(),
+                    title: news_item.title.clone(),
+                    link: news_item.link.clone(),
+                    pub_date: news_item.pub_date.clone(),
+                    matches,
+                });
+            }
+        }
+    }
+    alerts
+}
+
+/// Function that writes the alerts to a JSON file and returns a Result
+pub fn write_alerts_to_file(alerts: &[Alert], file: &str) -> Result<(), Box<dyn Error>> {
+    let mut file = std::fs::File::create(file)?;
+    writeln!(file, "{}", serde_json::to_string_pretty(alerts)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::entities::{Entity, EntityKind};
+
+    fn news_item() -> NewsItem {
+        NewsItem {
+            channel: "Other".to_string(),
+            title: "El Banco de España sube los tipos".to_string(),
+            link: "https://www.acme.es/economia/tipos.html".to_string(),
+            description: "La inflación sigue alta".to_string(),
+            categories: Some("economía,banca".to_string()),
+            entities: vec![Entity {
+                name: "Banco de España".to_string(),
+                kind: EntityKind::Organization,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_read_watchlists() {
+        let file = ".watchlists.json";
+        std::fs::write(
+            file,
+            r#"[
+                {"name": "banca", "rule": {"all": [{"entity": "banco de españa"}, {"not": {"phrase": "fútbol"}}]}},
+                {"name": "economía", "rule": {"query": "(economía OR inflación) AND NOT deportes"}}
+            ]"#,
+        )
+        .unwrap();
+        let watchlists = read_watchlists(file).unwrap();
+        std::fs::remove_file(file).unwrap();
+
+        assert_eq!(watchlists.len(), 2);
+        assert_eq!(watchlists[0].name, "banca");
+        assert!(watchlists[0].rule.matches(&news_item()));
+        assert!(watchlists[1].rule.matches(&news_item()));
+    }
+
+    #[test]
+    fn test_read_watchlists_with_bad_regex() {
+        let result: Result<Rule, _> = serde_json::from_str(r#"{"regex": "(unclosed"}"#);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_evaluate_watchlists() {
+        let watchlists = vec![
+            Watchlist {
+                name: "inflación".to_string(),
+                rule: Rule::All(vec![
+                    Rule::Phrase("inflación".to_string()),
+                    Rule::Regex(Pattern::try_from(r"(?i)tipos?\b".to_string()).unwrap()),
+                ]),
+            },
+            Watchlist {
+                name: "deportes".to_string(),
+                rule: Rule::Any(vec![Rule::Phrase("fútbol".to_string()), Rule::Entity("Real Madrid".to_string())]),
+            },
+            Watchlist {
+                name: "no deportes".to_string(),
+                rule: Rule::Not(Box::new(Rule::Phrase("fútbol".to_string()))),
+            },
+        ];
+
+        let alerts = evaluate_watchlists(&watchlists, &[news_item()]);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[0].watchlist, "inflación");
+        assert_eq!(alerts[0].matches, vec!["phrase: inflación", r"regex: (?i)tipos?\b"]);
+        assert_eq!(alerts[1].watchlist, "no deportes");
+        assert!(alerts[1].matches.is_empty());
+    }
+}